@@ -0,0 +1,157 @@
+use crate::dfa::{full_match_dfa, is_prefix_covered, is_subset};
+use crate::{Lexer, Token};
+use std::fmt;
+
+/// A likely mistake in a lexer's patterns, found by [`LexerBuilder::finish`] and available from
+/// [`Lexer::warnings`].
+///
+/// [`LexerBuilder::finish`]: crate::LexerBuilder::finish
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct LexerWarning {
+    pub kind: LexerWarningKind,
+    /// A description of the problem, using token names.
+    pub message: String,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum LexerWarningKind {
+    /// The token's pattern matches the empty string. If nothing else matches, the lexer will
+    /// produce empty lexemes forever.
+    MatchesEmpty { token: Token },
+    /// `token` can never be lexed: either `by` is a token that matches everything it does and
+    /// wins ties, or `by` is a skip pattern that matches the start of everything it does (skip
+    /// patterns are tried first).
+    Shadowed { token: Token, by: Token },
+    /// The string `keyword` is also matched by the regex `identifier`. The keyword wins, so the
+    /// identifier can't be spelled that way. This is usually intended.
+    KeywordOverlap { keyword: Token, identifier: Token },
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub enum Severity {
+    /// Worth knowing, but usually intended.
+    Note,
+    /// Probably a mistake.
+    Warning,
+}
+
+impl LexerWarningKind {
+    pub fn severity(&self) -> Severity {
+        match self {
+            LexerWarningKind::KeywordOverlap { .. } => Severity::Note,
+            LexerWarningKind::MatchesEmpty { .. } | LexerWarningKind::Shadowed { .. } => {
+                Severity::Warning
+            }
+        }
+    }
+}
+
+impl fmt::Display for LexerWarning {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self.kind.severity() {
+            Severity::Note => write!(f, "lexer note: {}", self.message),
+            Severity::Warning => write!(f, "lexer warning: {}", self.message),
+        }
+    }
+}
+
+impl Lexer {
+    /// Look for tokens that can never be lexed, patterns that match the empty string, and
+    /// keywords that identifiers also match.
+    ///
+    /// Shadowing is only detected by a single token or skip pattern in the same mode, not by
+    /// several together, or by whitespace.
+    pub(crate) fn analyze(&self) -> Vec<LexerWarning> {
+        let mut warnings = vec![];
+        let mut warn = |kind, message| warnings.push(LexerWarning { kind, message });
+        let name = |token: Token| self.token_name(token);
+
+        for mode in &self.modes {
+            let dfa = |token: &Token| full_match_dfa(self.patterns[*token].regex.as_str());
+            let dfas = mode.tokens.iter().map(dfa).collect::<Vec<_>>();
+            let skip_dfas = mode.skips.iter().map(dfa).collect::<Vec<_>>();
+            // Whether everything the `i`th token of the mode matches is matched by the `j`th.
+            let covers = |i: usize, j: usize| match (&dfas[i], &dfas[j]) {
+                (Some(sub), Some(sup)) => is_subset(sub, sup),
+                _ => false,
+            };
+
+            for (i, &token) in mode.tokens.iter().enumerate() {
+                let pattern = &self.patterns[token];
+                if pattern.regex.is_match("") {
+                    warn(
+                        LexerWarningKind::MatchesEmpty { token },
+                        format!("token {} matches the empty string", name(token)),
+                    );
+                }
+
+                // Find a skip pattern that always matches first, or else a token that always
+                // beats this one: for a string, an earlier identical string (with a different
+                // mode action); for a regex, any string or earlier regex that matches everything
+                // it does.
+                let skipped_by = mode
+                    .skips
+                    .iter()
+                    .zip(&skip_dfas)
+                    .find_map(|(&skip, skip_dfa)| match (&dfas[i], skip_dfa) {
+                        (Some(sub), Some(prefix)) => is_prefix_covered(sub, prefix).then_some(skip),
+                        _ => None,
+                    });
+                let shadowed_by = mode.tokens.iter().enumerate().find_map(|(j, &other)| {
+                    let other_pattern = &self.patterns[other];
+                    let wins_ties = match (pattern.length, other_pattern.length) {
+                        (Some(_), Some(_)) | (None, None) => j < i,
+                        (Some(_), None) => false,
+                        (None, Some(_)) => true,
+                    };
+                    let covered = match pattern.length {
+                        Some(_) => pattern.regex.as_str() == other_pattern.regex.as_str(),
+                        None => covers(i, j),
+                    };
+                    (wins_ties && covered).then_some(other)
+                });
+                if let Some(by) = skipped_by {
+                    warn(
+                        LexerWarningKind::Shadowed { token, by },
+                        format!(
+                            "token {} can never be lexed, because it always starts with a match \
+                             of the skip pattern {}",
+                            name(token),
+                            name(by)
+                        ),
+                    );
+                } else if let Some(by) = shadowed_by {
+                    warn(
+                        LexerWarningKind::Shadowed { token, by },
+                        format!(
+                            "token {} can never be lexed, because {} matches everything it does",
+                            name(token),
+                            name(by)
+                        ),
+                    );
+                }
+
+                // A keyword that can never be lexed at all already has a warning.
+                if pattern.length.is_some() && skipped_by.is_none() && shadowed_by.is_none() {
+                    for (j, &identifier) in mode.tokens.iter().enumerate() {
+                        if self.patterns[identifier].length.is_none() && covers(i, j) {
+                            warn(
+                                LexerWarningKind::KeywordOverlap {
+                                    keyword: token,
+                                    identifier,
+                                },
+                                format!(
+                                    "keyword {} is also matched by {}, but will always be lexed \
+                                     as the keyword",
+                                    name(token),
+                                    name(identifier)
+                                ),
+                            );
+                        }
+                    }
+                }
+            }
+        }
+        warnings
+    }
+}
@@ -1,7 +1,5 @@
-use lexer::indexing_lexer::{Lexer as LexerI, LexerBuilder as LexerBuilderI};
-use lexer::lexer_without_line_col::{Lexer, LexerBuilder};
 use lexer::line_and_col_indexer::LineAndColIndexer;
-use lexer::{Lexer as LexerLC, LexerBuilder as LexerBuilderLC};
+use lexer::{Backend, LazyLineCol, Lexer, LexerBuilder, NoPositions};
 use std::fs::read_to_string;
 use std::time::Instant;
 
@@ -16,84 +14,20 @@ use std::time::Instant;
 //   Lexer with line&col: 5636ms (1.1563398 longer)
 //   Lexer w/ indexer:    7098ms (1.2594038 longer)
 
-fn json_lexer_lc() -> LexerLC {
-    let mut builder = LexerBuilderLC::new(r#"[ \t\r\n]+"#).unwrap();
-
-    // Strings
-    builder.regex(r#""([^\\"]|(\\.))*""#).unwrap();
-    // Numbers
-    builder
-        .regex(r#"-?(?:0|[1-9]\d*)(?:\.\d+)?(?:[eE][+-]?\d+)?"#)
-        .unwrap();
-    // Everything else
-    builder.string("null").unwrap();
-    builder.string("true").unwrap();
-    builder.string("false").unwrap();
-    builder.string("[").unwrap();
-    builder.string("]").unwrap();
-    builder.string(",").unwrap();
-    builder.string("{").unwrap();
-    builder.string("}").unwrap();
-    builder.string(":").unwrap();
-
-    builder.finish().unwrap()
-}
-
-fn json_lexer_no_lc() -> Lexer {
-    let mut builder = LexerBuilder::new(r#"[ \t\r\n]+"#).unwrap();
-
-    // Strings
-    builder.regex(r#""([^\\"]|(\\.))*""#).unwrap();
-    // Numbers
-    builder
-        .regex(r#"-?(?:0|[1-9]\d*)(?:\.\d+)?(?:[eE][+-]?\d+)?"#)
-        .unwrap();
-    // Everything else
-    builder.string("null").unwrap();
-    builder.string("true").unwrap();
-    builder.string("false").unwrap();
-    builder.string("[").unwrap();
-    builder.string("]").unwrap();
-    builder.string(",").unwrap();
-    builder.string("{").unwrap();
-    builder.string("}").unwrap();
-    builder.string(":").unwrap();
-
-    builder.finish().unwrap()
-}
-
-fn json_lexer_i() -> LexerI {
-    let mut builder = LexerBuilderI::new(r#"[ \t\r\n]+"#).unwrap();
-
-    // Strings
-    builder.regex(r#""([^\\"]|(\\.))*""#).unwrap();
-    // Numbers
-    builder
-        .regex(r#"-?(?:0|[1-9]\d*)(?:\.\d+)?(?:[eE][+-]?\d+)?"#)
-        .unwrap();
-    // Everything else
-    builder.string("null").unwrap();
-    builder.string("true").unwrap();
-    builder.string("false").unwrap();
-    builder.string("[").unwrap();
-    builder.string("]").unwrap();
-    builder.string(",").unwrap();
-    builder.string("{").unwrap();
-    builder.string("}").unwrap();
-    builder.string(":").unwrap();
-
+fn json_lexer(backend: Backend) -> Lexer {
+    let mut builder = LexerBuilder::from_spec(include_str!("../../json.lex")).unwrap();
+    builder.backend(backend);
     builder.finish().unwrap()
 }
 
 fn main() {
-    let lexer_no_lc = json_lexer_no_lc();
-    let lexer_lc = json_lexer_lc();
-    let lexer_i = json_lexer_i();
+    let lexer = json_lexer(Backend::RegexSet);
+    let lexer_dfa = json_lexer(Backend::Dfa);
     let source = &read_to_string("sample.json").unwrap();
 
     let now = Instant::now();
     for _ in 0..1000 {
-        let lexemes = lexer_no_lc.lex(source);
+        let lexemes = lexer.lex_with::<NoPositions>(source);
         let count = lexemes.count();
         assert_eq!(count, 14517);
     }
@@ -102,7 +36,7 @@ fn main() {
 
     let now = Instant::now();
     for _ in 0..1000 {
-        let lexemes = lexer_lc.lex(source);
+        let lexemes = lexer.lex(source);
         let mut count = 0;
         for lexeme in lexemes {
             count += lexeme.start.col;
@@ -120,14 +54,32 @@ fn main() {
 
     let now = Instant::now();
     for _ in 0..1000 {
-        let mut lexemes = lexer_i.lex(source);
-        let spans = (&mut lexemes).map(|(_, span)| span).collect::<Vec<_>>();
+        let lexemes = lexer_dfa.lex(source);
+        let mut count = 0;
+        for lexeme in lexemes {
+            count += lexeme.start.col;
+            count += lexeme.end.line;
+            count += lexeme.end.utf8_col;
+        }
+        assert_eq!(count, 31156367);
+    }
+    let elapsed_dfa = now.elapsed().as_millis();
+    println!(
+        "Lexer with DFA:      {}ms ({} longer)",
+        elapsed_dfa,
+        elapsed_dfa as f32 / elapsed as f32
+    );
+
+    let now = Instant::now();
+    for _ in 0..1000 {
+        let mut lexemes = lexer.lex_with::<LazyLineCol>(source);
+        let spans = (&mut lexemes).map(|l| (l.start, l.end)).collect::<Vec<_>>();
         let indexer = lexemes.into_indexer();
         let mut count = 0;
-        for span in spans {
-            count += indexer.start_col(span);
-            count += indexer.end_line(span);
-            count += indexer.end_utf8_col(span);
+        for (start, end) in spans {
+            count += indexer.col(start);
+            count += indexer.line(end);
+            count += indexer.utf8_col(end);
         }
         assert_eq!(count, 31156367);
     }
@@ -140,14 +92,14 @@ fn main() {
 
     let now = Instant::now();
     for _ in 0..1000 {
-        let mut lexemes = lexer_i.lex(source);
-        let spans = (&mut lexemes).map(|(_, span)| span).collect::<Vec<_>>();
+        let lexemes = lexer.lex_with::<LazyLineCol>(source);
+        let spans = lexemes.map(|l| (l.start, l.end)).collect::<Vec<_>>();
         let indexer = LineAndColIndexer::new(source);
         let mut count = 0;
-        for span in spans {
-            count += indexer.start_col(span);
-            count += indexer.end_line(span);
-            count += indexer.end_utf8_col(span);
+        for (start, end) in spans {
+            count += indexer.col(start);
+            count += indexer.line(end);
+            count += indexer.utf8_col(end);
         }
         assert_eq!(count, 31156367);
     }
@@ -0,0 +1,163 @@
+//! A lexing backend that compiles all of a mode's patterns into a single DFA, to find the longest
+//! match in one pass over the input. DFAs are also used to check whether one pattern covers
+//! another, for [`Lexer::warnings`](crate::Lexer::warnings).
+
+use regex::Error as RegexError;
+use regex_automata::dfa::{dense, Automaton, StartKind};
+use regex_automata::util::primitives::StateID;
+use regex_automata::{Anchored, Input, MatchKind};
+use std::collections::HashSet;
+
+#[derive(Debug, Clone)]
+pub(crate) struct TokenDfa {
+    dfa: dense::DFA<Vec<u32>>,
+    /// When several patterns match the same (longest) length, the one with the lowest rank wins.
+    /// Strings beat regexes, and then earlier patterns beat later ones.
+    ranks: Vec<usize>,
+}
+
+impl TokenDfa {
+    /// Build a DFA from `(regex, is_string)` pairs, in the order they were added to the lexer.
+    pub(crate) fn new(patterns: &[(&str, bool)]) -> Result<TokenDfa, RegexError> {
+        let dfa = dense::Builder::new()
+            .configure(
+                dense::Config::new()
+                    .match_kind(MatchKind::All)
+                    .start_kind(StartKind::Anchored),
+            )
+            .build_many(&patterns.iter().map(|p| p.0).collect::<Vec<_>>())
+            .map_err(|err| RegexError::Syntax(err.to_string()))?;
+
+        let mut order = (0..patterns.len()).collect::<Vec<_>>();
+        order.sort_by_key(|i| (!patterns[*i].1, *i));
+        let mut ranks = vec![0; patterns.len()];
+        for (rank, i) in order.into_iter().enumerate() {
+            ranks[i] = rank;
+        }
+
+        Ok(TokenDfa { dfa, ranks })
+    }
+
+    /// Find the longest, highest priority, match at the start of `source`. Returns the index of
+    /// the pattern that matched, and the length of the match.
+    pub(crate) fn longest_match(&self, source: &str) -> Option<(usize, usize)> {
+        let dfa = &self.dfa;
+        let input = Input::new(source).anchored(Anchored::Yes);
+        let mut state = dfa.start_state_forward(&input).ok()?;
+        let mut best_match = None;
+        // Matches are reported one byte late: entering a match state on byte `i` means that
+        // there's a match of length `i`.
+        for (i, byte) in source.bytes().enumerate() {
+            state = dfa.next_state(state, byte);
+            if dfa.is_match_state(state) {
+                best_match = Some((self.best_pattern(state), i));
+            } else if dfa.is_dead_state(state) || dfa.is_quit_state(state) {
+                return best_match;
+            }
+        }
+        state = dfa.next_eoi_state(state);
+        if dfa.is_match_state(state) {
+            best_match = Some((self.best_pattern(state), source.len()));
+        }
+        best_match
+    }
+
+    fn best_pattern(&self, state: StateID) -> usize {
+        (0..self.dfa.match_len(state))
+            .map(|i| self.dfa.match_pattern(state, i).as_usize())
+            .min_by_key(|pattern| self.ranks[*pattern])
+            .unwrap()
+    }
+}
+
+/// A DFA that matches `regex` against the whole of its input, for use with `is_subset`. Returns
+/// `None` if the regex can't be compiled to a DFA.
+pub(crate) fn full_match_dfa(regex: &str) -> Option<dense::DFA<Vec<u32>>> {
+    dense::Builder::new()
+        .configure(
+            dense::Config::new()
+                .match_kind(MatchKind::All)
+                .start_kind(StartKind::Anchored),
+        )
+        .build(&format!("(?:{})$", regex))
+        .ok()
+}
+
+/// Whether every string that `sub` matches also matches `sup`. Both must come from
+/// `full_match_dfa`.
+pub(crate) fn is_subset(sub: &dense::DFA<Vec<u32>>, sup: &dense::DFA<Vec<u32>>) -> bool {
+    let input = Input::new("").anchored(Anchored::Yes);
+    let start = match (
+        sub.start_state_forward(&input),
+        sup.start_state_forward(&input),
+    ) {
+        (Ok(sub_start), Ok(sup_start)) => (sub_start, sup_start),
+        _ => return false,
+    };
+
+    // Search the product of the two DFAs for a string that `sub` accepts but `sup` doesn't.
+    let mut seen = HashSet::from([start]);
+    let mut stack = vec![start];
+    while let Some((sub_state, sup_state)) = stack.pop() {
+        if sub.is_match_state(sub.next_eoi_state(sub_state))
+            && !sup.is_match_state(sup.next_eoi_state(sup_state))
+        {
+            return false;
+        }
+        for byte in 0..=255 {
+            let next = (
+                sub.next_state(sub_state, byte),
+                sup.next_state(sup_state, byte),
+            );
+            if sub.is_dead_state(next.0) || sub.is_quit_state(next.0) {
+                continue;
+            }
+            if seen.insert(next) {
+                stack.push(next);
+            }
+        }
+    }
+    true
+}
+
+/// Whether every string that `sub` matches starts with a non-empty string that `prefix` matches,
+/// so that a skip pattern `prefix` would always match before a token `sub` could. Both must come
+/// from `full_match_dfa`.
+pub(crate) fn is_prefix_covered(sub: &dense::DFA<Vec<u32>>, prefix: &dense::DFA<Vec<u32>>) -> bool {
+    let input = Input::new("").anchored(Anchored::Yes);
+    let start = match (
+        sub.start_state_forward(&input),
+        prefix.start_state_forward(&input),
+    ) {
+        (Ok(sub_start), Ok(prefix_start)) => (sub_start, prefix_start),
+        _ => return false,
+    };
+
+    // Search the product of the two DFAs for a string that `sub` accepts without passing through
+    // a non-empty match of `prefix`. The flag is whether any bytes have been read.
+    let start = (start.0, start.1, false);
+    let mut seen = HashSet::from([start]);
+    let mut stack = vec![start];
+    while let Some((sub_state, prefix_state, non_empty)) = stack.pop() {
+        if non_empty && prefix.is_match_state(prefix.next_eoi_state(prefix_state)) {
+            continue;
+        }
+        if sub.is_match_state(sub.next_eoi_state(sub_state)) {
+            return false;
+        }
+        for byte in 0..=255 {
+            let next = (
+                sub.next_state(sub_state, byte),
+                prefix.next_state(prefix_state, byte),
+                true,
+            );
+            if sub.is_dead_state(next.0) || sub.is_quit_state(next.0) {
+                continue;
+            }
+            if seen.insert(next) {
+                stack.push(next);
+            }
+        }
+    }
+    true
+}
@@ -0,0 +1,206 @@
+//! Incremental re-lexing, for editors.
+//!
+//! After a small edit to a large file, re-lexing the whole file with [`Lexer::lex`] is wasteful.
+//! Instead, [`Lexer::relex`] takes the lexemes from before the edit, and re-lexes starting just
+//! before the edit, stopping as soon as it reaches a lexeme after the edit that starts at the same
+//! place (and in the same mode) as before. The remaining lexemes are copied over with their
+//! positions shifted.
+//!
+//! ```
+//! use lexer::LexerBuilder;
+//! use lexer::incremental::TextEdit;
+//!
+//! let mut builder = LexerBuilder::new(r#"[ \t\r\n]+"#).unwrap();
+//! builder.regex("[a-zA-Z_]+").unwrap();
+//! builder.string("+").unwrap();
+//! let lexer = builder.finish().unwrap();
+//!
+//! let old_source = "a + b + c\n+ d";
+//! let old_lexemes = lexer.lex(old_source).collect::<Vec<_>>();
+//!
+//! let edit = TextEdit::new(4..5, "bee + e");
+//! let new_source = edit.apply(old_source);
+//! let relexed = lexer.relex(&old_lexemes, &edit, &new_source);
+//!
+//! assert_eq!(relexed.lexemes, lexer.lex(&new_source).collect::<Vec<_>>());
+//! // Lexeme 2 ("b") was replaced by lexemes 2..5 ("bee + e").
+//! assert_eq!(relexed.old_range, 2..3);
+//! assert_eq!(relexed.new_range, 2..5);
+//! ```
+//!
+//! This assumes that whether a token matches (and how long its match is) never depends on text
+//! more than one lexeme past where its match ends. That holds for every reasonable set of tokens,
+//! but you could construct a regex that violates it.
+
+use super::{
+    apply_mode_action, ColumnKind, EagerLineCol, Lexeme, LexemeIter, Lexer, Position, DEFAULT_MODE,
+};
+use std::ops::Range;
+
+/// A change to a source text: replace the bytes in `range` with `replacement`.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct TextEdit<'a> {
+    pub range: Range<usize>,
+    pub replacement: &'a str,
+}
+
+impl<'a> TextEdit<'a> {
+    pub fn new(range: Range<usize>, replacement: &'a str) -> TextEdit<'a> {
+        TextEdit { range, replacement }
+    }
+
+    /// Apply this edit to `source`, producing the new source text.
+    ///
+    /// # Panics
+    ///
+    /// Panics if the range is out of bounds, or doesn't lie on char boundaries.
+    pub fn apply(&self, source: &str) -> String {
+        let mut new_source =
+            String::with_capacity(source.len() - self.range.len() + self.replacement.len());
+        new_source.push_str(&source[..self.range.start]);
+        new_source.push_str(self.replacement);
+        new_source.push_str(&source[self.range.end..]);
+        new_source
+    }
+}
+
+/// The result of [`Lexer::relex`].
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Relexed<'s> {
+    /// Every lexeme in the new source: the same as what `Lexer::lex` would produce.
+    pub lexemes: Vec<Lexeme<'s>>,
+    /// The range of the _old_ lexemes that changed.
+    pub old_range: Range<usize>,
+    /// The range of the new `lexemes` that replaced them. Lexemes before this range are
+    /// identical to the old ones, and lexemes after it are identical except for their positions.
+    pub new_range: Range<usize>,
+}
+
+impl Lexer {
+    /// Re-lex a source text after an edit. `old_lexemes` must be the complete output of lexing the
+    /// source before the edit (with this lexer), and `new_source` must be the result of applying
+    /// `edit` to it.
+    pub fn relex<'s>(
+        &self,
+        old_lexemes: &[Lexeme],
+        edit: &TextEdit,
+        new_source: &'s str,
+    ) -> Relexed<'s> {
+        let old_edit_end = edit.range.end;
+        let new_edit_end = edit.range.start + edit.replacement.len();
+
+        // Restart at the first lexeme that touches the edit, or one before that in case the edit
+        // lengthens it.
+        let first_touched = old_lexemes.partition_point(|lex| lex.end.pos < edit.range.start);
+        let restart = first_touched.saturating_sub(1);
+
+        let mut mode_stack = vec![DEFAULT_MODE];
+        for lexeme in &old_lexemes[..restart] {
+            apply_mode_action(&mut mode_stack, self.action(lexeme.token));
+        }
+        let start = if first_touched == 0 {
+            // The edit might be before the first lexeme, so start from the very beginning.
+            Position::default()
+        } else {
+            old_lexemes[restart].start
+        };
+
+        let mut lexemes = old_lexemes[..restart]
+            .iter()
+            .map(|lexeme| reslice(lexeme, lexeme.start, lexeme.start, new_source))
+            .collect::<Vec<_>>();
+
+        // Lex until we reach a lexeme after the edit that lines up with an old lexeme: it must
+        // start at the same (shifted) position, with the same mode stack.
+        let mut iter = LexemeIter {
+            full_source: new_source,
+            source: &new_source[start.pos..],
+            tracker: EagerLineCol(start),
+            lexer: self,
+            mode_stack: mode_stack.clone(),
+        };
+        // The old lexeme we might resync at, and the mode stack just before it.
+        let mut old_index = restart;
+        let mut old_mode_stack = mode_stack;
+        let mut resync = None;
+        loop {
+            let new_mode_stack = iter.mode_stack.clone();
+            let lexeme = match iter.next() {
+                Some(lexeme) => lexeme,
+                None => break,
+            };
+            if lexeme.start.pos >= new_edit_end {
+                let old_pos = lexeme.start.pos + old_edit_end - new_edit_end;
+                while old_index < old_lexemes.len() && old_lexemes[old_index].start.pos < old_pos {
+                    let token = old_lexemes[old_index].token;
+                    apply_mode_action(&mut old_mode_stack, self.action(token));
+                    old_index += 1;
+                }
+                if old_index < old_lexemes.len()
+                    && old_lexemes[old_index].start.pos == old_pos
+                    && old_mode_stack == new_mode_stack
+                {
+                    resync = Some((old_index, lexeme.start));
+                    break;
+                }
+            }
+            lexemes.push(lexeme);
+        }
+
+        let mut new_range = restart..lexemes.len();
+        let mut old_range = restart..old_lexemes.len();
+        if let Some((old_index, new_start)) = resync {
+            old_range.end = old_index;
+            let old_start = old_lexemes[old_index].start;
+            for lexeme in &old_lexemes[old_index..] {
+                lexemes.push(reslice(lexeme, old_start, new_start, new_source));
+            }
+        }
+
+        // The first re-lexed lexeme is often unchanged.
+        while !new_range.is_empty()
+            && !old_range.is_empty()
+            && lexemes[new_range.start] == old_lexemes[old_range.start]
+        {
+            new_range.start += 1;
+            old_range.start += 1;
+        }
+
+        Relexed {
+            lexemes,
+            old_range,
+            new_range,
+        }
+    }
+}
+
+/// Move an old lexeme into the new source, shifting it so that position `from` becomes `to`.
+/// `lexeme` must not start before `from`.
+fn reslice<'s>(lexeme: &Lexeme, from: Position, to: Position, new_source: &'s str) -> Lexeme<'s> {
+    let start = shift(lexeme.start, from, to);
+    let end = shift(lexeme.end, from, to);
+    Lexeme {
+        token: lexeme.token,
+        lexeme: &new_source[start.pos..end.pos],
+        start,
+        end,
+    }
+}
+
+fn shift(pos: Position, from: Position, to: Position) -> Position {
+    let shift_col = |kind| {
+        if pos.line == from.line {
+            pos.column(kind) - from.column(kind) + to.column(kind)
+        } else {
+            pos.column(kind)
+        }
+    };
+    Position {
+        pos: pos.pos - from.pos + to.pos,
+        line: pos.line - from.line + to.line,
+        col: shift_col(ColumnKind::Byte),
+        utf8_col: shift_col(ColumnKind::Utf8),
+        utf16_col: shift_col(ColumnKind::Utf16),
+        display_col: shift_col(ColumnKind::Display),
+    }
+}
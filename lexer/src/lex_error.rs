@@ -0,0 +1,175 @@
+use crate::{Lexeme, LexemeIter, PatternKind, Position, LEX_ERROR};
+use std::fmt;
+
+/// A lexing error, describing a `LEX_ERROR` lexeme. Get one from [`LexemeIter::error`]. Its
+/// `Display` impl shows the offending line, with carets under the lexeme.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct LexError {
+    pub kind: LexErrorKind,
+    /// The text of the `LEX_ERROR` lexeme.
+    pub lexeme: String,
+    pub start: Position,
+    pub end: Position,
+    /// The names of the tokens that could have been lexed here. See
+    /// [`Lexer::token_name`](crate::Lexer::token_name).
+    pub expected: Vec<String>,
+    filename: String,
+    line_contents: String,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum LexErrorKind {
+    /// No pattern matched.
+    Unexpected,
+    /// A nested skip pattern was opened, but never closed. `expected` contains the closing
+    /// delimiter.
+    Unclosed { open: String },
+}
+
+impl<'l, 's> LexemeIter<'l, 's> {
+    /// Describe a `LEX_ERROR` lexeme that this iterator just produced. (It must be called
+    /// before the iterator is advanced again, because the tokens that were expected depend on
+    /// the current mode.)
+    ///
+    /// # Panics
+    ///
+    /// Panics if `lexeme` isn't a `LEX_ERROR`.
+    pub fn error(&self, filename: &str, lexeme: &Lexeme<'s>) -> LexError {
+        assert_eq!(lexeme.token, LEX_ERROR, "Lexeme is not an error");
+
+        let lexer = self.lexer;
+        let mode = &lexer.modes[*self.mode_stack.last().unwrap()];
+        let unclosed = mode
+            .skips
+            .iter()
+            .find_map(|token| match &lexer.patterns[*token].kind {
+                PatternKind::SkipNested { open, close } if lexeme.lexeme.starts_with(open) => {
+                    Some((open, close))
+                }
+                _ => None,
+            });
+        let (kind, expected) = match unclosed {
+            Some((open, close)) => (
+                LexErrorKind::Unclosed { open: open.clone() },
+                vec![format!("{:?}", close)],
+            ),
+            None => (
+                LexErrorKind::Unexpected,
+                mode.tokens
+                    .iter()
+                    .map(|token| lexer.token_name(*token).to_owned())
+                    .collect(),
+            ),
+        };
+
+        let line_contents = self.full_source[lexeme.start.pos - lexeme.start.col..]
+            .lines()
+            .next()
+            .unwrap_or("")
+            .to_owned();
+        LexError {
+            kind,
+            lexeme: lexeme.lexeme.to_owned(),
+            start: lexeme.start,
+            end: lexeme.end,
+            expected,
+            filename: filename.to_owned(),
+            line_contents,
+        }
+    }
+}
+
+impl LexError {
+    fn message(&self) -> String {
+        match &self.kind {
+            LexErrorKind::Unexpected => format!("unexpected {:?}", self.lexeme),
+            LexErrorKind::Unclosed { open } => format!("unclosed {:?}", open),
+        }
+    }
+
+    fn expected_message(&self) -> String {
+        match self.expected.as_slice() {
+            [] => "expected nothing".to_owned(),
+            [expected] => format!("expected {}", expected),
+            expected => format!("expected one of: {}", expected.join(", ")),
+        }
+    }
+}
+
+impl fmt::Display for LexError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        use colored::Colorize;
+
+        let start = self.start;
+        let end = self.end;
+        let line_num = format!("{}", start.line + 1);
+        let margin_width = line_num.len();
+        let num_carets = if start.line == end.line {
+            (end.utf8_col - start.utf8_col).max(1)
+        } else {
+            (self.line_contents.chars().count() - start.utf8_col).max(1)
+        };
+
+        writeln!(
+            f,
+            "{}{} {}",
+            "lex error".red().bold(),
+            ":".bold(),
+            self.message().bold(),
+        )?;
+        writeln!(
+            f,
+            "{:indent$}{} {}:{}:{}",
+            "",
+            "-->".blue().bold(),
+            self.filename,
+            start.line + 1,
+            start.utf8_col + 1,
+            indent = margin_width,
+        )?;
+        writeln!(
+            f,
+            "{:indent$}{}",
+            "",
+            "|".blue().bold(),
+            indent = margin_width + 1
+        )?;
+        writeln!(
+            f,
+            "{} {}{}",
+            line_num.blue().bold(),
+            "|".blue().bold(),
+            self.line_contents,
+        )?;
+        writeln!(
+            f,
+            "{:indent$}{}{:start$}{}",
+            "",
+            "|".blue().bold(),
+            "",
+            &"^".repeat(num_carets).red().bold(),
+            start = start.utf8_col,
+            indent = margin_width + 1
+        )?;
+        writeln!(
+            f,
+            "{:indent$}{}{:start$}{}",
+            "",
+            "|".blue().bold(),
+            "",
+            self.expected_message().red().bold(),
+            start = start.utf8_col,
+            indent = margin_width + 1
+        )?;
+        write!(
+            f,
+            "{:indent$}{}",
+            "",
+            "|".blue().bold(),
+            indent = margin_width + 1
+        )?;
+        Ok(())
+    }
+}
+
+impl std::error::Error for LexError {}
@@ -27,18 +27,123 @@
 //!
 //! If there are multiple possible matches:
 //!
-//! - The longest match is used.
+//! - The longest match is used. Each regex matches as much as it can, so `=|==` matches all of
+//!   `==` (unlike `Regex::find`, which would stop at the first alternative).
 //! - If there is a tie, whichever token is a 'string' pattern instead of a 'regex' pattern will be
-//! used.
+//!   used.
 //! - If there is _still_ a tie, the regex that's first in the list provided to `Lexer::new()` will
-//! be used.
+//!   be used.
+//!
+//! ## Lexing errors
+//!
+//! How much text a `LEX_ERROR` lexeme covers is chosen with [`LexerBuilder::recovery`]. To report
+//! an error, [`LexemeIter::error`] gives a [`LexError`] listing the tokens that were expected,
+//! which displays with the offending line underlined:
+//!
+//! ```
+//! use lexer::{LexerBuilder, Recovery, LEX_ERROR};
+//!
+//! let mut builder = LexerBuilder::new(r#"[ \t\r\n]+"#).unwrap();
+//! builder.string("+").unwrap();
+//! builder.regex("[a-zA-Z_]+").unwrap();
+//! builder.recovery(Recovery::SkipToToken);
+//! let lexer = builder.finish().unwrap();
+//!
+//! let mut lexemes = lexer.lex("x + foo@bar");
+//! let mut errors = vec![];
+//! while let Some(lexeme) = lexemes.next() {
+//!     if lexeme.token == LEX_ERROR {
+//!         errors.push(lexemes.error("example.txt", &lexeme));
+//!     }
+//! }
+//! assert_eq!(errors.len(), 1);
+//! assert_eq!(errors[0].lexeme, "@");
+//! assert_eq!(errors[0].expected, vec!["\"+\"", "/[a-zA-Z_]+/"]);
+//! ```
+//!
+//! ## Comments and other skipped text
+//!
+//! Besides whitespace, you can register any number of _skip patterns_, like line comments, with
+//! `skip()`. Regexes can't match balanced delimiters, so there is also `skip_nested()`, which
+//! skips nested block comments like `/* a /* b */ c */`. Skip patterns are tried (in the order
+//! they were added) before any tokens are. They are discarded by default, but
+//! `emit_trivia(true)` will make the lexer emit them as _trivia_ lexemes instead, whose tokens
+//! are the ones returned by `skip()` and `skip_nested()`. (Whitespace given to
+//! `LexerBuilder::new` is never emitted.)
+//!
+//! ```
+//! use lexer::{LexerBuilder, LEX_ERROR};
+//!
+//! let mut builder = LexerBuilder::new(r#"[ \t\r\n]+"#).unwrap();
+//! let tok_var = builder.regex("[a-zA-Z_]+").unwrap();
+//! let tok_line_comment = builder.skip("//[^\n]*").unwrap();
+//! let tok_block_comment = builder.skip_nested("/*", "*/");
+//! builder.emit_trivia(true);
+//! let lexer = builder.finish().unwrap();
+//!
+//! let lexemes = lexer.lex("x /* a /* b */ c */ y // z").collect::<Vec<_>>();
+//! let tokens = lexemes.iter().map(|l| l.token).collect::<Vec<_>>();
+//! assert_eq!(tokens, vec![tok_var, tok_block_comment, tok_var, tok_line_comment]);
+//! assert_eq!(lexemes[1].lexeme, "/* a /* b */ c */");
+//! assert!(lexer.is_trivia(tok_block_comment));
+//!
+//! // An unterminated block comment is a lexing error.
+//! let lexemes = lexer.lex("x /* a /* b */").collect::<Vec<_>>();
+//! assert_eq!(lexemes[1].token, LEX_ERROR);
+//! ```
+//!
+//! ## Modes
+//!
+//! Some languages need different tokens in different contexts, like inside and outside of a
+//! string literal. For these, you can add named [`Mode`]s, each with its own patterns and
+//! whitespace. When a pattern matches, its [`ModeAction`] can push, pop, or switch the active
+//! mode, so that a single `Lexer` can handle string interpolation like `"abc ${x} def"`:
+//!
+//! ```
+//! use lexer::{LexerBuilder, ModeAction, DEFAULT_MODE};
+//!
+//! let mut builder = LexerBuilder::new(r#"[ \t\r\n]+"#).unwrap();
+//! let string_mode = builder.mode("string", None).unwrap();
+//! let tok_var = builder.regex("[a-zA-Z_]+").unwrap();
+//! let tok_quote = builder
+//!     .string_in(DEFAULT_MODE, "\"", ModeAction::Push(string_mode))
+//!     .unwrap();
+//! let tok_close = builder.string_in(DEFAULT_MODE, "}", ModeAction::Pop).unwrap();
+//! let tok_end_quote = builder.string_in(string_mode, "\"", ModeAction::Pop).unwrap();
+//! let tok_interp = builder
+//!     .string_in(string_mode, "${", ModeAction::Push(DEFAULT_MODE))
+//!     .unwrap();
+//! let tok_text = builder
+//!     .regex_in(string_mode, r#"([^"$]|\$[^{"])+"#, ModeAction::Stay)
+//!     .unwrap();
+//! let lexer = builder.finish().unwrap();
+//!
+//! let lexemes = lexer.lex(r#""abc ${x} def""#).collect::<Vec<_>>();
+//! let tokens = lexemes.iter().map(|l| l.token).collect::<Vec<_>>();
+//! assert_eq!(
+//!     tokens,
+//!     vec![tok_quote, tok_text, tok_interp, tok_var, tok_close, tok_text, tok_end_quote]
+//! );
+//! assert_eq!(lexemes[5].lexeme, " def");
+//! ```
 
-pub mod indexing_lexer;
-pub mod lexer_without_line_col;
+mod analysis;
+mod dfa;
+pub mod incremental;
+mod lex_error;
 pub mod line_and_col_indexer;
+mod position;
+pub mod spec;
+pub mod streaming;
 
+use dfa::TokenDfa;
+use line_and_col_indexer::LineAndColIndexer;
 use regex::{escape, Error as RegexError, Regex, RegexSet};
-use std::fmt;
+use regex_automata::{meta, Anchored, Input, MatchKind};
+
+pub use analysis::{LexerWarning, LexerWarningKind, Severity};
+pub use lex_error::{LexError, LexErrorKind};
+pub use position::{ColumnKind, EagerLineCol, LazyLineCol, NoPositions, Position, PositionTracker};
 
 /// A category of lexeme, such as "INTEGER" or "VARIABLE" or "OPEN_PAREN". The special Token called
 /// [`LEX_ERROR`] represents a lexing error.
@@ -46,15 +151,89 @@ pub type Token = usize;
 
 pub const LEX_ERROR: Token = Token::MAX;
 
+/// A lexing mode. Each mode has its own patterns and whitespace. The lexer is always in exactly
+/// one mode, the top of a stack of modes that patterns can manipulate via [`ModeAction`]s.
+pub type Mode = usize;
+
+/// The mode that lexing starts in. Patterns added with `string()` and `regex()` belong to it.
+pub const DEFAULT_MODE: Mode = 0;
+
+/// What to do to the mode stack after a pattern matches.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum ModeAction {
+    /// Stay in the current mode.
+    Stay,
+    /// Enter the given mode, remembering the current one.
+    Push(Mode),
+    /// Return to the mode that was active before the most recent `Push`. Does nothing if the
+    /// stack has only one mode.
+    Pop,
+    /// Replace the current mode with the given one (without growing the stack).
+    Switch(Mode),
+}
+
+/// How the lexer finds the longest matching token. Both backends produce the same lexemes:
+/// each regex matches as much as it can, whatever the order of its alternatives or the laziness
+/// of its repetitions.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum Backend {
+    /// Use a `RegexSet` to find which patterns match, then run each matching pattern's regex to
+    /// find its length.
+    #[default]
+    RegexSet,
+    /// Compile all of the patterns in a mode into a single DFA that finds the longest match in
+    /// one pass. Faster to lex with, but slower to build, and it doesn't support regex features
+    /// that DFAs can't express (like Unicode word boundaries `\b`).
+    Dfa,
+}
+
+/// Where a `LEX_ERROR` lexeme ends, when no pattern matches. Lexing resumes after it.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum Recovery {
+    /// Skip up to the next basic whitespace character (space, tab, or newline). This may swallow
+    /// valid tokens: `foo@bar` lexes as `foo` followed by the error `@bar`.
+    #[default]
+    SkipToWhitespace,
+    /// Skip a single character.
+    SkipChar,
+    /// Skip up to the next place where whitespace, a skip pattern, or a token matches:
+    /// `foo@bar` lexes as `foo`, the error `@`, and `bar`.
+    SkipToToken,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+enum PatternKind {
+    /// An ordinary token.
+    Token,
+    /// Text to skip (or emit as trivia) that's matched by the pattern's regex.
+    Skip,
+    /// Text to skip (or emit as trivia) between balanced, possibly nested, delimiters. The
+    /// pattern's regex matches the opening delimiter.
+    SkipNested { open: String, close: String },
+}
+
 #[derive(Debug, Clone)]
 pub struct Pattern {
     regex: Regex,
+    /// Finds the longest match of `regex`. `Regex::find` instead prefers earlier alternatives and
+    /// shorter lazy repetitions, so `=|==` would only match one `=` of `==`.
+    longest: meta::Regex,
+    /// Set with `LexerBuilder::name`. Defaults to the pattern's string in quotes, or its regex
+    /// between slashes.
+    name: String,
     length: Option<usize>,
+    mode: Mode,
+    action: ModeAction,
+    kind: PatternKind,
 }
 
 impl PartialEq for Pattern {
     fn eq(&self, other: &Pattern) -> bool {
-        self.regex.as_str() == other.regex.as_str() && self.length == other.length
+        self.regex.as_str() == other.regex.as_str()
+            && self.length == other.length
+            && self.mode == other.mode
+            && self.action == other.action
+            && self.kind == other.kind
     }
 }
 
@@ -64,37 +243,81 @@ fn new_regex(regex: &str) -> Result<Regex, RegexError> {
     Regex::new(&format!("^({})", regex))
 }
 
+fn new_longest_regex(regex: &Regex) -> Result<meta::Regex, RegexError> {
+    meta::Regex::builder()
+        .configure(meta::Regex::config().match_kind(MatchKind::All))
+        .build(regex.as_str())
+        .map_err(|err| RegexError::Syntax(err.to_string()))
+}
+
+impl Pattern {
+    /// The length of the longest match of this pattern at the start of `source`, if any.
+    fn match_len(&self, source: &str) -> Option<usize> {
+        self.longest
+            .find(Input::new(source).anchored(Anchored::Yes))
+            .map(|span| span.end())
+    }
+}
+
+#[derive(Debug, Clone)]
+struct ModeSpec {
+    name: String,
+    whitespace: Option<Regex>,
+}
+
 #[derive(Debug, Clone)]
 pub struct LexerBuilder {
-    whitespace: Regex,
+    modes: Vec<ModeSpec>,
     patterns: Vec<Pattern>,
+    emit_trivia: bool,
+    backend: Backend,
+    recovery: Recovery,
+    check_patterns: bool,
 }
 
 impl LexerBuilder {
+    /// Start building a lexer whose [`DEFAULT_MODE`] skips whitespace matching the given regex.
     pub fn new(whitespace_regex: &str) -> Result<LexerBuilder, RegexError> {
         Ok(LexerBuilder {
-            whitespace: new_regex(whitespace_regex)?,
+            modes: vec![ModeSpec {
+                name: "default".to_owned(),
+                whitespace: Some(new_regex(whitespace_regex)?),
+            }],
             patterns: vec![],
+            emit_trivia: false,
+            backend: Backend::default(),
+            recovery: Recovery::default(),
+            check_patterns: true,
         })
     }
 
+    /// Add a new lexing mode, which will skip whitespace matching `whitespace_regex` (if any).
+    /// Returns the mode, for use with `string_in()`, `regex_in()`, and [`ModeAction`]s.
+    ///
+    /// # Panics
+    ///
+    /// Panics if there is already a mode with this name.
+    pub fn mode(&mut self, name: &str, whitespace_regex: Option<&str>) -> Result<Mode, RegexError> {
+        assert!(
+            self.modes.iter().all(|mode| mode.name != name),
+            "Lexer mode '{}' already exists",
+            name
+        );
+        let whitespace = match whitespace_regex {
+            Some(regex) => Some(new_regex(regex)?),
+            None => None,
+        };
+        self.modes.push(ModeSpec {
+            name: name.to_owned(),
+            whitespace,
+        });
+        Ok(self.modes.len() - 1)
+    }
+
     /// Add a pattern that matches exactly the string provided. Returns the token that will be
     /// produced whenever this pattern matches.
     pub fn string(&mut self, constant: &str) -> Result<Token, RegexError> {
-        let pattern = Pattern {
-            regex: new_regex(&escape(constant))?,
-            length: Some(constant.len()),
-        };
-
-        for (existing_token, existing_pattern) in self.patterns.iter().enumerate() {
-            if &pattern == existing_pattern {
-                return Ok(existing_token);
-            }
-        }
-
-        let token = self.patterns.len();
-        self.patterns.push(pattern);
-        Ok(token)
+        self.string_in(DEFAULT_MODE, constant, ModeAction::Stay)
     }
 
     /// Add a pattern that matches the given regex. Returns the token that will be produced whenever
@@ -103,146 +326,324 @@ impl LexerBuilder {
     /// The syntax is that of the `regex` crate. You do not need to begin the pattern with a
     /// start-of-string character `^`.
     pub fn regex(&mut self, regex: &str) -> Result<Token, RegexError> {
+        self.regex_in(DEFAULT_MODE, regex, ModeAction::Stay)
+    }
+
+    /// Like `string()`, but the pattern only matches in the given `mode`, and performs `action`
+    /// on the mode stack when it matches.
+    pub fn string_in(
+        &mut self,
+        mode: Mode,
+        constant: &str,
+        action: ModeAction,
+    ) -> Result<Token, RegexError> {
+        let regex = new_regex(&escape(constant))?;
         let pattern = Pattern {
-            regex: new_regex(regex)?,
+            longest: new_longest_regex(&regex)?,
+            regex,
+            name: format!("{:?}", constant),
+            length: Some(constant.len()),
+            mode,
+            action,
+            kind: PatternKind::Token,
+        };
+        Ok(self.add_pattern(pattern))
+    }
+
+    /// Like `regex()`, but the pattern only matches in the given `mode`, and performs `action`
+    /// on the mode stack when it matches.
+    pub fn regex_in(
+        &mut self,
+        mode: Mode,
+        regex: &str,
+        action: ModeAction,
+    ) -> Result<Token, RegexError> {
+        let compiled = new_regex(regex)?;
+        let pattern = Pattern {
+            longest: new_longest_regex(&compiled)?,
+            regex: compiled,
+            name: format!("/{}/", regex),
             length: None,
+            mode,
+            action,
+            kind: PatternKind::Token,
         };
+        Ok(self.add_pattern(pattern))
+    }
+
+    /// Skip text matching the given regex, such as a line comment. Returns the token that the
+    /// skipped text will have if `emit_trivia` is on.
+    pub fn skip(&mut self, regex: &str) -> Result<Token, RegexError> {
+        self.skip_in(DEFAULT_MODE, regex)
+    }
+
+    /// Like `skip()`, but only in the given `mode`.
+    pub fn skip_in(&mut self, mode: Mode, regex: &str) -> Result<Token, RegexError> {
+        let compiled = new_regex(regex)?;
+        let pattern = Pattern {
+            longest: new_longest_regex(&compiled)?,
+            regex: compiled,
+            name: format!("/{}/", regex),
+            length: None,
+            mode,
+            action: ModeAction::Stay,
+            kind: PatternKind::Skip,
+        };
+        Ok(self.add_pattern(pattern))
+    }
+
+    /// Skip text between the delimiters `open` and `close`, which may be nested, such as
+    /// `/* a /* b */ c */`. Returns the token that the skipped text will have if `emit_trivia`
+    /// is on. An unclosed `open` delimiter is a lexing error.
+    ///
+    /// # Panics
+    ///
+    /// Panics if either delimiter is empty, or if they are the same.
+    pub fn skip_nested(&mut self, open: &str, close: &str) -> Token {
+        self.skip_nested_in(DEFAULT_MODE, open, close)
+    }
+
+    /// Like `skip_nested()`, but only in the given `mode`.
+    pub fn skip_nested_in(&mut self, mode: Mode, open: &str, close: &str) -> Token {
+        assert!(
+            !open.is_empty() && !close.is_empty() && open != close,
+            "Nested skip delimiters must be distinct and non-empty"
+        );
+        let regex = new_regex(&escape(open)).unwrap();
+        let pattern = Pattern {
+            longest: new_longest_regex(&regex).unwrap(),
+            regex,
+            name: format!("{:?}", open),
+            length: Some(open.len()),
+            mode,
+            action: ModeAction::Stay,
+            kind: PatternKind::SkipNested {
+                open: open.to_owned(),
+                close: close.to_owned(),
+            },
+        };
+        self.add_pattern(pattern)
+    }
+
+    /// Whether to emit text matched by skip patterns as trivia lexemes (`true`), or discard it
+    /// (`false`, the default).
+    pub fn emit_trivia(&mut self, emit: bool) {
+        self.emit_trivia = emit;
+    }
+
+    /// Give `token` a name, to be returned by [`Lexer::token_name`].
+    ///
+    /// # Panics
+    ///
+    /// Panics if the token doesn't exist.
+    pub fn name(&mut self, token: Token, name: &str) {
+        self.patterns[token].name = name.to_owned();
+    }
+
+    fn add_pattern(&mut self, pattern: Pattern) -> Token {
+        let num_modes = self.modes.len();
+        let check_mode =
+            |mode: Mode| assert!(mode < num_modes, "Lexer mode {} does not exist", mode);
+        check_mode(pattern.mode);
+        match pattern.action {
+            ModeAction::Push(mode) | ModeAction::Switch(mode) => check_mode(mode),
+            ModeAction::Stay | ModeAction::Pop => (),
+        }
 
         for (existing_token, existing_pattern) in self.patterns.iter().enumerate() {
             if &pattern == existing_pattern {
-                return Ok(existing_token);
+                return existing_token;
             }
         }
 
         let token = self.patterns.len();
         self.patterns.push(pattern);
-        Ok(token)
+        token
+    }
+
+    /// Choose how the lexer will find matches. Defaults to [`Backend::RegexSet`].
+    pub fn backend(&mut self, backend: Backend) {
+        self.backend = backend;
+    }
+
+    /// Choose how much text a `LEX_ERROR` lexeme covers. Defaults to
+    /// [`Recovery::SkipToWhitespace`].
+    pub fn recovery(&mut self, recovery: Recovery) {
+        self.recovery = recovery;
+    }
+
+    /// Whether `finish` should check the patterns for likely mistakes, reported by
+    /// [`Lexer::warnings`]. Defaults to true. The checks build a DFA for each regex, which can
+    /// double the time `finish` takes.
+    pub fn check_patterns(&mut self, check: bool) {
+        self.check_patterns = check;
     }
 
     /// Call this when you're done adding token patterns, to construct the lexer.
     pub fn finish(self) -> Result<Lexer, RegexError> {
-        Ok(Lexer {
-            whitespace: self.whitespace,
-            regex_set: RegexSet::new(self.patterns.iter().map(|p| p.regex.as_str()))?,
+        let mut modes = vec![];
+        for (mode, spec) in self.modes.into_iter().enumerate() {
+            let in_mode = |token: &Token| self.patterns[*token].mode == mode;
+            let tokens = (0..self.patterns.len())
+                .filter(in_mode)
+                .filter(|token| self.patterns[*token].kind == PatternKind::Token)
+                .collect::<Vec<_>>();
+            let skips = (0..self.patterns.len())
+                .filter(in_mode)
+                .filter(|token| self.patterns[*token].kind != PatternKind::Token)
+                .collect::<Vec<_>>();
+            let matcher = match self.backend {
+                Backend::RegexSet => Matcher::RegexSet(RegexSet::new(
+                    tokens
+                        .iter()
+                        .map(|token| self.patterns[*token].regex.as_str()),
+                )?),
+                Backend::Dfa => Matcher::Dfa(Box::new(TokenDfa::new(
+                    &tokens
+                        .iter()
+                        .map(|token| {
+                            let pattern = &self.patterns[*token];
+                            (pattern.regex.as_str(), pattern.length.is_some())
+                        })
+                        .collect::<Vec<_>>(),
+                )?)),
+            };
+            modes.push(LexerMode {
+                name: spec.name,
+                whitespace: spec.whitespace,
+                matcher,
+                tokens,
+                skips,
+            });
+        }
+        let mut lexer = Lexer {
             patterns: self.patterns,
-        })
+            modes,
+            emit_trivia: self.emit_trivia,
+            recovery: self.recovery,
+            warnings: vec![],
+        };
+        if self.check_patterns {
+            lexer.warnings = lexer.analyze();
+        }
+        Ok(lexer)
     }
 }
 
+#[derive(Debug, Clone)]
+struct LexerMode {
+    name: String,
+    whitespace: Option<Regex>,
+    /// Matches the patterns of this mode. Pattern `i` of the matcher is token `tokens[i]`.
+    matcher: Matcher,
+    tokens: Vec<Token>,
+    /// Skip patterns, in the order they should be tried.
+    skips: Vec<Token>,
+}
+
+#[derive(Debug, Clone)]
+enum Matcher {
+    RegexSet(RegexSet),
+    Dfa(Box<TokenDfa>),
+}
+
 #[derive(Debug, Clone)]
 pub struct Lexer {
-    whitespace: Regex,
     patterns: Vec<Pattern>,
-    regex_set: RegexSet,
+    modes: Vec<LexerMode>,
+    emit_trivia: bool,
+    recovery: Recovery,
+    warnings: Vec<LexerWarning>,
 }
 
 impl Lexer {
+    /// Likely mistakes in the lexer's patterns, found when it was built (unless turned off with
+    /// [`LexerBuilder::check_patterns`]).
+    pub fn warnings(&self) -> &[LexerWarning] {
+        &self.warnings
+    }
+
     /// Split `source` into a stream of lexemes. It is frequently useful to wrap this in
     /// [`iter::Peekable`](https://doc.rust-lang.org/stable/std/iter/struct.Peekable.html).
-    pub fn lex<'l, 's: 'l>(&'l self, source: &'s str) -> impl Iterator<Item = Lexeme<'s>> + 'l {
+    pub fn lex<'l, 's: 'l>(&'l self, source: &'s str) -> LexemeIter<'l, 's> {
+        self.lex_with(source)
+    }
+
+    /// Like [`Lexer::lex`], but with a choice of how to track the positions of lexemes. See
+    /// [`PositionTracker`].
+    pub fn lex_with<'l, 's: 'l, T: PositionTracker>(
+        &'l self,
+        source: &'s str,
+    ) -> LexemeIter<'l, 's, T> {
         LexemeIter {
+            full_source: source,
             source,
             lexer: self,
-            position: Position {
-                pos: 0,
-                line: 0,
-                col: 0,
-                utf8_col: 0,
-            },
+            tracker: T::new(),
+            mode_stack: vec![DEFAULT_MODE],
         }
     }
-}
 
-/// One "word" in the stream returned by the lexer.
-#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash)]
-pub struct Lexeme<'s> {
-    pub token: Token,
-    pub lexeme: &'s str,
-    /// The position just before the first character in the lexeme.
-    pub start: Position,
-    /// The position just after the last character in the lexeme.
-    pub end: Position,
-}
-
-#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash)]
-pub struct Position {
-    /// Byte offset from the beginning of the source string.
-    pub pos: usize,
-    /// Line number.
-    pub line: usize,
-    /// Column number, counted in bytes.
-    pub col: usize,
-    /// Column number, counted in utf8 codepoints.
-    pub utf8_col: usize,
-}
-
-impl fmt::Display for Position {
-    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
-        // TODO: col -> utf8_col
-        write!(f, "{}:{}", self.line, self.col)
+    /// The name that `mode` was given in [`LexerBuilder::mode`]. The default mode is called
+    /// `"default"`.
+    pub fn mode_name(&self, mode: Mode) -> &str {
+        &self.modes[mode].name
     }
-}
 
-impl Position {
-    fn advance(&mut self, ch: char) {
-        self.pos += ch.len_utf8();
-        if ch == '\n' {
-            self.col = 0;
-            self.utf8_col = 0;
-            self.line += 1;
+    /// The action that `token` performs on the mode stack when it is lexed.
+    fn action(&self, token: Token) -> ModeAction {
+        if token == LEX_ERROR {
+            ModeAction::Stay
         } else {
-            self.col += ch.len_utf8();
-            self.utf8_col += 1;
+            self.patterns[token].action
         }
     }
-}
-
-#[derive(Debug, Clone)]
-struct LexemeIter<'l, 's> {
-    position: Position,
-    // The _remaining, unlexed_ source text
-    source: &'s str,
-    lexer: &'l Lexer,
-}
 
-impl<'l, 's> LexemeIter<'l, 's> {
-    fn consume(&mut self, len: usize) -> (&'s str, Position, Position) {
-        let start = self.position;
-        for ch in self.source[..len].chars() {
-            self.position.advance(ch);
+    /// The name of `token`, for debugging and error messages. Set with [`LexerBuilder::name`];
+    /// by default it's the token's string in quotes (like `"("`), or its regex between slashes
+    /// (like `/[a-z]+/`). The name of `LEX_ERROR` is `LEX_ERROR`.
+    pub fn token_name(&self, token: Token) -> &str {
+        if token == LEX_ERROR {
+            "LEX_ERROR"
+        } else {
+            &self.patterns[token].name
         }
-        let end = self.position;
-
-        let lexeme = &self.source[..len];
-        self.source = &self.source[len..];
-        (lexeme, start, end)
     }
-}
 
-impl<'l, 's> Iterator for LexemeIter<'l, 's> {
-    type Item = Lexeme<'s>;
+    /// The first token with the given name, if any.
+    pub fn token(&self, name: &str) -> Option<Token> {
+        self.patterns
+            .iter()
+            .position(|pattern| pattern.name == name)
+    }
 
-    fn next(&mut self) -> Option<Lexeme<'s>> {
-        // Consume whitespace
-        if let Some(span) = self.lexer.whitespace.find(self.source) {
-            self.consume(span.end());
-        }
+    /// Whether `token` is for text matched by a skip pattern, rather than an ordinary token.
+    pub fn is_trivia(&self, token: Token) -> bool {
+        token != LEX_ERROR && self.patterns[token].kind != PatternKind::Token
+    }
 
-        // If we're at the end of the file, we're done.
-        if self.source.is_empty() {
-            return None;
-        }
+    /// Find the token with the best match at the start of `source`: the longest, with a
+    /// tie-breaker of is_str, then of being added first. Returns it and its length.
+    fn longest_match(&self, mode: &LexerMode, source: &str) -> Option<(Token, usize)> {
+        let regex_set = match &mode.matcher {
+            Matcher::RegexSet(regex_set) => regex_set,
+            Matcher::Dfa(dfa) => {
+                return dfa
+                    .longest_match(source)
+                    .map(|(index, len)| (mode.tokens[index], len))
+            }
+        };
 
-        // Find the best match (longest, with a tie-breaker of is_str)
         let mut best_match: Option<(Token, usize, bool)> = None;
-        for token in &self.lexer.regex_set.matches(self.source) {
-            let pattern = &self.lexer.patterns[token];
+        for index in &regex_set.matches(source) {
+            let token = mode.tokens[index];
+            let pattern = &self.patterns[token];
 
             // Find the length (and tie-breaker is_str) of this match.
             let (len, is_str) = if let Some(len) = pattern.length {
                 (len, true)
             } else {
-                (pattern.regex.find(self.source).unwrap().end(), false)
+                (pattern.match_len(source).unwrap(), false)
             };
 
             // If this is longer (or tie breaks) the best match so far, replace it.
@@ -255,9 +656,192 @@ impl<'l, 's> Iterator for LexemeIter<'l, 's> {
                 best_match = Some((token, len, is_str));
             }
         }
+        best_match.map(|(token, len, _)| (token, len))
+    }
+
+    /// If one of the skip patterns of `mode` matches at the start of `source`, return its token
+    /// and length. The token is `LEX_ERROR` if a nested skip pattern was never closed.
+    fn match_skip(&self, mode: &LexerMode, source: &str) -> Option<(Token, usize)> {
+        for token in &mode.skips {
+            let pattern = &self.patterns[*token];
+            let len = match &pattern.kind {
+                PatternKind::Token => unreachable!(),
+                PatternKind::Skip => match pattern.match_len(source) {
+                    Some(len) => len,
+                    None => continue,
+                },
+                PatternKind::SkipNested { open, close } => {
+                    if !source.starts_with(open.as_str()) {
+                        continue;
+                    }
+                    match nested_len(source, open, close) {
+                        Some(len) => len,
+                        None => return Some((LEX_ERROR, source.len())),
+                    }
+                }
+            };
+            if len > 0 {
+                return Some((*token, len));
+            }
+        }
+        None
+    }
+}
+
+impl Lexer {
+    /// The length of the `LEX_ERROR` lexeme at the start of `source`, where nothing matches.
+    fn error_len(&self, mode: &LexerMode, source: &str) -> usize {
+        match self.recovery {
+            // By definition we can't lex, but let's say the problem lies in the current chunk of
+            // non-basic-whitespace characters.
+            Recovery::SkipToWhitespace => {
+                let basic_whitespace = &[' ', '\t', '\r', '\n'];
+                source.find(basic_whitespace).unwrap_or(source.len())
+            }
+            Recovery::SkipChar => source.chars().next().unwrap().len_utf8(),
+            Recovery::SkipToToken => source
+                .char_indices()
+                .skip(1)
+                .map(|(i, _)| i)
+                .find(|i| self.matches_at(mode, &source[*i..]))
+                .unwrap_or(source.len()),
+        }
+    }
+
+    /// Whether whitespace, a skip pattern, or a token matches at the start of `source`.
+    fn matches_at(&self, mode: &LexerMode, source: &str) -> bool {
+        if let Some(whitespace) = &mode.whitespace {
+            if whitespace.find(source).is_some_and(|span| span.end() > 0) {
+                return true;
+            }
+        }
+        self.match_skip(mode, source).is_some()
+            || self
+                .longest_match(mode, source)
+                .is_some_and(|(_, len)| len > 0)
+    }
+}
+
+/// The length of the text at the start of `source` delimited by `open` and `close`, counting
+/// nesting. `source` must start with `open`. Returns `None` if it is never closed.
+fn nested_len(source: &str, open: &str, close: &str) -> Option<usize> {
+    let mut depth = 1;
+    let mut len = open.len();
+    while len < source.len() {
+        let rest = &source[len..];
+        if rest.starts_with(close) {
+            depth -= 1;
+            len += close.len();
+            if depth == 0 {
+                return Some(len);
+            }
+        } else if rest.starts_with(open) {
+            depth += 1;
+            len += open.len();
+        } else {
+            len += rest.chars().next().unwrap().len_utf8();
+        }
+    }
+    None
+}
+
+/// One "word" in the stream returned by the lexer. Its `start` and `end` are [`Position`]s,
+/// unless a different [`PositionTracker`] was passed to [`Lexer::lex_with`].
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub struct Lexeme<'s, P = Position> {
+    pub token: Token,
+    pub lexeme: &'s str,
+    /// The position just before the first character in the lexeme.
+    pub start: P,
+    /// The position just after the last character in the lexeme.
+    pub end: P,
+}
+
+/// The iterator returned by [`Lexer::lex`] and [`Lexer::lex_with`].
+#[derive(Debug, Clone)]
+pub struct LexemeIter<'l, 's, T: PositionTracker = EagerLineCol> {
+    full_source: &'s str,
+    // The _remaining, unlexed_ source text
+    source: &'s str,
+    lexer: &'l Lexer,
+    tracker: T,
+    // Never empty. The current mode is on top.
+    mode_stack: Vec<Mode>,
+}
+
+impl<'l, 's, T: PositionTracker> LexemeIter<'l, 's, T> {
+    fn consume(&mut self, len: usize) -> (&'s str, T::Position, T::Position) {
+        let start = self.tracker.position();
+        let lexeme = &self.source[..len];
+        self.tracker.advance(lexeme);
+        let end = self.tracker.position();
+
+        self.source = &self.source[len..];
+        (lexeme, start, end)
+    }
+}
+
+impl<'l, 's> LexemeIter<'l, 's, LazyLineCol> {
+    /// Get a [`LineAndColIndexer`] for the source text, which can compute line and column numbers
+    /// for the offsets in the lexemes. It only knows about the lines that have been lexed so far,
+    /// so this is typically called after the iterator is exhausted.
+    pub fn into_indexer(self) -> LineAndColIndexer<'s> {
+        let lexed = &self.full_source[..self.full_source.len() - self.source.len()];
+        LineAndColIndexer::from_raw_parts(lexed, self.tracker.newline_positions)
+    }
+}
+
+fn apply_mode_action(mode_stack: &mut Vec<Mode>, action: ModeAction) {
+    match action {
+        ModeAction::Stay => (),
+        ModeAction::Push(mode) => mode_stack.push(mode),
+        ModeAction::Pop => {
+            if mode_stack.len() > 1 {
+                mode_stack.pop();
+            }
+        }
+        ModeAction::Switch(mode) => *mode_stack.last_mut().unwrap() = mode,
+    }
+}
+
+impl<'l, 's, T: PositionTracker> Iterator for LexemeIter<'l, 's, T> {
+    type Item = Lexeme<'s, T::Position>;
+
+    fn next(&mut self) -> Option<Lexeme<'s, T::Position>> {
+        let lexer = self.lexer;
+        let mode = &lexer.modes[*self.mode_stack.last().unwrap()];
+
+        // Consume whitespace and skip patterns (possibly emitting the latter as trivia)
+        loop {
+            if let Some(whitespace) = &mode.whitespace {
+                if let Some(span) = whitespace.find(self.source) {
+                    self.consume(span.end());
+                }
+            }
+            match lexer.match_skip(mode, self.source) {
+                None => break,
+                Some((token, len)) => {
+                    let (lexeme, start, end) = self.consume(len);
+                    if token == LEX_ERROR || lexer.emit_trivia {
+                        return Some(Lexeme {
+                            token,
+                            lexeme,
+                            start,
+                            end,
+                        });
+                    }
+                }
+            }
+        }
+
+        // If we're at the end of the file, we're done.
+        if self.source.is_empty() {
+            return None;
+        }
 
         // If there was a best match, consume and return it.
-        if let Some((token, len, _)) = best_match {
+        if let Some((token, len)) = lexer.longest_match(mode, self.source) {
+            apply_mode_action(&mut self.mode_stack, lexer.action(token));
             let (lexeme, start, end) = self.consume(len);
             return Some(Lexeme {
                 token,
@@ -267,14 +851,8 @@ impl<'l, 's> Iterator for LexemeIter<'l, 's> {
             });
         }
 
-        // Otherwise, nothing matched. Lex error! By definition we can't lex, but let's say the
-        // problem lies in the current chunk of non-basic-whitespace characters.
-        let basic_whitespace = &[' ', '\t', '\r', '\n'];
-        let len = if let Some(len) = self.source.find(basic_whitespace) {
-            len
-        } else {
-            self.source.len()
-        };
+        // Otherwise, nothing matched. Lex error!
+        let len = lexer.error_len(mode, self.source);
         let (lexeme, start, end) = self.consume(len);
         Some(Lexeme {
             token: LEX_ERROR,
@@ -1,5 +1,3 @@
-//! An unnecessary abstraction. Don't use me.
-
 //! Compute line and column info for a source file.
 //!
 //! Upon construction, the `LineAndColIndexer` will scan the source file once for newlines. After
@@ -26,6 +24,28 @@
 //! // View all of "e"s line
 //! assert_eq!(counter.line_contents(1), " ef");
 //! ```
+//!
+//! Columns can also be counted in utf8 codepoints, UTF-16 code units (for the Language Server
+//! Protocol), or display width (for terminals), and converted between these:
+//!
+//! ```
+//! use lexer::line_and_col_indexer::LineAndColIndexer;
+//! use lexer::ColumnKind;
+//!
+//! let counter = LineAndColIndexer::new("a\n日本語 x😀 y");
+//! let y = counter.full_source().find('y').unwrap();
+//! assert_eq!(counter.col(y), 16);
+//! assert_eq!(counter.utf8_col(y), 7);
+//! assert_eq!(counter.utf16_col(y), 8);
+//! assert_eq!(counter.display_col(y), 11);
+//! assert_eq!(counter.offset(1, 8, ColumnKind::Utf16), y);
+//! assert_eq!(counter.convert_col(1, 11, ColumnKind::Display, ColumnKind::Utf16), 8);
+//! ```
+//!
+//! [`Lexer::lex_with::<LazyLineCol>`](crate::Lexer::lex_with) produces one of these as a side
+//! effect of lexing, via `into_indexer()`.
+
+use crate::{ColumnKind, Position};
 
 /// A store of newline locations within a source text, for the purpose of quickly computing line
 /// and column positions.
@@ -35,23 +55,9 @@ pub struct LineAndColIndexer<'s> {
     newline_positions: Vec<Offset>,
 }
 
-pub type Line = u32;
-pub type Col = u32;
+/// A byte offset into the source text.
 pub type Offset = usize;
 
-#[derive(Debug, Clone, Copy, PartialEq, Eq)]
-pub struct Pos {
-    pub(crate) line: Line,
-    pub(crate) col: Col,
-    pub(crate) utf8_col: Col,
-}
-
-#[derive(Debug, Clone, Copy, PartialEq, Eq)]
-pub struct Span {
-    pub start: Pos,
-    pub end: Pos,
-}
-
 impl<'s> LineAndColIndexer<'s> {
     /// Construct a line counter for the source file. This will scan the file for newlines, to
     /// allow all further operations to be O(1).
@@ -73,7 +79,7 @@ impl<'s> LineAndColIndexer<'s> {
     pub(crate) fn from_raw_parts(
         source: &'s str,
         newline_positions: Vec<Offset>,
-    ) -> LineAndColIndexer {
+    ) -> LineAndColIndexer<'s> {
         LineAndColIndexer {
             source,
             newline_positions,
@@ -90,77 +96,101 @@ impl<'s> LineAndColIndexer<'s> {
         self.newline_positions.len() - 1
     }
 
-    pub fn source(&self, span: Span) -> &'s str {
-        &self.source[self.offset(span.start)..self.offset(span.end)]
-    }
-
-    pub fn start_line(&self, span: Span) -> Line {
-        span.start.line
-    }
-
-    pub fn end_line(&self, span: Span) -> Line {
-        span.end.line
-    }
-
-    pub fn start_col(&self, span: Span) -> Col {
-        span.start.col
+    /// Get the line number of a position (byte index) within the source. See `line_col`.
+    pub fn line(&self, pos: Offset) -> usize {
+        self.line_col(pos).0
     }
 
-    pub fn end_col(&self, span: Span) -> Col {
-        span.start.col
+    /// Get the column of a position (byte index) within the source, counted in bytes. See
+    /// `line_col`.
+    pub fn col(&self, pos: Offset) -> usize {
+        self.line_col(pos).1
     }
 
-    pub fn start_offset(&self, span: Span) -> Offset {
-        self.offset(span.start)
+    /// Get the column of a position (byte index) within the source, counted in utf8 codepoints.
+    /// This takes time proportional to the length of the line.
+    pub fn utf8_col(&self, pos: Offset) -> usize {
+        self.column(pos, ColumnKind::Utf8)
     }
 
-    pub fn end_offset(&self, span: Span) -> Offset {
-        self.offset(span.start)
+    /// Get the column of a position (byte index) within the source, counted in UTF-16 code
+    /// units. This takes time proportional to the length of the line.
+    pub fn utf16_col(&self, pos: Offset) -> usize {
+        self.column(pos, ColumnKind::Utf16)
     }
 
-    pub fn start_utf8_col(&self, span: Span) -> Col {
-        span.start.utf8_col
+    /// Get the column of a position (byte index) within the source, counted in display width.
+    /// This takes time proportional to the length of the line.
+    pub fn display_col(&self, pos: Offset) -> usize {
+        self.column(pos, ColumnKind::Display)
     }
 
-    pub fn end_utf8_col(&self, span: Span) -> Col {
-        span.end.utf8_col
+    /// Get the column of a position (byte index) within the source, counted in the given way.
+    /// This takes time proportional to the length of the line, except for `ColumnKind::Byte`.
+    pub fn column(&self, pos: Offset, kind: ColumnKind) -> usize {
+        let (line, col) = self.line_col(pos);
+        match kind {
+            ColumnKind::Byte => col,
+            _ => self.source[self.newline_positions[line]..pos]
+                .chars()
+                .map(|ch| kind.width(ch))
+                .sum(),
+        }
     }
 
-    fn offset(&self, pos: Pos) -> Offset {
-        self.newline_positions[pos.line as usize] + pos.col as usize
+    /// Get the position (byte index) of the `col`th column of the `line_no`th line, where the
+    /// column is counted in the given way. This is the inverse of `column`.
+    ///
+    /// A column past the end of the line gives the end of the line (before the line termination
+    /// character(s)). A column in the middle of a character (like the second half of a wide
+    /// character) gives the start of that character. Zero-width characters are kept with the
+    /// character before them.
+    ///
+    /// # Panics
+    ///
+    /// Panics if there are fewer than `line_no` lines.
+    pub fn offset(&self, line_no: usize, col: usize, kind: ColumnKind) -> Offset {
+        let (start, end) = self.line_span(line_no);
+        let mut width_so_far = 0;
+        for (i, ch) in self.source[start..end].char_indices() {
+            let width = kind.width(ch);
+            if (width_so_far == col && width > 0) || width_so_far + width > col {
+                return start + i;
+            }
+            width_so_far += width;
+        }
+        end
     }
 
-    /*
-    /// Lookup the position (line&col) of the start of a substring within the source string.
-    pub fn start(&self, lexeme: &str) -> Pos {
-        let start = self.offset(lexeme);
-        let (line, col) = self.line_col(start);
-        Pos {
-            offset: start,
-            line,
-            col,
-        }
+    /// Convert a column of the `line_no`th line from one way of counting to another.
+    ///
+    /// # Panics
+    ///
+    /// Panics if there are fewer than `line_no` lines.
+    pub fn convert_col(
+        &self,
+        line_no: usize,
+        col: usize,
+        from: ColumnKind,
+        to: ColumnKind,
+    ) -> usize {
+        self.column(self.offset(line_no, col, from), to)
     }
 
-    /// Lookup the position (line&col) of the end of a substring within the source string.
-    pub fn end(&self, lexeme: &str) -> Pos {
-        let start = self.offset(lexeme);
-        let end = start + lexeme.len();
-        let (line, col) = self.line_col(end);
-        Pos {
-            offset: end,
+    /// Get the full [`Position`] of a position (byte index) within the source: the same one that
+    /// [`Lexer::lex`](crate::Lexer::lex) would give.
+    pub fn position(&self, pos: Offset) -> Position {
+        let (line, col) = self.line_col(pos);
+        Position {
+            pos,
             line,
             col,
+            utf8_col: self.utf8_col(pos),
+            utf16_col: self.utf16_col(pos),
+            display_col: self.display_col(pos),
         }
     }
 
-    pub fn offset(&self, lexeme: &str) -> usize {
-        let source_ptr = self.source as *const str as *const u8 as usize;
-        let start_ptr = lexeme as *const str as *const u8 as usize;
-        start_ptr - source_ptr
-    }
-    */
-
     /// Get the line and column of a position (byte index) within the source. `pos` is relative to
     /// the start of the `source` string. A newline or return character is considered part of the
     /// line it ends.
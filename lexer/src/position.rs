@@ -0,0 +1,199 @@
+//! Strategies for keeping track of where lexemes are in the source.
+//!
+//! Computing line and column numbers for every lexeme has a cost, which you might not want to pay
+//! if you only need them for the occasional error message. So [`Lexer::lex_with`] takes a type
+//! parameter saying how to track positions:
+//!
+//! - [`EagerLineCol`] (what [`Lexer::lex`] uses) gives every lexeme a full [`Position`].
+//! - [`LazyLineCol`] gives every lexeme its byte offset, and records where the newlines are as it
+//!   goes. Afterwards, `LexemeIter::into_indexer` gives a [`LineAndColIndexer`] that can compute
+//!   the line and column of any offset.
+//! - [`NoPositions`] doesn't track positions at all.
+//!
+//! ```
+//! use lexer::{LexerBuilder, LazyLineCol, NoPositions};
+//!
+//! let mut builder = LexerBuilder::new(r#"[ \t\r\n]+"#).unwrap();
+//! builder.regex("[a-zA-Z_]+").unwrap();
+//! let lexer = builder.finish().unwrap();
+//!
+//! let lexemes = lexer.lex_with::<NoPositions>("x\n y").collect::<Vec<_>>();
+//! assert_eq!(lexemes[1].lexeme, "y");
+//!
+//! let mut iter = lexer.lex_with::<LazyLineCol>("x\n y");
+//! let lexemes = (&mut iter).collect::<Vec<_>>();
+//! assert_eq!(lexemes[1].start, 3);
+//! let indexer = iter.into_indexer();
+//! assert_eq!(indexer.line_col(lexemes[1].start), (1, 1));
+//! ```
+//!
+//! [`Lexer::lex_with`]: crate::Lexer::lex_with
+//! [`Lexer::lex`]: crate::Lexer::lex
+//! [`LineAndColIndexer`]: crate::line_and_col_indexer::LineAndColIndexer
+
+use crate::line_and_col_indexer::Offset;
+use std::fmt;
+use unicode_width::UnicodeWidthChar;
+
+/// A position-tracking strategy, used by the lexer to compute the `start` and `end` of each
+/// lexeme.
+pub trait PositionTracker {
+    /// The type of a lexeme's `start` and `end`.
+    type Position: fmt::Debug + Clone + Copy + PartialEq + Eq;
+
+    /// Begin tracking at the start of a source text.
+    fn new() -> Self;
+
+    /// Move past `text`, which has just been lexed.
+    fn advance(&mut self, text: &str);
+
+    /// The current position.
+    fn position(&self) -> Self::Position;
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash, Default)]
+pub struct Position {
+    /// Byte offset from the beginning of the source string.
+    pub pos: usize,
+    /// Line number.
+    pub line: usize,
+    /// Column number, counted in bytes.
+    pub col: usize,
+    /// Column number, counted in utf8 codepoints.
+    pub utf8_col: usize,
+    /// Column number, counted in UTF-16 code units, as used by the Language Server Protocol.
+    pub utf16_col: usize,
+    /// Column number, counted in display width: wide characters (like CJK ideographs) count as
+    /// two, and zero-width characters (like combining accents) as zero.
+    pub display_col: usize,
+}
+
+/// The ways of counting columns, one for each column field of [`Position`].
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum ColumnKind {
+    /// Bytes (`Position::col`).
+    Byte,
+    /// Utf8 codepoints (`Position::utf8_col`).
+    Utf8,
+    /// UTF-16 code units (`Position::utf16_col`).
+    Utf16,
+    /// Display width (`Position::display_col`).
+    Display,
+}
+
+impl ColumnKind {
+    /// How many columns `ch` takes up.
+    pub fn width(self, ch: char) -> usize {
+        match self {
+            ColumnKind::Byte => ch.len_utf8(),
+            ColumnKind::Utf8 => 1,
+            ColumnKind::Utf16 => ch.len_utf16(),
+            ColumnKind::Display => ch.width().unwrap_or(0),
+        }
+    }
+}
+
+impl fmt::Display for Position {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        // TODO: col -> utf8_col
+        write!(f, "{}:{}", self.line, self.col)
+    }
+}
+
+impl Position {
+    /// The column, counted in the given way.
+    pub fn column(&self, kind: ColumnKind) -> usize {
+        match kind {
+            ColumnKind::Byte => self.col,
+            ColumnKind::Utf8 => self.utf8_col,
+            ColumnKind::Utf16 => self.utf16_col,
+            ColumnKind::Display => self.display_col,
+        }
+    }
+
+    pub(crate) fn advance(&mut self, ch: char) {
+        self.pos += ch.len_utf8();
+        if ch == '\n' {
+            self.col = 0;
+            self.utf8_col = 0;
+            self.utf16_col = 0;
+            self.display_col = 0;
+            self.line += 1;
+        } else {
+            self.col += ch.len_utf8();
+            self.utf8_col += 1;
+            self.utf16_col += ch.len_utf16();
+            self.display_col += ColumnKind::Display.width(ch);
+        }
+    }
+}
+
+/// Compute the full [`Position`] (offset, line, and columns) of every lexeme as it's lexed.
+#[derive(Debug, Clone)]
+pub struct EagerLineCol(pub(crate) Position);
+
+impl PositionTracker for EagerLineCol {
+    type Position = Position;
+
+    fn new() -> EagerLineCol {
+        EagerLineCol(Position::default())
+    }
+
+    fn advance(&mut self, text: &str) {
+        for ch in text.chars() {
+            self.0.advance(ch);
+        }
+    }
+
+    fn position(&self) -> Position {
+        self.0
+    }
+}
+
+/// Give each lexeme its byte offset, and remember where the newlines are so that line and column
+/// numbers can be computed later, by a `LineAndColIndexer`.
+#[derive(Debug, Clone)]
+pub struct LazyLineCol {
+    offset: Offset,
+    pub(crate) newline_positions: Vec<Offset>,
+}
+
+impl PositionTracker for LazyLineCol {
+    type Position = Offset;
+
+    fn new() -> LazyLineCol {
+        LazyLineCol {
+            offset: 0,
+            newline_positions: vec![0],
+        }
+    }
+
+    fn advance(&mut self, text: &str) {
+        for (i, byte) in text.bytes().enumerate() {
+            if byte == b'\n' {
+                self.newline_positions.push(self.offset + i + 1);
+            }
+        }
+        self.offset += text.len();
+    }
+
+    fn position(&self) -> Offset {
+        self.offset
+    }
+}
+
+/// Don't track positions at all.
+#[derive(Debug, Clone)]
+pub struct NoPositions;
+
+impl PositionTracker for NoPositions {
+    type Position = ();
+
+    fn new() -> NoPositions {
+        NoPositions
+    }
+
+    fn advance(&mut self, _text: &str) {}
+
+    fn position(&self) {}
+}
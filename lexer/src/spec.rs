@@ -0,0 +1,256 @@
+//! Lexer definitions written as text, so that they can live in data files.
+//!
+//! A spec has one definition per line. Patterns are written between backticks (a doubled backtick
+//! stands for a literal one), and `#` starts a comment. No token can be called `mode`:
+//!
+//! ```text
+//! # Declare a mode, and the whitespace it skips (if any). The default mode is called `default`,
+//! # and skips no whitespace unless it's declared too.
+//! mode default `[ \t\r\n]+`
+//! mode string
+//!
+//! # NAME KIND PATTERN [in MODE] [push MODE | pop | switch MODE]
+//! Ident   regex   `[a-zA-Z_]+`
+//! Plus    string  `+`
+//! Quote   string  `"`  push string
+//! Text    regex   `[^"]+`  in string
+//! EndQuote string `"`  in string  pop
+//!
+//! # Skip patterns take no mode action.
+//! Comment skip        `//[^\n]*`
+//! Block   skip_nested `/*` `*/`
+//! ```
+//!
+//! The kinds correspond to the `LexerBuilder` methods of the same names. Each token is named
+//! after its definition (see [`Lexer::token_name`] and [`Lexer::token`]):
+//!
+//! ```
+//! use lexer::LexerBuilder;
+//!
+//! let spec = "
+//!     mode default `[ \\t\\r\\n]+`
+//!     Ident regex `[a-zA-Z_]+`
+//!     Plus string `+`
+//! ";
+//! let lexer = LexerBuilder::from_spec(spec).unwrap().finish().unwrap();
+//! let plus = lexer.token("Plus").unwrap();
+//! let tokens = lexer.lex("x + y").map(|l| l.token).collect::<Vec<_>>();
+//! assert_eq!(tokens[1], plus);
+//! assert_eq!(lexer.token_name(tokens[0]), "Ident");
+//! ```
+//!
+//! [`Lexer::token_name`]: crate::Lexer::token_name
+//! [`Lexer::token`]: crate::Lexer::token
+
+use crate::{new_regex, Backend, LexerBuilder, Mode, ModeAction, ModeSpec, Recovery, Token};
+use std::fmt;
+
+/// An error in a lexer spec, with the (1-indexed) line it's on.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct SpecError {
+    pub line: usize,
+    pub message: String,
+}
+
+impl fmt::Display for SpecError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "lexer spec line {}: {}", self.line, self.message)
+    }
+}
+
+impl std::error::Error for SpecError {}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+enum Field {
+    Word(String),
+    Pattern(String),
+}
+
+impl LexerBuilder {
+    /// Start building a lexer from a spec, as described in the [`spec`](crate::spec) module.
+    /// You can keep adding patterns (and set options like the backend) before calling `finish`.
+    pub fn from_spec(spec: &str) -> Result<LexerBuilder, SpecError> {
+        let mut builder = LexerBuilder {
+            modes: vec![ModeSpec {
+                name: "default".to_owned(),
+                whitespace: None,
+            }],
+            patterns: vec![],
+            emit_trivia: false,
+            backend: Backend::default(),
+            recovery: Recovery::default(),
+            check_patterns: true,
+        };
+        // The name, token, and line of each definition so far.
+        let mut defined = Vec::<(String, Token, usize)>::new();
+        for (i, line) in spec.lines().enumerate() {
+            let err = |message: String| SpecError {
+                line: i + 1,
+                message,
+            };
+            let fields = split_fields(line).map_err(err)?;
+            let token = builder.add_definition(&fields).map_err(err)?;
+            if let (Some(token), Some(Field::Word(name))) = (token, fields.first()) {
+                if defined.iter().any(|(other, _, _)| other == name) {
+                    return Err(err(format!("token `{}` is already defined", name)));
+                }
+                // Identical patterns are merged into one token, which can only have one name.
+                if let Some((other, _, other_line)) = defined
+                    .iter()
+                    .find(|(_, other_token, _)| *other_token == token)
+                {
+                    return Err(err(format!(
+                        "token `{}` has the same pattern as `{}` on line {}",
+                        name, other, other_line
+                    )));
+                }
+                builder.name(token, name);
+                defined.push((name.clone(), token, i + 1));
+            }
+        }
+        Ok(builder)
+    }
+
+    /// Add one line of a spec. Returns the token it defined, if any.
+    fn add_definition(&mut self, fields: &[Field]) -> Result<Option<Token>, String> {
+        let (name, kind, rest) = match fields {
+            [] => return Ok(None),
+            [Field::Word(name), Field::Word(kind), rest @ ..] => (name, kind, rest),
+            [Field::Word(_), ..] => return Err("expected a kind".to_owned()),
+            [Field::Pattern(_), ..] => return Err("expected a name".to_owned()),
+        };
+
+        if name == "mode" {
+            let whitespace = match rest {
+                [] => None,
+                [Field::Pattern(regex)] => Some(regex.as_str()),
+                _ => return Err("expected a whitespace pattern".to_owned()),
+            };
+            if kind == "default" {
+                self.modes[0].whitespace = match whitespace {
+                    Some(regex) => Some(new_regex(regex).map_err(|err| err.to_string())?),
+                    None => None,
+                };
+            } else if self.mode_named(kind).is_some() {
+                return Err(format!("mode `{}` is already defined", kind));
+            } else {
+                self.mode(kind, whitespace).map_err(|err| err.to_string())?;
+            }
+            return Ok(None);
+        }
+
+        let num_patterns = if kind == "skip_nested" { 2 } else { 1 };
+        if rest.len() < num_patterns {
+            return Err(format!(
+                "expected {} pattern(s) after `{}`",
+                num_patterns, kind
+            ));
+        }
+        let mut patterns = vec![];
+        for field in &rest[..num_patterns] {
+            match field {
+                Field::Pattern(pattern) => patterns.push(pattern.as_str()),
+                Field::Word(word) => return Err(format!("expected a pattern, found `{}`", word)),
+            }
+        }
+        let (mode, action) = self.parse_mode_and_action(&rest[num_patterns..])?;
+
+        let regex_err = |err: regex::Error| err.to_string();
+        let token = match kind.as_str() {
+            "string" => self
+                .string_in(mode, patterns[0], action)
+                .map_err(regex_err)?,
+            "regex" => self
+                .regex_in(mode, patterns[0], action)
+                .map_err(regex_err)?,
+            "skip" | "skip_nested" if action != ModeAction::Stay => {
+                return Err("skip patterns can't change the mode".to_owned())
+            }
+            "skip" => self.skip_in(mode, patterns[0]).map_err(regex_err)?,
+            "skip_nested" => {
+                let (open, close) = (patterns[0], patterns[1]);
+                if open.is_empty() || close.is_empty() || open == close {
+                    return Err("nested skip delimiters must be distinct and non-empty".to_owned());
+                }
+                self.skip_nested_in(mode, open, close)
+            }
+            _ => {
+                return Err(format!(
+                    "unknown kind `{}`: expected string, regex, skip, or skip_nested",
+                    kind
+                ))
+            }
+        };
+        Ok(Some(token))
+    }
+
+    /// Parse `[in MODE] [push MODE | pop | switch MODE]`.
+    fn parse_mode_and_action(&self, fields: &[Field]) -> Result<(Mode, ModeAction), String> {
+        let mut words = vec![];
+        for field in fields {
+            match field {
+                Field::Word(word) => words.push(word.as_str()),
+                Field::Pattern(_) => return Err("unexpected pattern".to_owned()),
+            }
+        }
+        let find_mode = |name: &str| {
+            self.mode_named(name)
+                .ok_or_else(|| format!("mode `{}` is not defined", name))
+        };
+
+        let (mode, words) = match words.as_slice() {
+            ["in", mode, words @ ..] => (find_mode(mode)?, words),
+            words => (crate::DEFAULT_MODE, words),
+        };
+        let action = match words {
+            [] => ModeAction::Stay,
+            ["push", mode] => ModeAction::Push(find_mode(mode)?),
+            ["pop"] => ModeAction::Pop,
+            ["switch", mode] => ModeAction::Switch(find_mode(mode)?),
+            [word, ..] => return Err(format!("unexpected `{}`", word)),
+        };
+        Ok((mode, action))
+    }
+
+    fn mode_named(&self, name: &str) -> Option<Mode> {
+        self.modes.iter().position(|mode| mode.name == name)
+    }
+}
+
+/// Split a line into words and backtick-delimited patterns, stopping at a comment.
+fn split_fields(line: &str) -> Result<Vec<Field>, String> {
+    let mut fields = vec![];
+    let mut chars = line.chars().peekable();
+    while let Some(ch) = chars.next() {
+        if ch.is_whitespace() {
+            continue;
+        } else if ch == '#' {
+            break;
+        } else if ch == '`' {
+            let mut pattern = String::new();
+            loop {
+                match chars.next() {
+                    None => return Err("unterminated pattern".to_owned()),
+                    Some('`') if chars.peek() == Some(&'`') => {
+                        chars.next();
+                        pattern.push('`');
+                    }
+                    Some('`') => break,
+                    Some(ch) => pattern.push(ch),
+                }
+            }
+            fields.push(Field::Pattern(pattern));
+        } else {
+            let mut word = ch.to_string();
+            while let Some(ch) = chars.peek() {
+                if ch.is_whitespace() || *ch == '`' || *ch == '#' {
+                    break;
+                }
+                word.push(*ch);
+                chars.next();
+            }
+            fields.push(Field::Word(word));
+        }
+    }
+    Ok(fields)
+}
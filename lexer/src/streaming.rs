@@ -0,0 +1,218 @@
+//! Lexing from an `io::Read`, for inputs too large to hold in memory.
+//!
+//! [`Lexer::lex_reader`] reads its input a chunk at a time into a buffer, and yields
+//! [`OwnedLexeme`]s, with the same tokens and positions that [`Lexer::lex`] would give for the
+//! whole input. Only the unlexed tail of the input is kept in memory; the buffer grows as needed
+//! to hold a single long lexeme.
+//!
+//! ```
+//! use lexer::LexerBuilder;
+//!
+//! let mut builder = LexerBuilder::new(r#"[ \t\r\n]+"#).unwrap();
+//! builder.regex("[a-zA-Z_]+").unwrap();
+//! builder.string("+").unwrap();
+//! let lexer = builder.finish().unwrap();
+//!
+//! let input = "x + long_name\n+ y".as_bytes();
+//! let lexemes = lexer
+//!     .lex_reader_with_capacity(4, input)
+//!     .collect::<Result<Vec<_>, _>>()
+//!     .unwrap();
+//! assert_eq!(lexemes[2].lexeme, "long_name");
+//! assert_eq!(lexemes[3].start.line, 1);
+//! ```
+//!
+//! A lexeme is only yielded once the lexeme after it has been fully read, since more input could
+//! change how it lexes. Like [`incremental`](crate::incremental), this assumes that whether a
+//! token matches never depends on text more than one lexeme past where its match ends.
+//!
+//! [`Lexer::lex`]: crate::Lexer::lex
+
+use crate::{EagerLineCol, Lexeme, LexemeIter, Lexer, Mode, Position, Token, DEFAULT_MODE};
+use std::collections::VecDeque;
+use std::io::{self, Read};
+
+const DEFAULT_CAPACITY: usize = 8 * 1024;
+
+/// Like a [`Lexeme`], but owning its text.
+#[derive(Debug, Clone, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub struct OwnedLexeme {
+    pub token: Token,
+    pub lexeme: String,
+    /// The position just before the first character in the lexeme.
+    pub start: Position,
+    /// The position just after the last character in the lexeme.
+    pub end: Position,
+}
+
+impl<'s> From<Lexeme<'s>> for OwnedLexeme {
+    fn from(lexeme: Lexeme<'s>) -> OwnedLexeme {
+        OwnedLexeme {
+            token: lexeme.token,
+            lexeme: lexeme.lexeme.to_owned(),
+            start: lexeme.start,
+            end: lexeme.end,
+        }
+    }
+}
+
+/// The iterator returned by [`Lexer::lex_reader`]. It yields an error if reading fails, or if
+/// the input isn't valid UTF-8, and then stops.
+#[derive(Debug)]
+pub struct StreamingLexemeIter<'l, R: Read> {
+    lexer: &'l Lexer,
+    reader: R,
+    /// How many bytes to read at a time. Doubles whenever a read doesn't complete a lexeme.
+    chunk_size: usize,
+    /// Bytes that have been read but not yet decoded: the start of a UTF-8 character that was
+    /// split across reads.
+    bytes: Vec<u8>,
+    /// The text that has been read but not yet lexed.
+    buffer: String,
+    /// The position of the start of `buffer`.
+    position: Position,
+    /// The mode stack at the start of `buffer`.
+    mode_stack: Vec<Mode>,
+    /// Lexemes that have been lexed but not yet yielded.
+    ready: VecDeque<OwnedLexeme>,
+    eof: bool,
+    failed: bool,
+}
+
+impl Lexer {
+    /// Lex the text read from `reader`, which must be UTF-8. There's no need to wrap it in a
+    /// `BufReader`.
+    pub fn lex_reader<R: Read>(&self, reader: R) -> StreamingLexemeIter<'_, R> {
+        self.lex_reader_with_capacity(DEFAULT_CAPACITY, reader)
+    }
+
+    /// Like [`Lexer::lex_reader`], but reading `capacity` bytes at a time (to begin with).
+    ///
+    /// # Panics
+    ///
+    /// Panics if `capacity` is 0.
+    pub fn lex_reader_with_capacity<R: Read>(
+        &self,
+        capacity: usize,
+        reader: R,
+    ) -> StreamingLexemeIter<'_, R> {
+        assert!(capacity > 0, "Capacity must be positive");
+        StreamingLexemeIter {
+            lexer: self,
+            reader,
+            chunk_size: capacity,
+            bytes: vec![],
+            buffer: String::new(),
+            position: Position::default(),
+            mode_stack: vec![DEFAULT_MODE],
+            ready: VecDeque::new(),
+            eof: false,
+            failed: false,
+        }
+    }
+}
+
+impl<'l, R: Read> StreamingLexemeIter<'l, R> {
+    /// Read another chunk of input onto the end of `buffer`. Reads `chunk_size` bytes, unless the
+    /// input ends first.
+    fn fill(&mut self) -> io::Result<()> {
+        let start = self.bytes.len();
+        self.bytes.resize(start + self.chunk_size, 0);
+        let mut len = 0;
+        while len < self.chunk_size {
+            match self.reader.read(&mut self.bytes[start + len..]) {
+                Ok(0) => {
+                    self.eof = true;
+                    break;
+                }
+                Ok(read_len) => len += read_len,
+                Err(err) if err.kind() == io::ErrorKind::Interrupted => continue,
+                Err(err) => {
+                    self.bytes.truncate(start + len);
+                    return Err(err);
+                }
+            }
+        }
+        self.bytes.truncate(start + len);
+
+        let valid_len = match std::str::from_utf8(&self.bytes) {
+            Ok(_) => self.bytes.len(),
+            // The input ends partway through a character, which the next read should complete.
+            Err(err) if err.error_len().is_none() && !self.eof => err.valid_up_to(),
+            Err(err) => return Err(io::Error::new(io::ErrorKind::InvalidData, err)),
+        };
+        self.buffer
+            .push_str(std::str::from_utf8(&self.bytes[..valid_len]).unwrap());
+        self.bytes.drain(..valid_len);
+        Ok(())
+    }
+
+    /// Lex as much of `buffer` as is certain not to change when more input is read, moving the
+    /// lexemes into `ready`. Returns whether any progress was made.
+    fn lex_buffer(&mut self) -> bool {
+        let mut iter = LexemeIter {
+            full_source: &self.buffer,
+            source: &self.buffer,
+            lexer: self.lexer,
+            tracker: EagerLineCol(self.position),
+            mode_stack: self.mode_stack.clone(),
+        };
+        let buffer_end = self.position.pos + self.buffer.len();
+
+        // The most recent lexeme, which can't be yielded until the one after it is found, and
+        // the mode stack just after it.
+        let mut pending: Option<(Lexeme, Vec<Mode>)> = None;
+        // Where the lexemes that are ready end, and the mode stack there.
+        let mut done = None;
+        while let Some(lexeme) = iter.next() {
+            if !self.eof && lexeme.end.pos == buffer_end {
+                // More input could make this lexeme longer.
+                break;
+            }
+            if let Some((prev, mode_stack)) = pending.take() {
+                done = Some((prev.end, mode_stack));
+                self.ready.push_back(prev.into());
+            }
+            pending = Some((lexeme, iter.mode_stack.clone()));
+        }
+        if self.eof {
+            if let Some((prev, _)) = pending {
+                self.ready.push_back(prev.into());
+            }
+            done = Some((iter.tracker.0, iter.mode_stack));
+        }
+
+        match done {
+            None => false,
+            Some((position, mode_stack)) => {
+                self.buffer.drain(..position.pos - self.position.pos);
+                self.position = position;
+                self.mode_stack = mode_stack;
+                true
+            }
+        }
+    }
+}
+
+impl<'l, R: Read> Iterator for StreamingLexemeIter<'l, R> {
+    type Item = io::Result<OwnedLexeme>;
+
+    fn next(&mut self) -> Option<io::Result<OwnedLexeme>> {
+        loop {
+            if let Some(lexeme) = self.ready.pop_front() {
+                return Some(Ok(lexeme));
+            }
+            if self.failed || (self.eof && self.buffer.is_empty()) {
+                return None;
+            }
+            if let Err(err) = self.fill() {
+                self.failed = true;
+                return Some(Err(err));
+            }
+            if !self.lex_buffer() && !self.eof {
+                // A single lexeme is longer than what we've read; read more at a time.
+                self.chunk_size *= 2;
+            }
+        }
+    }
+}
@@ -1,6 +1,35 @@
+use lexer::line_and_col_indexer::LineAndColIndexer;
+use lexer::{LazyLineCol, Lexeme, Lexer, NoPositions};
+
+/// Lex `source` with every position-tracking strategy, check that they agree, and return the
+/// lexemes with full positions.
+fn lex_all<'s>(lexer: &Lexer, source: &'s str) -> Vec<Lexeme<'s>> {
+    let eager = lexer.lex(source).collect::<Vec<_>>();
+
+    let mut iter = lexer.lex_with::<LazyLineCol>(source);
+    let lazy = (&mut iter).collect::<Vec<_>>();
+    let indexer = iter.into_indexer();
+    let full_indexer = LineAndColIndexer::new(source);
+    assert_eq!(lazy.len(), eager.len());
+    for (lazy, eager) in lazy.iter().zip(&eager) {
+        assert_eq!((lazy.token, lazy.lexeme), (eager.token, eager.lexeme));
+        assert_eq!(indexer.position(lazy.start), eager.start);
+        assert_eq!(indexer.position(lazy.end), eager.end);
+        assert_eq!(full_indexer.position(lazy.end), eager.end);
+    }
+
+    let none = lexer.lex_with::<NoPositions>(source).collect::<Vec<_>>();
+    assert_eq!(none.len(), eager.len());
+    for (none, eager) in none.iter().zip(&eager) {
+        assert_eq!((none.token, none.lexeme), (eager.token, eager.lexeme));
+    }
+
+    eager
+}
+
 #[test]
 fn test_lexer() {
-    use lexer::{Lexeme, LexerBuilder, LEX_ERROR};
+    use lexer::{LexerBuilder, LEX_ERROR};
 
     fn show(lexeme: Option<Lexeme>) -> String {
         if let Some(lexeme) = lexeme {
@@ -32,12 +61,12 @@ fn test_lexer() {
     let lexer = builder.finish().unwrap();
 
     let source = "raised";
-    let mut lexemes = lexer.lex(source);
+    let mut lexemes = lex_all(&lexer, source).into_iter();
     assert_eq!(show(lexemes.next()), "0 raised 0:0-0:6");
     assert_eq!(show(lexemes.next()), "None");
 
     let source = "raise(my_error)";
-    let mut lexemes = lexer.lex(source);
+    let mut lexemes = lex_all(&lexer, source).into_iter();
     assert_eq!(show(lexemes.next()), "1 raise 0:0-0:5");
     assert_eq!(show(lexemes.next()), "2 ( 0:5-0:6");
     assert_eq!(show(lexemes.next()), "0 my_error 0:6-0:14");
@@ -45,42 +74,638 @@ fn test_lexer() {
     assert_eq!(show(lexemes.next()), "None");
 
     let source = "x\n$$ !";
-    let mut lexemes = lexer.lex(source);
+    let mut lexemes = lex_all(&lexer, source).into_iter();
     assert_eq!(show(lexemes.next()), "0 x 0:0-0:1");
     assert_eq!(show(lexemes.next()), "ERR $$ 1:0-1:2");
     assert_eq!(show(lexemes.next()), "ERR ! 1:3-1:4");
     assert_eq!(show(lexemes.next()), "None");
 }
 
-/* TODO: update
 #[test]
-fn test_lexer_without_line_col() {
-    use lexer::lexer_without_line_col::{LexerBuilder, LEX_ERROR};
+fn test_lexer_modes() {
+    use lexer::{LexerBuilder, ModeAction, DEFAULT_MODE};
 
     let mut builder = LexerBuilder::new(r#"[ \t\r\n]+"#).unwrap();
+    let string_mode = builder.mode("string", None).unwrap();
+    let heredoc_mode = builder.mode("heredoc", None).unwrap();
+
+    builder.regex("[a-zA-Z_]+").unwrap();
+    builder
+        .string_in(DEFAULT_MODE, "\"", ModeAction::Push(string_mode))
+        .unwrap();
+    builder
+        .string_in(DEFAULT_MODE, "{", ModeAction::Push(DEFAULT_MODE))
+        .unwrap();
+    builder
+        .string_in(DEFAULT_MODE, "}", ModeAction::Pop)
+        .unwrap();
+    builder
+        .string_in(DEFAULT_MODE, "<<<", ModeAction::Switch(heredoc_mode))
+        .unwrap();
+    builder
+        .string_in(string_mode, "\"", ModeAction::Pop)
+        .unwrap();
+    builder
+        .string_in(string_mode, "${", ModeAction::Push(DEFAULT_MODE))
+        .unwrap();
+    builder
+        .regex_in(string_mode, r#"([^"$]|\$[^{"])+"#, ModeAction::Stay)
+        .unwrap();
+    builder
+        .regex_in(heredoc_mode, r#"(.|\n)*"#, ModeAction::Stay)
+        .unwrap();
+    let lexer = builder.finish().unwrap();
+    assert_eq!(lexer.mode_name(DEFAULT_MODE), "default");
+    assert_eq!(lexer.mode_name(string_mode), "string");
+
+    let lexemes = |source| {
+        lex_all(&lexer, source)
+            .iter()
+            .map(|lexeme| lexeme.lexeme)
+            .collect::<Vec<_>>()
+    };
+
+    assert_eq!(
+        lexemes(r#"x "a ${ {y} "b ${z}" } c" w"#),
+        vec![
+            "x", "\"", "a ", "${", "{", "y", "}", "\"", "b ", "${", "z", "}", "\"", "}", " c",
+            "\"", "w"
+        ]
+    );
+    // An unmatched `}` doesn't pop the last mode.
+    assert_eq!(lexemes("} x"), vec!["}", "x"]);
+    assert_eq!(lexemes("x <<< y \"z\n w"), vec!["x", "<<<", " y \"z\n w"]);
+}
+
+#[test]
+fn test_lexer_skip_patterns() {
+    use lexer::{LexerBuilder, LEX_ERROR};
 
+    let mut builder = LexerBuilder::new(r#"[ \t\r\n]+"#).unwrap();
     let tok_var = builder.regex("[a-zA-Z_]+").unwrap();
+    let tok_div = builder.string("/").unwrap();
+    let tok_line = builder.skip("//[^\n]*").unwrap();
+    let tok_hash = builder.skip("#[^\n]*").unwrap();
+    let tok_block = builder.skip_nested("/*", "*/");
+    let lexer = builder.clone().finish().unwrap();
+    builder.emit_trivia(true);
+    let trivia_lexer = builder.finish().unwrap();
+
+    let source = "a / b // c\n/* d /* e */ f */ g # h\n/*/**/*/i";
+    let lexemes = lex_all(&lexer, source);
+    let tokens = lexemes.iter().map(|l| l.token).collect::<Vec<_>>();
+    assert_eq!(tokens, vec![tok_var, tok_div, tok_var, tok_var, tok_var]);
+    assert_eq!(lexemes[3].lexeme, "g");
+    assert_eq!(lexemes[3].start.line, 1);
+    assert_eq!(lexemes[4].lexeme, "i");
+
+    let lexemes = lex_all(&trivia_lexer, source);
+    let tokens = lexemes.iter().map(|l| l.token).collect::<Vec<_>>();
+    assert_eq!(
+        tokens,
+        vec![tok_var, tok_div, tok_var, tok_line, tok_block, tok_var, tok_hash, tok_block, tok_var]
+    );
+    assert_eq!(lexemes[4].lexeme, "/* d /* e */ f */");
+    assert_eq!(lexemes[7].lexeme, "/*/**/*/");
+    assert!(trivia_lexer.is_trivia(tok_line));
+    assert!(!trivia_lexer.is_trivia(tok_var));
+
+    let lexemes = lex_all(&lexer, "a /* b /* c */ d");
+    assert_eq!(lexemes.len(), 2);
+    assert_eq!(lexemes[1].token, LEX_ERROR);
+    assert_eq!(lexemes[1].lexeme, "/* b /* c */ d");
+}
+
+#[test]
+fn test_dfa_backend() {
+    use lexer::{Backend, LexerBuilder, ModeAction, DEFAULT_MODE};
+    use std::fs::read_to_string;
+
+    let lexers = [Backend::RegexSet, Backend::Dfa].map(|backend| {
+        let mut builder = LexerBuilder::new(r#"[ \t\r\n]+"#).unwrap();
+        let string_mode = builder.mode("string", None).unwrap();
+        builder.regex("[a-zA-Z_]+").unwrap();
+        builder.regex("[a-z]+").unwrap();
+        builder.string("raise").unwrap();
+        builder
+            .regex(r#"-?(?:0|[1-9]\d*)(?:\.\d+)?(?:[eE][+-]?\d+)?"#)
+            .unwrap();
+        for op in ["[", "]", "{", "}", ",", ":", "=", "==", "==="] {
+            builder.string(op).unwrap();
+        }
+        builder.skip_nested("/*", "*/");
+        builder
+            .string_in(DEFAULT_MODE, "\"", ModeAction::Push(string_mode))
+            .unwrap();
+        builder
+            .regex_in(string_mode, r#"([^\\"]|(\\.))+"#, ModeAction::Stay)
+            .unwrap();
+        builder
+            .string_in(string_mode, "\"", ModeAction::Pop)
+            .unwrap();
+        builder.backend(backend);
+        builder.finish().unwrap()
+    });
+    fn lex_both<'s>(lexers: &[Lexer; 2], source: &'s str) -> Vec<(usize, &'s str)> {
+        let [regex_set, dfa] = lexers;
+        let expected = lex_all(regex_set, source);
+        assert_eq!(lex_all(dfa, source), expected);
+        expected
+            .iter()
+            .map(|lexeme| (lexeme.token, lexeme.lexeme))
+            .collect()
+    }
+    let lex = |source| lex_both(&lexers, source);
+
+    // Strings beat regexes, and earlier regexes beat later ones.
+    assert_eq!(lex("raise raised"), vec![(2, "raise"), (0, "raised")]);
+    assert_eq!(lex("===="), vec![(12, "==="), (10, "=")]);
+    assert_eq!(
+        lex("x /* y */ -1.5e3 @"),
+        vec![(0, "x"), (3, "-1.5e3"), (usize::MAX, "@")]
+    );
+    assert_eq!(
+        lex(r#"{"a\"b": "é"}"#),
+        vec![
+            (6, "{"),
+            (14, "\""),
+            (15, "a\\\"b"),
+            (16, "\""),
+            (9, ":"),
+            (14, "\""),
+            (15, "é"),
+            (16, "\""),
+            (7, "}")
+        ]
+    );
+    lex(&read_to_string("sample.json").unwrap());
+}
+
+#[test]
+fn test_backends_take_longest_match_of_each_regex() {
+    use lexer::{Backend, LexerBuilder};
+
+    let lex = |regex: &str, backend, source| {
+        let mut builder = LexerBuilder::new(r#"[ \t\r\n]+"#).unwrap();
+        builder.regex(regex).unwrap();
+        builder.skip("#|#[a-z]*").unwrap();
+        builder.backend(backend);
+        let lexer = builder.finish().unwrap();
+        lex_all(&lexer, source)
+            .iter()
+            .map(|lexeme| lexeme.lexeme)
+            .collect::<Vec<_>>()
+    };
+    for (regex, source, expected) in [
+        ("=|==", "==", vec!["=="]),
+        ("a+?", "aaa", vec!["aaa"]),
+        ("ab|abcd", "abcd #skipped abcd", vec!["abcd", "abcd"]),
+    ] {
+        assert_eq!(lex(regex, Backend::RegexSet, source), expected, "{}", regex);
+        assert_eq!(lex(regex, Backend::Dfa, source), expected, "{}", regex);
+    }
+}
+
+#[test]
+fn test_position_strategies() {
+    use lexer::{LexerBuilder, LEX_ERROR};
+
+    let mut builder = LexerBuilder::new(r#"[ \t\r\n]+"#).unwrap();
+    let tok_var = builder.regex("[a-zA-Zé_]+").unwrap();
     let tok_lparen = builder.string("(").unwrap();
     let tok_raise = builder.string("raise").unwrap();
     let tok_rparen = builder.string(")").unwrap();
+    let lexer = builder.finish().unwrap();
+
+    let tokens = |source| {
+        lexer
+            .lex_with::<NoPositions>(source)
+            .map(|lexeme| (lexeme.token, lexeme.lexeme))
+            .collect::<Vec<_>>()
+    };
+    assert_eq!(tokens("raised"), vec![(tok_var, "raised")]);
+    assert_eq!(
+        tokens("raise(my_error)"),
+        vec![
+            (tok_raise, "raise"),
+            (tok_lparen, "("),
+            (tok_var, "my_error"),
+            (tok_rparen, ")")
+        ]
+    );
+    assert_eq!(
+        tokens("x $$ !"),
+        vec![(tok_var, "x"), (LEX_ERROR, "$$"), (LEX_ERROR, "!")]
+    );
 
+    let source = "raise(\r\n  éé_x\n)\n";
+    let mut iter = lexer.lex_with::<LazyLineCol>(source);
+    let lexemes = (&mut iter).collect::<Vec<_>>();
+    assert_eq!(lexemes[2].lexeme, "éé_x");
+    assert_eq!((lexemes[2].start, lexemes[2].end), (10, 16));
+    let indexer = iter.into_indexer();
+    assert_eq!(indexer.num_lines(), 3);
+    assert_eq!(indexer.line(16), 1);
+    assert_eq!(indexer.col(16), 8);
+    assert_eq!(indexer.utf8_col(16), 6);
+    assert_eq!(indexer.line_contents(1), "  éé_x");
+    lex_all(&lexer, source);
+}
+
+#[test]
+fn test_lexer_recovery() {
+    use lexer::{LexerBuilder, Recovery};
+
+    let lexemes = |recovery, source| {
+        let mut builder = LexerBuilder::new(r#"[ \t\r\n]+"#).unwrap();
+        builder.regex("[a-z]+").unwrap();
+        builder.string("(").unwrap();
+        builder.skip_nested("/*", "*/");
+        builder.recovery(recovery);
+        let lexer = builder.finish().unwrap();
+        lex_all(&lexer, source)
+            .iter()
+            .map(|lexeme| lexeme.lexeme)
+            .collect::<Vec<_>>()
+    };
+
+    let source = "foo@bar $$( x@/* y */ !";
+    assert_eq!(
+        lexemes(Recovery::SkipToWhitespace, source),
+        vec!["foo", "@bar", "$$(", "x", "@/*", "y", "*/", "!"]
+    );
+    assert_eq!(
+        lexemes(Recovery::SkipChar, source),
+        vec!["foo", "@", "bar", "$", "$", "(", "x", "@", "!"]
+    );
+    assert_eq!(
+        lexemes(Recovery::SkipToToken, source),
+        vec!["foo", "@", "bar", "$$", "(", "x", "@", "!"]
+    );
+    assert_eq!(lexemes(Recovery::SkipToToken, "é%"), vec!["é%"]);
+}
+
+#[test]
+fn test_lex_error() {
+    use lexer::{LexErrorKind, LexerBuilder, Recovery, LEX_ERROR};
+
+    colored::control::set_override(false);
+
+    let mut builder = LexerBuilder::new(r#"[ \t\r\n]+"#).unwrap();
+    builder.regex("[a-z]+").unwrap();
+    builder.string("(").unwrap();
+    builder.skip_nested("/*", "*/");
+    builder.recovery(Recovery::SkipToToken);
     let lexer = builder.finish().unwrap();
 
-    let mut lexemes = lexer.lex("raised");
-    assert_eq!(lexemes.next(), Some((tok_var, "raised")));
-    assert_eq!(lexemes.next(), None);
-
-    let mut lexemes = lexer.lex("raise(my_error)");
-    assert_eq!(lexemes.next(), Some((tok_raise, "raise")));
-    assert_eq!(lexemes.next(), Some((tok_lparen, "(")));
-    assert_eq!(lexemes.next(), Some((tok_var, "my_error")));
-    assert_eq!(lexemes.next(), Some((tok_rparen, ")")));
-    assert_eq!(lexemes.next(), None);
-
-    let mut lexemes = lexer.lex("x $$ !");
-    assert_eq!(lexemes.next(), Some((tok_var, "x")));
-    assert_eq!(lexemes.next(), Some((LEX_ERROR, "$$")));
-    assert_eq!(lexemes.next(), Some((LEX_ERROR, "!")));
-    assert_eq!(lexemes.next(), None);
+    let errors = |source| {
+        let mut lexemes = lexer.lex(source);
+        let mut errors = vec![];
+        while let Some(lexeme) = lexemes.next() {
+            if lexeme.token == LEX_ERROR {
+                errors.push(lexemes.error("<test>", &lexeme));
+            }
+        }
+        errors
+    };
+
+    let errs = errors("foo\n  éé@@bar\n");
+    assert_eq!(errs.len(), 1);
+    assert_eq!(errs[0].kind, LexErrorKind::Unexpected);
+    assert_eq!(errs[0].expected, vec!["/[a-z]+/", "\"(\""]);
+    assert_eq!(
+        errs[0].to_string(),
+        r#"lex error: unexpected "éé@@"
+ --> <test>:2:3
+  |
+2 |  éé@@bar
+  |  ^^^^
+  |  expected one of: /[a-z]+/, "("
+  |"#
+    );
+
+    let errs = errors("x /* y /* z */\r\n w");
+    assert_eq!(errs.len(), 1);
+    assert_eq!(
+        errs[0].kind,
+        LexErrorKind::Unclosed {
+            open: "/*".to_owned()
+        }
+    );
+    assert_eq!(
+        errs[0].to_string(),
+        r#"lex error: unclosed "/*"
+ --> <test>:1:3
+  |
+1 |x /* y /* z */
+  |  ^^^^^^^^^^^^
+  |  expected "*/"
+  |"#
+    );
+}
+
+#[test]
+fn test_token_names() {
+    use lexer::{LexerBuilder, LEX_ERROR};
+
+    let mut builder = LexerBuilder::new(r#"[ \t\r\n]+"#).unwrap();
+    let tok_var = builder.regex("[a-z]+").unwrap();
+    let tok_plus = builder.string("+").unwrap();
+    let tok_comment = builder.skip_nested("/*", "*/");
+    builder.name(tok_var, "Var");
+    let lexer = builder.finish().unwrap();
+
+    assert_eq!(lexer.token_name(tok_var), "Var");
+    assert_eq!(lexer.token_name(tok_plus), "\"+\"");
+    assert_eq!(lexer.token_name(tok_comment), "\"/*\"");
+    assert_eq!(lexer.token_name(LEX_ERROR), "LEX_ERROR");
+    assert_eq!(lexer.token("Var"), Some(tok_var));
+    assert_eq!(lexer.token("\"+\""), Some(tok_plus));
+    assert_eq!(lexer.token("Plus"), None);
+}
+
+#[test]
+fn test_lexer_spec() {
+    use lexer::{LexerBuilder, ModeAction, DEFAULT_MODE};
+
+    let spec = r#"
+        # Strings with interpolation
+        mode default `[ \t\r\n]+`
+        mode string
+
+        Var      regex       `[a-zA-Z_]+`
+        Quote    string      `"`          push string
+        Close    string      `}`          pop
+        EndQuote string      `"`          in string pop
+        Interp   string      `${`         in string push default
+        Text     regex       `([^"$]|\$[^{"])+`  in string
+        Tick     string      ````         # a single backtick
+        Comment  skip_nested `/*` `*/`
+    "#;
+    let lexer = LexerBuilder::from_spec(spec).unwrap().finish().unwrap();
+
+    let mut builder = LexerBuilder::new(r#"[ \t\r\n]+"#).unwrap();
+    let string_mode = builder.mode("string", None).unwrap();
+    builder.regex("[a-zA-Z_]+").unwrap();
+    builder
+        .string_in(DEFAULT_MODE, "\"", ModeAction::Push(string_mode))
+        .unwrap();
+    builder
+        .string_in(DEFAULT_MODE, "}", ModeAction::Pop)
+        .unwrap();
+    builder
+        .string_in(string_mode, "\"", ModeAction::Pop)
+        .unwrap();
+    builder
+        .string_in(string_mode, "${", ModeAction::Push(DEFAULT_MODE))
+        .unwrap();
+    builder
+        .regex_in(string_mode, r#"([^"$]|\$[^{"])+"#, ModeAction::Stay)
+        .unwrap();
+    builder.string("`").unwrap();
+    builder.skip_nested("/*", "*/");
+    let expected_lexer = builder.finish().unwrap();
+
+    let source = r#"x "a ${ y /* } */ } b" ` z"#;
+    assert_eq!(lex_all(&lexer, source), lex_all(&expected_lexer, source));
+    let names = lex_all(&lexer, source)
+        .iter()
+        .map(|lexeme| lexer.token_name(lexeme.token))
+        .collect::<Vec<_>>();
+    assert_eq!(
+        names,
+        vec!["Var", "Quote", "Text", "Interp", "Var", "Close", "Text", "EndQuote", "Tick", "Var"]
+    );
+
+    let json = LexerBuilder::from_spec(include_str!("../json.lex"))
+        .unwrap()
+        .finish()
+        .unwrap();
+    let names = lex_all(&json, r#"{"a": [1.5e3, null]}"#)
+        .iter()
+        .map(|lexeme| json.token_name(lexeme.token))
+        .collect::<Vec<_>>();
+    assert_eq!(
+        names,
+        vec!["LBrace", "String", "Colon", "LBrack", "Number", "Comma", "Null", "RBrack", "RBrace"]
+    );
+
+    let error = |spec| LexerBuilder::from_spec(spec).unwrap_err().to_string();
+    assert_eq!(
+        error("A string `a`\nA string `b`"),
+        "lexer spec line 2: token `A` is already defined"
+    );
+    assert_eq!(
+        error("A string `a`\n# B is the same token\nB string `a`"),
+        "lexer spec line 3: token `B` has the same pattern as `A` on line 1"
+    );
+    assert!(LexerBuilder::from_spec("A string `a`\nB string `a` pop").is_ok());
+    assert_eq!(
+        error("A string `a` in nowhere"),
+        "lexer spec line 1: mode `nowhere` is not defined"
+    );
+    assert_eq!(
+        error("\n\nA strin `a`"),
+        "lexer spec line 3: unknown kind `strin`: expected string, regex, skip, or skip_nested"
+    );
+    assert_eq!(
+        error("A regex `a"),
+        "lexer spec line 1: unterminated pattern"
+    );
+    assert_eq!(
+        error("A skip `a` pop"),
+        "lexer spec line 1: skip patterns can't change the mode"
+    );
+    assert_eq!(
+        error("mode m\nmode m"),
+        "lexer spec line 2: mode `m` is already defined"
+    );
+}
+
+#[test]
+fn test_lexer_warnings() {
+    use lexer::{LexerBuilder, LexerWarningKind, ModeAction, Severity, DEFAULT_MODE};
+
+    let mut builder = LexerBuilder::new(r#"[ \t\r\n]+"#).unwrap();
+    let string_mode = builder.mode("string", None).unwrap();
+    let tok_ident = builder.regex("[a-z_]+").unwrap();
+    let tok_if = builder.string("if").unwrap();
+    let tok_lower = builder.regex("[a-z]+").unwrap();
+    let tok_abc = builder.regex("a(b|c)").unwrap();
+    let tok_num = builder.regex("[0-9]*").unwrap();
+    let tok_ab = builder.string("ab").unwrap();
+    let tok_ab_push = builder
+        .string_in(DEFAULT_MODE, "ab", ModeAction::Push(string_mode))
+        .unwrap();
+    let tok_upper = builder.regex("[A-Z]+").unwrap();
+    let tok_text = builder
+        .regex_in(string_mode, "[a-z]+", ModeAction::Stay)
+        .unwrap();
+    builder.name(tok_ident, "Ident");
+    let lexer = builder.finish().unwrap();
+    let warnings = lexer.warnings();
+
+    let kinds = warnings.iter().map(|w| w.kind).collect::<Vec<_>>();
+    assert_eq!(
+        kinds,
+        vec![
+            LexerWarningKind::KeywordOverlap {
+                keyword: tok_if,
+                identifier: tok_ident
+            },
+            LexerWarningKind::KeywordOverlap {
+                keyword: tok_if,
+                identifier: tok_lower
+            },
+            LexerWarningKind::Shadowed {
+                token: tok_lower,
+                by: tok_ident
+            },
+            LexerWarningKind::Shadowed {
+                token: tok_abc,
+                by: tok_ident
+            },
+            LexerWarningKind::MatchesEmpty { token: tok_num },
+            LexerWarningKind::KeywordOverlap {
+                keyword: tok_ab,
+                identifier: tok_ident
+            },
+            LexerWarningKind::KeywordOverlap {
+                keyword: tok_ab,
+                identifier: tok_lower
+            },
+            LexerWarningKind::KeywordOverlap {
+                keyword: tok_ab,
+                identifier: tok_abc
+            },
+            LexerWarningKind::Shadowed {
+                token: tok_ab_push,
+                by: tok_ab
+            },
+        ]
+    );
+    // Keywords that identifiers also match are usually intended, so they're only notes.
+    assert_eq!(kinds[0].severity(), Severity::Note);
+    assert_eq!(kinds[2].severity(), Severity::Warning);
+    assert_eq!(
+        warnings[0].to_string(),
+        "lexer note: keyword \"if\" is also matched by Ident, but will always be lexed as the keyword"
+    );
+    // Different modes don't interfere, and neither do disjoint tokens.
+    assert!(!kinds.iter().any(|kind| matches!(
+        kind,
+        LexerWarningKind::Shadowed { token, .. } if *token == tok_text || *token == tok_upper
+    )));
+    assert_eq!(
+        warnings[2].to_string(),
+        "lexer warning: token /[a-z]+/ can never be lexed, because Ident matches everything it does"
+    );
+
+    // Skip patterns are tried first, so they shadow tokens that always start with one of them.
+    let mut builder = LexerBuilder::new(r#"[ \t\r\n]+"#).unwrap();
+    let tok_hash = builder.regex("#[a-z]+").unwrap();
+    let tok_doc = builder.string("///").unwrap();
+    let tok_slash = builder.regex("/+").unwrap();
+    let tok_comment = builder.skip("//[^\n]*").unwrap();
+    let tok_block = builder.skip_nested("#[", "]#");
+    let tok_attr = builder.regex(r"#\[[a-z]+\]").unwrap();
+    builder.name(tok_comment, "LineComment");
+    let lexer = builder.finish().unwrap();
+    let warnings = lexer.warnings();
+    let kinds = warnings.iter().map(|w| w.kind).collect::<Vec<_>>();
+    assert_eq!(
+        kinds,
+        vec![
+            LexerWarningKind::Shadowed {
+                token: tok_doc,
+                by: tok_comment
+            },
+            LexerWarningKind::Shadowed {
+                token: tok_attr,
+                by: tok_block
+            },
+        ]
+    );
+    assert!(!kinds.iter().any(|kind| matches!(
+        kind,
+        LexerWarningKind::Shadowed { token, .. } if *token == tok_hash || *token == tok_slash
+    )));
+    assert_eq!(
+        warnings[0].to_string(),
+        "lexer warning: token \"///\" can never be lexed, because it always starts with a match \
+         of the skip pattern LineComment"
+    );
+
+    let builder = LexerBuilder::from_spec(include_str!("../json.lex")).unwrap();
+    assert_eq!(builder.finish().unwrap().warnings(), &[]);
+
+    // The checks can be turned off.
+    let mut builder = LexerBuilder::new(r#"[ \t\r\n]+"#).unwrap();
+    builder.regex("[0-9]*").unwrap();
+    builder.check_patterns(false);
+    assert_eq!(builder.finish().unwrap().warnings(), &[]);
+}
+
+#[test]
+fn test_column_kinds() {
+    use lexer::{ColumnKind, LexerBuilder};
+
+    let kinds = [
+        ColumnKind::Byte,
+        ColumnKind::Utf8,
+        ColumnKind::Utf16,
+        ColumnKind::Display,
+    ];
+    // "e\u{301}" is "e" with a combining accent
+    let source = "ab\r\n日本 e\u{301}x😀z\n\n𝔸";
+    let indexer = LineAndColIndexer::new(source);
+
+    let columns = |pos| kinds.map(|kind| indexer.column(pos, kind));
+    let x = source.find('x').unwrap();
+    let z = source.find('z').unwrap();
+    assert_eq!(columns(x), [10, 5, 5, 6]);
+    assert_eq!(columns(z), [15, 7, 8, 9]);
+    assert_eq!(columns(source.len()), [4, 1, 2, 1]);
+
+    // Every char boundary round-trips through every kind of column.
+    for (pos, _) in source.char_indices().chain(Some((source.len(), ' '))) {
+        let line = indexer.line(pos);
+        if pos > indexer.line_span(line).1 {
+            continue; // inside a "\r\n"
+        }
+        let position = indexer.position(pos);
+        for from in kinds {
+            let offset = indexer.offset(line, position.column(from), from);
+            let expected = if from == ColumnKind::Display && pos == x - 2 {
+                // The combining accent is kept with the "e"
+                x
+            } else {
+                pos
+            };
+            assert_eq!(offset, expected, "{:?} column of {}", from, pos);
+            for to in kinds {
+                assert_eq!(
+                    indexer.convert_col(line, position.column(from), from, to),
+                    indexer.column(expected, to)
+                );
+            }
+        }
+    }
+
+    // Columns inside a character round down; columns past the end of a line clamp.
+    assert_eq!(indexer.offset(1, 1, ColumnKind::Display), 4);
+    assert_eq!(indexer.offset(1, 7, ColumnKind::Utf16), z - 4);
+    assert_eq!(indexer.offset(0, 100, ColumnKind::Utf8), 2);
+    assert_eq!(
+        indexer.offset(2, 5, ColumnKind::Byte),
+        indexer.line_span(2).0
+    );
+
+    // The lexer tracks the same columns.
+    let mut builder = LexerBuilder::new(r#"[ \t\r\n]+"#).unwrap();
+    builder.regex(r"[^ \t\r\n]").unwrap();
+    let lexer = builder.finish().unwrap();
+    let lexemes = lex_all(&lexer, source);
+    let lexeme_z = lexemes.iter().find(|lexeme| lexeme.lexeme == "z").unwrap();
+    assert_eq!(kinds.map(|kind| lexeme_z.start.column(kind)), columns(z));
 }
-*/
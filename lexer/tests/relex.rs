@@ -0,0 +1,113 @@
+use lexer::incremental::TextEdit;
+use lexer::{Lexer, LexerBuilder, ModeAction, DEFAULT_MODE};
+use proptest::prelude::*;
+
+fn test_lexer(emit_trivia: bool) -> Lexer {
+    let mut builder = LexerBuilder::new(r#"[ \t\r\n]+"#).unwrap();
+    let string_mode = builder.mode("string", None).unwrap();
+    builder.regex("[a-zA-Zé_]+").unwrap();
+    builder.regex("[0-9]+(\\.[0-9]+)?").unwrap();
+    builder.string("+").unwrap();
+    builder.string("+=").unwrap();
+    builder.string("/").unwrap();
+    builder.skip("//[^\n]*").unwrap();
+    builder.skip_nested("/*", "*/");
+    builder
+        .string_in(DEFAULT_MODE, "\"", ModeAction::Push(string_mode))
+        .unwrap();
+    builder
+        .string_in(DEFAULT_MODE, "{", ModeAction::Push(DEFAULT_MODE))
+        .unwrap();
+    builder
+        .string_in(DEFAULT_MODE, "}", ModeAction::Pop)
+        .unwrap();
+    builder
+        .string_in(string_mode, "\"", ModeAction::Pop)
+        .unwrap();
+    builder
+        .string_in(string_mode, "${", ModeAction::Push(DEFAULT_MODE))
+        .unwrap();
+    builder
+        .regex_in(string_mode, r#"([^"$]|\$[^{"])+"#, ModeAction::Stay)
+        .unwrap();
+    builder.emit_trivia(emit_trivia);
+    builder.finish().unwrap()
+}
+
+fn fragment() -> impl Strategy<Value = &'static str> {
+    prop::sample::select(vec![
+        "x", "abc", "é", "12", "3.5", ".", " ", "  ", "\n", "+", "=", "/", "*", "//", "/*", "*/",
+        "\"", "${", "{", "}", "$", "@",
+    ])
+}
+
+fn source() -> impl Strategy<Value = String> {
+    prop::collection::vec(fragment(), 0..30).prop_map(|fragments| fragments.concat())
+}
+
+/// A source, and an edit to it whose range lies on char boundaries.
+fn source_and_edit() -> impl Strategy<Value = (String, usize, usize, String)> {
+    (source(), source()).prop_flat_map(|(source, replacement)| {
+        let boundaries = source
+            .char_indices()
+            .map(|(i, _)| i)
+            .chain(Some(source.len()))
+            .collect::<Vec<_>>();
+        let n = boundaries.len();
+        (0..n, 0..n).prop_map(move |(i, j)| {
+            let (i, j) = (i.min(j), i.max(j));
+            (
+                source.clone(),
+                boundaries[i],
+                boundaries[j],
+                replacement.clone(),
+            )
+        })
+    })
+}
+
+fn check_relex(lexer: &Lexer, source: &str, start: usize, end: usize, replacement: &str) {
+    let old_lexemes = lexer.lex(source).collect::<Vec<_>>();
+    let edit = TextEdit::new(start..end, replacement);
+    let new_source = edit.apply(source);
+    let relexed = lexer.relex(&old_lexemes, &edit, &new_source);
+    let expected = lexer.lex(&new_source).collect::<Vec<_>>();
+    assert_eq!(relexed.lexemes, expected);
+
+    // Everything outside of the changed range is the same as before (up to shifting).
+    let (old_range, new_range) = (relexed.old_range, relexed.new_range);
+    assert_eq!(
+        old_lexemes.len() - old_range.len(),
+        expected.len() - new_range.len()
+    );
+    assert_eq!(
+        &old_lexemes[..old_range.start],
+        &expected[..new_range.start]
+    );
+    for (old, new) in old_lexemes[old_range.end..]
+        .iter()
+        .zip(&expected[new_range.end..])
+    {
+        assert_eq!((old.token, old.lexeme), (new.token, new.lexeme));
+    }
+}
+
+#[test]
+fn test_relex_examples() {
+    let lexer = test_lexer(false);
+    check_relex(&lexer, "a + b", 0, 0, "x");
+    check_relex(&lexer, " a + b", 0, 0, "x");
+    check_relex(&lexer, "a + b", 5, 5, "c");
+    check_relex(&lexer, "a + b", 2, 3, "+=");
+    check_relex(&lexer, "a /* b */ c", 2, 4, "");
+    check_relex(&lexer, "\"a ${b} c\" d", 0, 1, "");
+    check_relex(&lexer, "x\n\"a ${b}\ny\" d\ne", 3, 3, "\n\"");
+}
+
+proptest! {
+    #[test]
+    fn prop_relex_matches_full_lex((source, start, end, replacement) in source_and_edit()) {
+        check_relex(&test_lexer(false), &source, start, end, &replacement);
+        check_relex(&test_lexer(true), &source, start, end, &replacement);
+    }
+}
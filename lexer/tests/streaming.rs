@@ -0,0 +1,122 @@
+use lexer::streaming::OwnedLexeme;
+use lexer::{Lexer, LexerBuilder, ModeAction, Recovery, DEFAULT_MODE};
+use proptest::prelude::*;
+use std::io::{self, Read};
+
+fn test_lexer(emit_trivia: bool) -> Lexer {
+    let mut builder = LexerBuilder::new(r#"[ \t\r\n]+"#).unwrap();
+    let string_mode = builder.mode("string", None).unwrap();
+    builder.regex("[a-zA-Zé_]+").unwrap();
+    builder.regex("[0-9]+(\\.[0-9]+)?").unwrap();
+    builder.string("+").unwrap();
+    builder.string("+=").unwrap();
+    builder.skip("//[^\n]*").unwrap();
+    builder.skip_nested("/*", "*/");
+    builder
+        .string_in(DEFAULT_MODE, "\"", ModeAction::Push(string_mode))
+        .unwrap();
+    builder
+        .string_in(string_mode, "\"", ModeAction::Pop)
+        .unwrap();
+    builder
+        .regex_in(string_mode, r#"[^"]+"#, ModeAction::Stay)
+        .unwrap();
+    builder.emit_trivia(emit_trivia);
+    builder.recovery(Recovery::SkipToToken);
+    builder.finish().unwrap()
+}
+
+/// A reader that returns at most `max_read` bytes at a time.
+struct ChunkedReader<'a> {
+    bytes: &'a [u8],
+    max_read: usize,
+}
+
+impl<'a> Read for ChunkedReader<'a> {
+    fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
+        let len = buf.len().min(self.max_read).min(self.bytes.len());
+        buf[..len].copy_from_slice(&self.bytes[..len]);
+        self.bytes = &self.bytes[len..];
+        Ok(len)
+    }
+}
+
+fn check_streaming(lexer: &Lexer, source: &str, capacity: usize, max_read: usize) {
+    let expected = lexer.lex(source).map(OwnedLexeme::from).collect::<Vec<_>>();
+    let reader = ChunkedReader {
+        bytes: source.as_bytes(),
+        max_read,
+    };
+    let lexemes = lexer
+        .lex_reader_with_capacity(capacity, reader)
+        .collect::<io::Result<Vec<_>>>()
+        .unwrap();
+    assert_eq!(lexemes, expected);
+}
+
+#[test]
+fn test_streaming_examples() {
+    let lexer = test_lexer(false);
+    for capacity in 1..10 {
+        check_streaming(&lexer, "", capacity, capacity);
+        check_streaming(&lexer, "abc + def += 12.5", capacity, capacity);
+        check_streaming(&lexer, "abc+\n+=éé\"x é\" // y\n z", capacity, 1);
+        check_streaming(&lexer, "a /* b /* c */ d */ e /* f", capacity, 3);
+        check_streaming(&lexer, "a @@ 1.x $ \"unclosed", capacity, 2);
+    }
+
+    let source = "x".repeat(100_000) + " y";
+    check_streaming(&lexer, &source, 16, 16);
+    check_streaming(&lexer, &source, 16, 7);
+
+    let lexer = test_lexer(true);
+    check_streaming(&lexer, "a // b\n/* c */ d //", 2, 2);
+}
+
+#[test]
+fn test_streaming_errors() {
+    let lexer = test_lexer(false);
+
+    // Lexemes before the error may or may not be yielded, depending on where reads end.
+    let source = &b"abc def \xFF ghi jkl mno"[..];
+    for capacity in 1..10 {
+        let mut lexemes = lexer.lex_reader_with_capacity(capacity, source);
+        let err = loop {
+            match lexemes.next().unwrap() {
+                Ok(lexeme) => assert!(["abc", "def"].contains(&lexeme.lexeme.as_str())),
+                Err(err) => break err,
+            }
+        };
+        assert_eq!(err.kind(), io::ErrorKind::InvalidData);
+        assert!(lexemes.next().is_none());
+    }
+
+    // Ends partway through "é"
+    let mut lexemes = lexer.lex_reader(&b"abc \xC3"[..]);
+    let err = lexemes.next().unwrap().unwrap_err();
+    assert_eq!(err.kind(), io::ErrorKind::InvalidData);
+    assert!(lexemes.next().is_none());
+}
+
+fn fragment() -> impl Strategy<Value = &'static str> {
+    prop::sample::select(vec![
+        "x", "abc", "é", "12", "3.5", ".", " ", "  ", "\n", "+", "=", "/", "*", "//", "/*", "*/",
+        "\"", "@",
+    ])
+}
+
+fn source() -> impl Strategy<Value = String> {
+    prop::collection::vec(fragment(), 0..30).prop_map(|fragments| fragments.concat())
+}
+
+proptest! {
+    #[test]
+    fn prop_streaming_matches_full_lex(
+        source in source(),
+        capacity in 1usize..12,
+        max_read in 1usize..12,
+    ) {
+        check_streaming(&test_lexer(false), &source, capacity, max_read);
+        check_streaming(&test_lexer(true), &source, capacity, max_read);
+    }
+}
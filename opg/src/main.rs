@@ -1,162 +1,359 @@
-#[derive(Debug, Clone, Copy)]
-enum Token {
-    Letter,
-    OpenParen,
-    CloseParen,
-    Plus,
-    Minus,
-    Asterisk,
-    Question,
-    Colon,
-    Semi,
-}
-
-#[derive(Debug, Clone, Copy)]
-enum OpToken {
-    Blank,
-    Juxt,
-    Id,
-    Open,
-    Apply,
-    Close,
-    Plus,
-    Negative,
-    Minus,
-    Times,
-    Question,
-    Colon,
-    Semi,
-}
-
-fn lex(stream: &str) -> Vec<(char, Token)> {
-    fn char_to_token(ch: char) -> Token {
-        match ch {
-            'x' => Token::Letter,
-            '(' => Token::OpenParen,
-            ')' => Token::CloseParen,
-            '+' => Token::Plus,
-            '-' => Token::Minus,
-            '*' => Token::Asterisk,
-            '?' => Token::Question,
-            ':' => Token::Colon,
-            ';' => Token::Semi,
-            _ => panic!("unrecognized char {}", ch),
-        }
-    }
-    stream
-        .chars()
-        .map(|ch| (ch, char_to_token(ch)))
-        .collect::<Vec<_>>()
-}
-
-fn resolve(stream: Vec<(char, Token)>) -> Vec<(char, OpToken)> {
-    fn token_to_prefixy_op(tok: Token) -> Option<OpToken> {
-        match tok {
-            Token::Letter => Some(OpToken::Id),
-            Token::OpenParen => Some(OpToken::Open),
-            Token::CloseParen => None,
-            Token::Plus => None,
-            Token::Minus => Some(OpToken::Negative),
-            Token::Asterisk => None,
-            Token::Question => None,
-            Token::Colon => None,
-            Token::Semi => None,
-        }
-    }
-    fn token_to_suffixy_op(tok: Token) -> Option<OpToken> {
+use std::fmt;
+
+/// A byte range of the source.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+struct Span {
+    start: usize,
+    end: usize,
+}
+
+impl Span {
+    fn empty(pos: usize) -> Span {
+        Span {
+            start: pos,
+            end: pos,
+        }
+    }
+
+    fn to(self, other: Span) -> Span {
+        Span {
+            start: self.start,
+            end: other.end,
+        }
+    }
+}
+
+/// Binds tighter than any operator, so that the parts of a mixfix operator stay together.
+const LINK: u32 = u32::MAX;
+
+/// An operator, like `+` or `x ? x : x ;`. It has an argument between each pair of its parts,
+/// and before and after its parts if it has a left or right precedence. Lower precedences
+/// bind tighter.
+#[derive(Debug, Clone)]
+struct Operator {
+    /// Shown in syntax trees.
+    name: String,
+    /// The lexemes of the operator, like `["?", ":", ";"]`. Mixfix if there's more than one.
+    parts: Vec<String>,
+    left_prec: Option<u32>,
+    right_prec: Option<u32>,
+    /// Shown in postfix output instead of the first part, to tell it apart from other
+    /// operators that start with the same lexeme.
+    show: Option<String>,
+}
+
+impl Operator {
+    fn new(name: &str, parts: &[&str], left_prec: Option<u32>, right_prec: Option<u32>) -> Self {
+        Operator {
+            name: name.to_owned(),
+            parts: parts.iter().map(|part| (*part).to_owned()).collect(),
+            left_prec,
+            right_prec,
+            show: None,
+        }
+    }
+
+    fn shown_as(mut self, show: &str) -> Self {
+        self.show = Some(show.to_owned());
+        self
+    }
+
+    /// No arguments outside its parts, like `x` or `( x )`.
+    fn nilfix(name: &str, parts: &[&str]) -> Self {
+        Operator::new(name, parts, None, None)
+    }
+
+    fn prefix(name: &str, parts: &[&str], right_prec: u32) -> Self {
+        Operator::new(name, parts, None, Some(right_prec))
+    }
+
+    fn infix(name: &str, parts: &[&str], left_prec: u32, right_prec: u32) -> Self {
+        Operator::new(name, parts, Some(left_prec), Some(right_prec))
+    }
+
+    fn postfix(name: &str, parts: &[&str], left_prec: u32) -> Self {
+        Operator::new(name, parts, Some(left_prec), None)
+    }
+}
+
+/// What a later part of a mixfix operator is followed by.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum PartKind {
+    /// An argument and then another part.
+    Middle,
+    /// The end of the operator, and its right argument if it has one.
+    Last { right_prec: Option<u32> },
+}
+
+/// Everything a lexeme can mean. Which one depends on whether it comes after an argument.
+#[derive(Debug)]
+struct Lexeme {
+    text: String,
+    /// The operator it starts, when not after an argument.
+    prefixy: Option<usize>,
+    /// The operator it starts, when after an argument.
+    suffixy: Option<usize>,
+    /// Set if it's a later part of operators, which is always after an argument.
+    part: Option<PartKind>,
+}
+
+#[derive(Debug)]
+struct Grammar {
+    /// The precedence of the invisible operator between adjacent arguments, as in `x x`.
+    juxt_prec: (u32, u32),
+    operators: Vec<Operator>,
+    lexemes: Vec<Lexeme>,
+}
+
+impl Grammar {
+    /// Fails if the meaning of a lexeme would be ambiguous.
+    fn new(juxt_prec: (u32, u32), operators: Vec<Operator>) -> Result<Grammar, String> {
+        fn lexeme<'a>(lexemes: &'a mut Vec<Lexeme>, text: &str) -> &'a mut Lexeme {
+            let index = match lexemes.iter().position(|lexeme| lexeme.text == text) {
+                Some(index) => index,
+                None => {
+                    lexemes.push(Lexeme {
+                        text: text.to_owned(),
+                        prefixy: None,
+                        suffixy: None,
+                        part: None,
+                    });
+                    lexemes.len() - 1
+                }
+            };
+            &mut lexemes[index]
+        }
+
+        let mut precs = [juxt_prec.0, juxt_prec.1].into_iter().chain(
+            operators
+                .iter()
+                .flat_map(|op| op.left_prec.into_iter().chain(op.right_prec)),
+        );
+        if precs.any(|prec| prec == LINK) {
+            return Err(format!("precedences must be less than {}", LINK));
+        }
+
+        let mut lexemes = vec![];
+        for (index, op) in operators.iter().enumerate() {
+            if op.parts.is_empty() || op.parts.iter().any(|part| part.is_empty()) {
+                return Err(format!("`{}` has an empty part", op.name));
+            }
+            let first = lexeme(&mut lexemes, &op.parts[0]);
+            let starts = match op.left_prec {
+                None => &mut first.prefixy,
+                Some(_) => &mut first.suffixy,
+            };
+            if let Some(other) = starts.replace(index) {
+                return Err(format!(
+                    "`{}` starts both `{}` and `{}`",
+                    op.parts[0], operators[other].name, op.name
+                ));
+            }
+            for (i, part) in op.parts.iter().enumerate().skip(1) {
+                let kind = if i + 1 < op.parts.len() {
+                    PartKind::Middle
+                } else {
+                    PartKind::Last {
+                        right_prec: op.right_prec,
+                    }
+                };
+                let lexeme = lexeme(&mut lexemes, part);
+                if *lexeme.part.get_or_insert(kind) != kind {
+                    return Err(format!(
+                        "`{}` continues operators in conflicting ways",
+                        part
+                    ));
+                }
+            }
+        }
+        if let Some(lexeme) = lexemes
+            .iter()
+            .find(|lexeme| lexeme.suffixy.is_some() && lexeme.part.is_some())
+        {
+            return Err(format!(
+                "`{}` both starts an operator and continues one",
+                lexeme.text
+            ));
+        }
+
+        Ok(Grammar {
+            juxt_prec,
+            operators,
+            lexemes,
+        })
+    }
+
+    fn prefixy_op(&self, lexeme: usize) -> Option<OpToken> {
+        self.lexemes[lexeme].prefixy.map(OpToken::Op)
+    }
+
+    fn suffixy_op(&self, lexeme: usize) -> Option<OpToken> {
+        match &self.lexemes[lexeme] {
+            Lexeme {
+                suffixy: Some(op), ..
+            } => Some(OpToken::Op(*op)),
+            Lexeme { part: Some(_), .. } => Some(OpToken::Part(lexeme)),
+            _ => None,
+        }
+    }
+
+    fn part_kind(&self, lexeme: usize) -> PartKind {
+        self.lexemes[lexeme]
+            .part
+            .expect("lexeme is not a later part of any operator")
+    }
+
+    /// The text of the token, for error messages.
+    fn text(&self, tok: OpToken) -> &str {
         match tok {
-            Token::Letter => None,
-            Token::OpenParen => Some(OpToken::Apply),
-            Token::CloseParen => Some(OpToken::Close),
-            Token::Plus => Some(OpToken::Plus),
-            Token::Minus => Some(OpToken::Minus),
-            Token::Asterisk => Some(OpToken::Times),
-            Token::Question => Some(OpToken::Question),
-            Token::Colon => Some(OpToken::Colon),
-            Token::Semi => Some(OpToken::Semi),
-        }
-    }
-    fn has_right_arg(tok: OpToken) -> bool {
+            OpToken::Blank => "_",
+            OpToken::Juxt => ".",
+            OpToken::Op(op) => &self.operators[op].parts[0],
+            OpToken::Part(lexeme) => &self.lexemes[lexeme].text,
+        }
+    }
+
+    fn has_right_arg(&self, tok: OpToken) -> bool {
         match tok {
             OpToken::Blank => false,
             OpToken::Juxt => true,
-            OpToken::Id => false,
-            OpToken::Open => true,
-            OpToken::Apply => true,
-            OpToken::Close => false,
-            OpToken::Plus => true,
-            OpToken::Negative => true,
-            OpToken::Minus => true,
-            OpToken::Times => true,
-            OpToken::Question => true,
-            OpToken::Colon => true,
-            OpToken::Semi => false,
+            OpToken::Op(op) => {
+                let op = &self.operators[op];
+                op.parts.len() > 1 || op.right_prec.is_some()
+            }
+            OpToken::Part(lexeme) => match self.part_kind(lexeme) {
+                PartKind::Middle => true,
+                PartKind::Last { right_prec } => right_prec.is_some(),
+            },
+        }
+    }
+
+    fn prec(&self, tok: OpToken) -> (u32, u32) {
+        match tok {
+            OpToken::Blank => (0, 0),
+            OpToken::Juxt => self.juxt_prec,
+            OpToken::Op(op) => {
+                let op = &self.operators[op];
+                let right = if op.parts.len() > 1 {
+                    LINK
+                } else {
+                    op.right_prec.unwrap_or(0)
+                };
+                (op.left_prec.unwrap_or(0), right)
+            }
+            OpToken::Part(lexeme) => match self.part_kind(lexeme) {
+                PartKind::Middle => (LINK, LINK),
+                PartKind::Last { right_prec } => (LINK, right_prec.unwrap_or(0)),
+            },
         }
     }
+}
+
+/// The operators the algorithm was first written for.
+fn standard_grammar() -> Grammar {
+    Grammar::new(
+        (10, 10),
+        vec![
+            Operator::nilfix("x", &["x"]),
+            Operator::nilfix("paren", &["(", ")"]),
+            Operator::postfix("apply", &["(", ")"], 40).shown_as("["),
+            Operator::prefix("neg", &["-"], 99).shown_as("~"),
+            Operator::infix("+", &["+"], 100, 99),
+            Operator::infix("-", &["-"], 100, 99),
+            Operator::infix("*", &["*"], 60, 61),
+            Operator::postfix("cond", &["?", ":", ";"], 50),
+        ],
+    )
+    .unwrap()
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum OpToken {
+    /// A missing argument.
+    Blank,
+    /// The invisible operator between adjacent arguments.
+    Juxt,
+    /// The first part of an operator, by its index in the grammar.
+    Op(usize),
+    /// A later part of a mixfix operator, by its lexeme's index in the grammar. Which operator
+    /// it belongs to isn't known until it's linked to a first part by `build_tree`.
+    Part(usize),
+}
+
+/// Splits the stream into lexemes of the grammar, taking the longest one that matches.
+fn lex(grammar: &Grammar, stream: &str) -> Vec<(Span, usize)> {
+    let mut out = vec![];
+    let mut pos = 0;
+    while let Some(ch) = stream[pos..].chars().next() {
+        let (lexeme, text) = grammar
+            .lexemes
+            .iter()
+            .map(|lexeme| &lexeme.text)
+            .enumerate()
+            .filter(|(_, text)| stream[pos..].starts_with(text.as_str()))
+            .max_by_key(|(_, text)| text.len())
+            .unwrap_or_else(|| panic!("unrecognized char {}", ch));
+        let span = Span {
+            start: pos,
+            end: pos + text.len(),
+        };
+        out.push((span, lexeme));
+        pos = span.end;
+    }
+    out
+}
+
+fn resolve(grammar: &Grammar, stream: Vec<(Span, usize)>) -> Vec<(Span, OpToken)> {
     let mut out = vec![];
     let mut expr_mode = true;
-    for (ch, tok) in stream {
+    let mut end = 0;
+    for (span, lexeme) in stream {
+        end = span.end;
         if expr_mode {
-            if let Some(op) = token_to_prefixy_op(tok) {
-                expr_mode = has_right_arg(op);
-                out.push((ch, op));
+            if let Some(op) = grammar.prefixy_op(lexeme) {
+                expr_mode = grammar.has_right_arg(op);
+                out.push((span, op));
             } else {
-                let op = token_to_suffixy_op(tok).unwrap();
-                expr_mode = has_right_arg(op);
-                out.push(('_', OpToken::Blank));
-                out.push((ch, op));
+                let op = grammar.suffixy_op(lexeme).unwrap();
+                expr_mode = grammar.has_right_arg(op);
+                out.push((Span::empty(span.start), OpToken::Blank));
+                out.push((span, op));
             }
         } else {
-            if let Some(op) = token_to_suffixy_op(tok) {
-                expr_mode = has_right_arg(op);
-                out.push((ch, op));
+            if let Some(op) = grammar.suffixy_op(lexeme) {
+                expr_mode = grammar.has_right_arg(op);
+                out.push((span, op));
             } else {
-                let op = token_to_prefixy_op(tok).unwrap();
-                expr_mode = has_right_arg(op);
-                out.push(('.', OpToken::Juxt));
-                out.push((ch, op));
+                let op = grammar.prefixy_op(lexeme).unwrap();
+                expr_mode = grammar.has_right_arg(op);
+                out.push((Span::empty(span.start), OpToken::Juxt));
+                out.push((span, op));
             }
         }
     }
     if expr_mode {
-        out.push(('_', OpToken::Blank));
+        out.push((Span::empty(end), OpToken::Blank));
     }
     println!("resolved: {:?}", out);
     out
 }
 
-fn shunt(stream: Vec<(char, OpToken)>) -> Vec<(char, OpToken)> {
-    fn prec(tok: OpToken) -> (u32, u32) {
-        match tok {
-            OpToken::Blank => (0, 0),
-            OpToken::Juxt => (10, 10),
-            OpToken::Id => (0, 0),
-            OpToken::Open => (0, 1000),
-            OpToken::Apply => (40, 1000),
-            OpToken::Close => (1000, 0),
-            OpToken::Plus => (100, 99),
-            OpToken::Negative => (0, 99),
-            OpToken::Minus => (100, 99),
-            OpToken::Times => (60, 61),
-            OpToken::Question => (50, 1000),
-            OpToken::Colon => (1000, 1000),
-            OpToken::Semi => (1000, 0),
-        }
-    }
+fn shunt(grammar: &Grammar, stream: Vec<(Span, OpToken)>) -> Vec<(Span, OpToken)> {
+    let prec = |tok| grammar.prec(tok);
 
     let mut stack = vec![];
     let mut out = vec![];
-    for (ch, tok) in stream {
+    for (span, tok) in stream {
         loop {
             let rprec = stack.last().map(|(_, t)| prec(*t).1).unwrap_or(u32::MAX);
             let lprec = prec(tok).0;
             if rprec >= lprec {
-                stack.push((ch, tok));
+                stack.push((span, tok));
                 break;
             } else {
-                while let Some((ch, tok)) = stack.pop() {
+                while let Some((span, tok)) = stack.pop() {
                     let lprec = prec(tok).0;
-                    out.push((ch, tok));
+                    out.push((span, tok));
                     let rprec = stack.last().map(|(_, t)| prec(*t).1).unwrap_or(u32::MAX);
                     if rprec > lprec {
                         break;
@@ -171,35 +368,218 @@ fn shunt(stream: Vec<(char, OpToken)>) -> Vec<(char, OpToken)> {
     out
 }
 
-fn show(tok: OpToken) -> char {
-    use OpToken::*;
+/// A syntax tree, built from the output of `shunt`.
+#[derive(Debug, Clone, PartialEq, Eq)]
+struct Expr {
+    kind: ExprKind,
+    span: Span,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+enum ExprKind {
+    /// A missing argument, where an operator needed one. Its span is empty.
+    Blank,
+    /// Two expressions next to each other, like `x x`.
+    Juxt(Box<Expr>, Box<Expr>),
+    /// An operator and its arguments, in order.
+    Op { name: String, args: Vec<Expr> },
+}
+
+/// A part of a mixfix operator, like `(` or `?`, without the parts it's linked to.
+#[derive(Debug, Clone, PartialEq, Eq)]
+enum TreeError {
+    /// An opening part, like the `(` in `(x`.
+    Unclosed(String, Span),
+    /// A closing part, like the `)` in `x)`.
+    Unopened(String, Span),
+}
+
+impl fmt::Display for TreeError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            TreeError::Unclosed(part, span) => {
+                write!(f, "unclosed `{}` at {}..{}", part, span.start, span.end)
+            }
+            TreeError::Unopened(part, span) => {
+                write!(f, "unopened `{}` at {}..{}", part, span.start, span.end)
+            }
+        }
+    }
+}
+
+/// What's on the stack while building a tree: either an expression, or the later parts of a
+/// mixfix operator, waiting for its first part. For example `x?y:z;` shunts to `xyz;:?`, so
+/// the `;` and then the `:` are pushed before the `?` that completes them.
+enum Partial {
+    Expr(Expr),
+    /// The lexemes and spans of the parts, and the arguments between and after them.
+    Parts(Vec<(usize, Span)>, Vec<Expr>),
+}
+
+impl Partial {
+    fn into_expr(self, grammar: &Grammar) -> Result<Expr, TreeError> {
+        match self {
+            Partial::Expr(expr) => Ok(expr),
+            Partial::Parts(parts, _) => {
+                let (lexeme, span) = parts[0];
+                let text = grammar.lexemes[lexeme].text.clone();
+                Err(TreeError::Unopened(text, span))
+            }
+        }
+    }
+}
+
+/// The error for a `tok` that was expected to be linked to `partial` but wasn't. If `partial`
+/// is the wrong parts, like the `;` in `(x;)`, those are the ones to blame.
+fn unlinked(grammar: &Grammar, partial: Partial, tok: OpToken, span: Span) -> TreeError {
+    match partial.into_expr(grammar) {
+        Ok(_) => TreeError::Unclosed(grammar.text(tok).to_owned(), span),
+        Err(err) => err,
+    }
+}
+
+fn build_tree(grammar: &Grammar, postfix: Vec<(Span, OpToken)>) -> Result<Expr, TreeError> {
+    let mut stack = vec![];
+    let pop = |stack: &mut Vec<Partial>| stack.pop().expect("shunt output is missing an operand");
+    let pop_expr = |stack: &mut Vec<Partial>| pop(stack).into_expr(grammar);
+
+    for (span, tok) in postfix {
+        let partial = match tok {
+            OpToken::Blank => Partial::Expr(Expr {
+                kind: ExprKind::Blank,
+                span,
+            }),
+            OpToken::Juxt => {
+                let right = pop_expr(&mut stack)?;
+                let left = pop_expr(&mut stack)?;
+                Partial::Expr(Expr {
+                    span: left.span.to(right.span),
+                    kind: ExprKind::Juxt(Box::new(left), Box::new(right)),
+                })
+            }
+            OpToken::Part(lexeme) => {
+                let mut parts = vec![(lexeme, span)];
+                let mut args = vec![];
+                match grammar.part_kind(lexeme) {
+                    PartKind::Middle => match pop(&mut stack) {
+                        Partial::Parts(later_parts, later_args) => {
+                            parts.extend(later_parts);
+                            args = later_args;
+                        }
+                        partial => return Err(unlinked(grammar, partial, tok, span)),
+                    },
+                    PartKind::Last {
+                        right_prec: Some(_),
+                    } => args.push(pop_expr(&mut stack)?),
+                    PartKind::Last { right_prec: None } => (),
+                }
+                args.insert(0, pop_expr(&mut stack)?);
+                Partial::Parts(parts, args)
+            }
+            OpToken::Op(op) => {
+                let op = &grammar.operators[op];
+                let (mut args, last_part) = if op.parts.len() > 1 {
+                    match pop(&mut stack) {
+                        Partial::Parts(parts, args)
+                            if parts
+                                .iter()
+                                .map(|(lexeme, _)| &grammar.lexemes[*lexeme].text)
+                                .eq(&op.parts[1..]) =>
+                        {
+                            (args, parts.last().unwrap().1)
+                        }
+                        partial => return Err(unlinked(grammar, partial, tok, span)),
+                    }
+                } else if op.right_prec.is_some() {
+                    (vec![pop_expr(&mut stack)?], span)
+                } else {
+                    (vec![], span)
+                };
+                let end = match op.right_prec {
+                    Some(_) => args.last().unwrap().span,
+                    None => last_part,
+                };
+                let mut start = span;
+                if op.left_prec.is_some() {
+                    let left = pop_expr(&mut stack)?;
+                    start = left.span;
+                    args.insert(0, left);
+                }
+                Partial::Expr(Expr {
+                    span: start.to(end),
+                    kind: ExprKind::Op {
+                        name: op.name.clone(),
+                        args,
+                    },
+                })
+            }
+        };
+        stack.push(partial);
+    }
+    let expr = pop_expr(&mut stack)?;
+    assert!(stack.is_empty(), "shunt output has extra operands");
+    Ok(expr)
+}
+
+/// Shown as an s-expression, like `(+ x (neg x))`.
+impl fmt::Display for Expr {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match &self.kind {
+            ExprKind::Blank => write!(f, "_"),
+            ExprKind::Juxt(e1, e2) => write!(f, "(juxt {} {})", e1, e2),
+            ExprKind::Op { name, args } if args.is_empty() => write!(f, "{}", name),
+            ExprKind::Op { name, args } => {
+                write!(f, "({}", name)?;
+                for arg in args {
+                    write!(f, " {}", arg)?;
+                }
+                write!(f, ")")
+            }
+        }
+    }
+}
 
+/// Shows a token of postfix output, using the operator's `show` text if it has one.
+fn show(grammar: &Grammar, tok: OpToken) -> &str {
     match tok {
-        Blank => '_',
-        Juxt => '.',
-        Id => 'x',
-        Open => '(',
-        Apply => '[',
-        Close => ')',
-        Plus => '+',
-        Negative => '~',
-        Minus => '-',
-        Times => '*',
-        Question => '?',
-        Colon => ':',
-        Semi => ';',
-    }
-}
-
-fn parse(stream: &str) -> String {
-    shunt(resolve(lex(stream)))
+        OpToken::Op(op) => match &grammar.operators[op].show {
+            Some(show) => show,
+            None => grammar.text(tok),
+        },
+        _ => grammar.text(tok),
+    }
+}
+
+fn parse(grammar: &Grammar, stream: &str) -> String {
+    shunt(grammar, resolve(grammar, lex(grammar, stream)))
         .into_iter()
-        .map(|(_, tok)| show(tok))
+        .map(|(_, tok)| show(grammar, tok))
         .collect()
 }
 
+fn parse_tree(grammar: &Grammar, stream: &str) -> Result<Expr, TreeError> {
+    build_tree(
+        grammar,
+        shunt(grammar, resolve(grammar, lex(grammar, stream))),
+    )
+}
+
+fn main() {
+    let grammar = standard_grammar();
+    for source in std::env::args().skip(1) {
+        println!("postfix: {}", parse(&grammar, &source));
+        match parse_tree(&grammar, &source) {
+            Ok(expr) => println!("tree: {}", expr),
+            Err(err) => println!("error: {}", err),
+        }
+    }
+}
+
 #[test]
 fn test() {
+    let grammar = standard_grammar();
+    let parse = |stream| parse(&grammar, stream);
+
     assert_eq!(parse("x"), "x");
 
     // Blank & Juxtapose
@@ -224,3 +604,97 @@ fn test() {
     assert_eq!(parse("((x))"), "x)()(");
     assert_eq!(parse("(x((x)))"), "xx)()[)(");
 }
+
+#[test]
+fn test_tree() {
+    let grammar = standard_grammar();
+    let parse_tree = |stream| parse_tree(&grammar, stream);
+    let tree = |stream| parse_tree(stream).map(|expr| expr.to_string());
+
+    assert_eq!(tree("x"), Ok("x".to_owned()));
+    assert_eq!(tree("xx"), Ok("(juxt x x)".to_owned()));
+    assert_eq!(tree("x+"), Ok("(+ x _)".to_owned()));
+    assert_eq!(tree("x+x*x"), Ok("(+ x (* x x))".to_owned()));
+    assert_eq!(tree("-x--x"), Ok("(- (neg x) (neg x))".to_owned()));
+    assert_eq!(tree("(x+x)*x"), Ok("(* (paren (+ x x)) x)".to_owned()));
+    assert_eq!(tree("x(x)(x)"), Ok("(apply (apply x x) x)".to_owned()));
+    assert_eq!(
+        tree("x?x:x?x:x;;"),
+        Ok("(cond x x (cond x x x))".to_owned())
+    );
+    assert_eq!(tree("()"), Ok("(paren _)".to_owned()));
+
+    // Spans
+    let expr = parse_tree("x(-x)+").unwrap();
+    assert_eq!(expr.span, Span { start: 0, end: 6 });
+    match &expr.kind {
+        ExprKind::Op { name, args } if name == "+" => {
+            assert_eq!(args[0].span, Span { start: 0, end: 5 });
+            assert_eq!(args[1].span, Span::empty(6));
+        }
+        kind => panic!("expected a plus, got {:?}", kind),
+    }
+
+    // Unlinked tokens
+    let unclosed =
+        |part: &str, start, end| TreeError::Unclosed(part.to_owned(), Span { start, end });
+    let unopened =
+        |part: &str, start, end| TreeError::Unopened(part.to_owned(), Span { start, end });
+    assert_eq!(tree("(x"), Err(unclosed("(", 0, 1)));
+    assert_eq!(tree("x(x"), Err(unclosed("(", 1, 2)));
+    assert_eq!(tree("x)"), Err(unopened(")", 1, 2)));
+    assert_eq!(tree("x?x"), Err(unclosed("?", 1, 2)));
+    assert_eq!(tree("x?x:x"), Err(unclosed(":", 3, 4)));
+    assert_eq!(tree("x:x;"), Err(unopened(":", 1, 2)));
+    assert_eq!(tree("(x;)"), Err(unopened(";", 2, 3)));
+}
+
+#[test]
+fn test_grammar() {
+    let grammar = Grammar::new(
+        (10, 10),
+        vec![
+            Operator::nilfix("x", &["x"]),
+            Operator::prefix("if", &["if", "then", "else"], 200),
+            Operator::infix("*", &["*"], 60, 61),
+            Operator::infix("**", &["**"], 50, 51),
+        ],
+    )
+    .unwrap();
+    let tree = |stream| parse_tree(&grammar, stream).map(|expr| expr.to_string());
+
+    assert_eq!(tree("x*x**x"), Ok("(* x (** x x))".to_owned()));
+    assert_eq!(tree("ifxthenxelsex**x"), Ok("(if x x (** x x))".to_owned()));
+    assert_eq!(tree("ifxthenxelsex*x"), Ok("(if x x (* x x))".to_owned()));
+    assert_eq!(
+        tree("ifx*xthenx"),
+        Err(TreeError::Unclosed(
+            "then".to_owned(),
+            Span { start: 5, end: 9 }
+        ))
+    );
+
+    // Ambiguous lexemes
+    let err = |operators| Grammar::new((10, 10), operators).unwrap_err();
+    assert_eq!(
+        err(vec![
+            Operator::infix("+", &["+"], 100, 99),
+            Operator::postfix("inc", &["+"], 10),
+        ]),
+        "`+` starts both `+` and `inc`"
+    );
+    assert_eq!(
+        err(vec![
+            Operator::nilfix("paren", &["(", ")"]),
+            Operator::postfix("call", &["(", ")", ")"], 40),
+        ]),
+        "`)` continues operators in conflicting ways"
+    );
+    assert_eq!(
+        err(vec![
+            Operator::nilfix("paren", &["(", ")"]),
+            Operator::postfix("close", &[")"], 40),
+        ]),
+        "`)` both starts an operator and continues one"
+    );
+}
@@ -0,0 +1,432 @@
+//! Run every parser on generated JSON documents of different sizes and shapes, both building a
+//! `Json` and sending events, and report each one's median parse time, throughput, and peak
+//! allocation.
+//!
+//! The documents are written to `target/bench-inputs`, so that a single parser can be run on
+//! one of them with `cargo run --release -- PARSER < target/bench-inputs/FILE`.
+
+use crate::events::JsonHandler;
+use crate::Json;
+use std::alloc::{GlobalAlloc, Layout, System};
+use std::fmt::Write as _;
+use std::fs;
+use std::path::PathBuf;
+use std::sync::atomic::{AtomicBool, AtomicIsize, Ordering};
+use std::time::{Duration, Instant};
+
+const DEFAULT_RUNS: usize = 10;
+const SIZES: &[(&str, usize)] = &[("10KB", 10_000), ("100KB", 100_000), ("1MB", 1_000_000)];
+
+/*=============*
+ * Allocations *
+ *=============*/
+
+/// The system allocator, keeping track of how many bytes are allocated while `COUNTING` is on.
+/// Otherwise it only costs a check of the flag.
+struct CountingAlloc;
+
+#[global_allocator]
+static ALLOCATOR: CountingAlloc = CountingAlloc;
+
+static COUNTING: AtomicBool = AtomicBool::new(false);
+/// Bytes allocated minus bytes freed since counting started. Negative if more was freed than
+/// allocated.
+static ALLOCATED: AtomicIsize = AtomicIsize::new(0);
+static PEAK_ALLOCATED: AtomicIsize = AtomicIsize::new(0);
+
+fn record_alloc(size: usize) {
+    if COUNTING.load(Ordering::Relaxed) {
+        let allocated = ALLOCATED.fetch_add(size as isize, Ordering::Relaxed) + size as isize;
+        PEAK_ALLOCATED.fetch_max(allocated, Ordering::Relaxed);
+    }
+}
+
+fn record_dealloc(size: usize) {
+    if COUNTING.load(Ordering::Relaxed) {
+        ALLOCATED.fetch_sub(size as isize, Ordering::Relaxed);
+    }
+}
+
+unsafe impl GlobalAlloc for CountingAlloc {
+    unsafe fn alloc(&self, layout: Layout) -> *mut u8 {
+        let ptr = System.alloc(layout);
+        if !ptr.is_null() {
+            record_alloc(layout.size());
+        }
+        ptr
+    }
+
+    unsafe fn alloc_zeroed(&self, layout: Layout) -> *mut u8 {
+        let ptr = System.alloc_zeroed(layout);
+        if !ptr.is_null() {
+            record_alloc(layout.size());
+        }
+        ptr
+    }
+
+    unsafe fn dealloc(&self, ptr: *mut u8, layout: Layout) {
+        System.dealloc(ptr, layout);
+        record_dealloc(layout.size());
+    }
+
+    unsafe fn realloc(&self, ptr: *mut u8, layout: Layout, new_size: usize) -> *mut u8 {
+        let new_ptr = System.realloc(ptr, layout, new_size);
+        if !new_ptr.is_null() {
+            record_dealloc(layout.size());
+            record_alloc(new_size);
+        }
+        new_ptr
+    }
+}
+
+/// Run `f`, returning its result and the most bytes that were allocated at once while it ran,
+/// beyond what was already allocated. This includes whatever it returns.
+fn peak_allocation<T>(f: impl FnOnce() -> T) -> (T, usize) {
+    ALLOCATED.store(0, Ordering::Relaxed);
+    PEAK_ALLOCATED.store(0, Ordering::Relaxed);
+    COUNTING.store(true, Ordering::Relaxed);
+    let result = f();
+    COUNTING.store(false, Ordering::Relaxed);
+    (result, PEAK_ALLOCATED.load(Ordering::Relaxed) as usize)
+}
+
+/*========*
+ * Inputs *
+ *========*/
+
+/// A deterministic xorshift random number generator, so that the inputs are the same each run.
+struct Rng(u64);
+
+impl Rng {
+    fn next(&mut self) -> u64 {
+        self.0 ^= self.0 << 13;
+        self.0 ^= self.0 >> 7;
+        self.0 ^= self.0 << 17;
+        self.0
+    }
+
+    /// A number in `0..n`.
+    fn below(&mut self, n: usize) -> usize {
+        (self.next() % n as u64) as usize
+    }
+
+    fn pick<'a>(&mut self, options: &[&'a str]) -> &'a str {
+        options[self.below(options.len())]
+    }
+}
+
+const WORDS: &[&str] = &[
+    "lorem",
+    "ipsum",
+    "dolor",
+    "sit",
+    "amet",
+    "parser",
+    "combinator",
+    "token",
+    "grammar",
+    "héllo",
+    "世界",
+    "naïve",
+];
+const ESCAPES: &[&str] = &[
+    r"\n",
+    r"\t",
+    r#"\""#,
+    r"\\",
+    r"\/",
+    r"\u00e9",
+    r"\ud83d\ude00",
+];
+
+/// Appends one random element to the JSON.
+type Generator = fn(&mut Rng, &mut String);
+
+/// The kinds of generated document, by the elements of their top-level array.
+const SHAPES: &[(&str, Generator)] = &[
+    ("numbers", number_elem),
+    ("strings", string_elem),
+    ("records", record_elem),
+    ("nested", nested_elem),
+];
+
+/// A document of each shape and size, named like `records-100KB.json`.
+pub fn generated_inputs(sizes: &[(&str, usize)]) -> Vec<(String, String)> {
+    let mut inputs = Vec::new();
+    for (shape, elem) in SHAPES {
+        for (size_name, size) in sizes {
+            let name = format!("{}-{}.json", shape, size_name);
+            inputs.push((name, generate(*elem, *size)));
+        }
+    }
+    inputs
+}
+
+/// A top-level array of elements, one per line, of at least `size` bytes.
+fn generate(elem: Generator, size: usize) -> String {
+    let mut rng = Rng(0x2545_f491_4f6c_dd1d);
+    let mut json = "[\n".to_owned();
+    loop {
+        json.push_str("  ");
+        elem(&mut rng, &mut json);
+        if json.len() >= size {
+            break;
+        }
+        json.push_str(",\n");
+    }
+    json.push_str("\n]\n");
+    json
+}
+
+fn number_elem(rng: &mut Rng, json: &mut String) {
+    let sign = rng.pick(&["", "", "-"]);
+    let int = rng.below(100_000);
+    match rng.below(4) {
+        0 => write!(json, "{}{}", sign, int),
+        1 => write!(json, "{}{}.{}", sign, int, rng.below(1000)),
+        2 => write!(json, "{}{}e{}", sign, int, rng.below(20)),
+        _ => write!(json, "{}0.{:03}E-{}", sign, rng.below(1000), rng.below(20)),
+    }
+    .unwrap()
+}
+
+fn string_elem(rng: &mut Rng, json: &mut String) {
+    json.push('"');
+    for i in 0..1 + rng.below(12) {
+        if i > 0 {
+            json.push(' ');
+        }
+        if rng.below(5) == 0 {
+            json.push_str(rng.pick(ESCAPES));
+        } else {
+            json.push_str(rng.pick(WORDS));
+        }
+    }
+    json.push('"');
+}
+
+fn record_elem(rng: &mut Rng, json: &mut String) {
+    json.push_str("{\n    \"id\": ");
+    number_elem(rng, json);
+    json.push_str(",\n    \"name\": ");
+    string_elem(rng, json);
+    let active = rng.pick(&["true", "false"]);
+    write!(json, ",\n    \"active\": {},\n    \"tags\": [", active).unwrap();
+    for i in 0..rng.below(4) {
+        if i > 0 {
+            json.push_str(", ");
+        }
+        string_elem(rng, json);
+    }
+    let parent = rng.pick(&["null", "{}", "{\"id\": 0}"]);
+    write!(json, "],\n    \"parent\": {}\n  }}", parent).unwrap();
+}
+
+/// An array or object nested up to 100 deep, with scalars alongside each level.
+fn nested_elem(rng: &mut Rng, json: &mut String) {
+    let mut closers = Vec::new();
+    for _ in 0..1 + rng.below(100) {
+        if rng.below(2) == 0 {
+            json.push_str("[null, ");
+            closers.push(']');
+        } else {
+            json.push_str("{\"a\": 1, \"b\": ");
+            closers.push('}');
+        }
+    }
+    json.push_str("true");
+    while let Some(closer) = closers.pop() {
+        json.push(closer);
+    }
+}
+
+/*=========*
+ * Running *
+ *=========*/
+
+struct Measurement {
+    parser: &'static str,
+    /// "tree" or "events".
+    mode: &'static str,
+    input: String,
+    bytes: usize,
+    median: Duration,
+    peak_alloc: usize,
+}
+
+impl Measurement {
+    /// In megabytes per second.
+    fn throughput(&self) -> f64 {
+        self.bytes as f64 / self.median.as_secs_f64() / 1e6
+    }
+}
+
+/// Run the benchmarks, with arguments `[--runs N] [--csv FILE]`.
+pub fn run(args: &[String]) {
+    let usage = "Usage: bench [--runs N] [--csv FILE]";
+    let mut runs = DEFAULT_RUNS;
+    let mut csv_path = None;
+    let mut args = args.iter();
+    while let Some(arg) = args.next() {
+        match arg.as_str() {
+            "--runs" => match args.next().and_then(|n| n.parse().ok()) {
+                Some(n) if n > 0 => runs = n,
+                _ => panic!("--runs must be followed by a positive number. {}", usage),
+            },
+            "--csv" => match args.next() {
+                Some(path) => csv_path = Some(path),
+                None => panic!("--csv must be followed by a file name. {}", usage),
+            },
+            _ => panic!("Unknown argument `{}`. {}", arg, usage),
+        }
+    }
+
+    // Write the inputs, so they can be looked at or used by hand
+    let mut inputs = generated_inputs(SIZES);
+    let manifest_dir = PathBuf::from(env!("CARGO_MANIFEST_DIR"));
+    let sample = fs::read_to_string(manifest_dir.join("sample.json")).unwrap();
+    inputs.push(("sample.json".to_owned(), sample));
+    let input_dir = manifest_dir.join("target").join("bench-inputs");
+    fs::create_dir_all(&input_dir).unwrap();
+    for (name, input) in &inputs {
+        fs::write(input_dir.join(name), input).unwrap();
+    }
+    println!("Wrote {} inputs to {}", inputs.len(), input_dir.display());
+
+    let parsers = crate::all_parsers();
+    let mut measurements = Vec::new();
+    for (name, input) in &inputs {
+        for parser in &parsers {
+            let parse_tree = || parser.parse_json(input);
+            let parse_events = || {
+                let mut counter = EventCounter(0);
+                parser.parse_events(input, &mut counter).map(|()| counter)
+            };
+            let modes = [
+                ("tree", measure(parse_tree, runs)),
+                ("events", measure(parse_events, runs)),
+            ];
+            for (mode, result) in modes {
+                let (median, peak_alloc) = match result {
+                    Ok(measured) => measured,
+                    Err(err) => panic!("{} failed to parse {}:\n{}", parser.name(), name, err),
+                };
+                measurements.push(Measurement {
+                    parser: parser.name(),
+                    mode,
+                    input: name.clone(),
+                    bytes: input.len(),
+                    median,
+                    peak_alloc,
+                });
+            }
+        }
+    }
+
+    println!();
+    print!("{}", table(&measurements));
+    if let Some(path) = csv_path {
+        fs::write(path, csv(&measurements)).unwrap();
+        println!("\nWrote {}", path);
+    }
+}
+
+/// Counts the events, so that sending them isn't free.
+struct EventCounter(usize);
+
+impl JsonHandler for EventCounter {
+    fn on_begin_object(&mut self) {
+        self.0 += 1;
+    }
+    fn on_key(&mut self, _key: &str) {
+        self.0 += 1;
+    }
+    fn on_end_object(&mut self) {
+        self.0 += 1;
+    }
+    fn on_begin_array(&mut self) {
+        self.0 += 1;
+    }
+    fn on_end_array(&mut self) {
+        self.0 += 1;
+    }
+    fn on_value(&mut self, _value: Json) {
+        self.0 += 1;
+    }
+}
+
+/// The median time `parse` takes over `runs` runs, and the peak allocation while it runs
+/// (including what it returns).
+fn measure<T>(
+    parse: impl Fn() -> Result<T, String>,
+    runs: usize,
+) -> Result<(Duration, usize), String> {
+    // The first run is a warm-up, and the only one that counts allocations.
+    let (result, peak_alloc) = peak_allocation(&parse);
+    result?;
+
+    let mut times = Vec::with_capacity(runs);
+    for _ in 0..runs {
+        let now = Instant::now();
+        let result = parse();
+        times.push(now.elapsed());
+        // (Dropping the result isn't part of parsing.)
+        drop(result);
+    }
+    times.sort();
+    Ok((times[times.len() / 2], peak_alloc))
+}
+
+fn table(measurements: &[Measurement]) -> String {
+    let input_width = measurements
+        .iter()
+        .map(|m| m.input.len())
+        .max()
+        .unwrap_or(0);
+    let parser_width = measurements
+        .iter()
+        .map(|m| m.parser.len())
+        .max()
+        .unwrap_or(0);
+    let mut table = String::new();
+    writeln!(
+        table,
+        "{:input_width$}  {:parser_width$}  {:6}  {:>12}  {:>10}  {:>14}",
+        "input", "parser", "mode", "median (μs)", "MB/s", "peak alloc (B)",
+    )
+    .unwrap();
+    for m in measurements {
+        writeln!(
+            table,
+            "{:input_width$}  {:parser_width$}  {:6}  {:>12}  {:>10.1}  {:>14}",
+            m.input,
+            m.parser,
+            m.mode,
+            m.median.as_micros(),
+            m.throughput(),
+            m.peak_alloc,
+        )
+        .unwrap();
+    }
+    table
+}
+
+fn csv(measurements: &[Measurement]) -> String {
+    let mut csv =
+        "input,parser,mode,bytes,median_ns,throughput_mb_per_s,peak_alloc_bytes\n".to_owned();
+    for m in measurements {
+        writeln!(
+            csv,
+            "{},{},{},{},{},{:.3},{}",
+            m.input,
+            m.parser,
+            m.mode,
+            m.bytes,
+            m.median.as_nanos(),
+            m.throughput(),
+            m.peak_alloc
+        )
+        .unwrap();
+    }
+    csv
+}
@@ -0,0 +1,306 @@
+//! Every `JsonParser` must accept the documents in `conformance/y_*.json` and reject the ones
+//! in `conformance/n_*.json`, following the naming of
+//! [JSONTestSuite](https://github.com/nst/JSONTestSuite). As there, the `i_*.json` documents
+//! are ones the spec leaves up to the parser (such as numbers out of `f64`'s range), so the
+//! tests don't check them.
+
+use crate::bench::generated_inputs;
+use crate::events::{JsonHandler, TreeBuilder};
+use crate::handwritten::parse_reader_events;
+use crate::{all_parsers, Json};
+use std::fs;
+use std::io::{self, Read};
+use std::path::PathBuf;
+
+/// The contents of every corpus file whose name starts with `prefix`, sorted by name.
+fn corpus(prefix: &str) -> Vec<(String, String)> {
+    let dir = PathBuf::from(env!("CARGO_MANIFEST_DIR")).join("conformance");
+    let mut files = fs::read_dir(&dir)
+        .unwrap()
+        .map(|entry| entry.unwrap().file_name().into_string().unwrap())
+        .filter(|name| name.starts_with(prefix) && name.ends_with(".json"))
+        .map(|name| {
+            let contents = fs::read_to_string(dir.join(&name)).unwrap();
+            (name, contents)
+        })
+        .collect::<Vec<_>>();
+    files.sort();
+    assert!(!files.is_empty(), "no {}*.json files in {:?}", prefix, dir);
+    files
+}
+
+fn parse_all(input: &str) -> Json {
+    let mut result = None;
+    for parser in all_parsers() {
+        let json = match parser.parse_json(input) {
+            Ok(json) => json,
+            Err(err) => panic!("{} failed on {:?}:\n{}", parser.name(), input, err),
+        };
+        match &result {
+            None => result = Some(json),
+            Some(expected) => assert_eq!(&json, expected, "{} disagrees", parser.name()),
+        }
+    }
+    result.unwrap()
+}
+
+#[test]
+fn accept_valid_documents() {
+    for parser in all_parsers() {
+        for (name, input) in corpus("y_") {
+            let json = match parser.parse_json(&input) {
+                Ok(json) => json,
+                Err(err) => panic!("{} rejected {}:\n{}", parser.name(), name, err),
+            };
+            let printed = json.to_string();
+            match parser.parse_json(&printed) {
+                Ok(reparsed) => {
+                    assert_eq!(reparsed, json, "{} round trip of {}", parser.name(), name)
+                }
+                Err(err) => panic!(
+                    "{} rejected the printed form of {}:\n{}\n{}",
+                    parser.name(),
+                    name,
+                    printed,
+                    err
+                ),
+            }
+        }
+    }
+}
+
+#[test]
+fn reject_invalid_documents() {
+    for parser in all_parsers() {
+        for (name, input) in corpus("n_") {
+            if let Ok(json) = parser.parse_json(&input) {
+                panic!("{} accepted {} as:\n{}", parser.name(), name, json);
+            }
+            let mut tree = TreeBuilder::default();
+            if parser.parse_events(&input, &mut tree).is_ok() {
+                panic!("{} accepted {} as events", parser.name(), name);
+            }
+        }
+    }
+}
+
+#[test]
+fn events_match_tree() {
+    for parser in all_parsers() {
+        for (name, input) in corpus("y_") {
+            let mut tree = TreeBuilder::default();
+            if let Err(err) = parser.parse_events(&input, &mut tree) {
+                panic!("{} rejected {} as events:\n{}", parser.name(), name, err);
+            }
+            let expected = parser.parse_json(&input).unwrap();
+            assert_eq!(
+                tree.finish(),
+                Some(expected),
+                "{} on {}",
+                parser.name(),
+                name
+            );
+        }
+    }
+}
+
+#[test]
+fn event_order() {
+    /// Records the events as strings.
+    #[derive(Default)]
+    struct Log(Vec<String>);
+
+    impl JsonHandler for Log {
+        fn on_begin_object(&mut self) {
+            self.0.push("{".to_owned());
+        }
+        fn on_key(&mut self, key: &str) {
+            self.0.push(format!("{}:", key));
+        }
+        fn on_end_object(&mut self) {
+            self.0.push("}".to_owned());
+        }
+        fn on_begin_array(&mut self) {
+            self.0.push("[".to_owned());
+        }
+        fn on_end_array(&mut self) {
+            self.0.push("]".to_owned());
+        }
+        fn on_value(&mut self, value: Json) {
+            self.0.push(value.to_string().trim_end().to_owned());
+        }
+    }
+
+    let input = r#"{"a": [1, {}], "b\n": null, "c": []}"#;
+    let expected = [
+        "{", "a:", "[", "1", "{", "}", "]", "b\n:", "null", "c:", "[", "]", "}",
+    ];
+    for parser in all_parsers() {
+        let mut log = Log::default();
+        parser.parse_events(input, &mut log).unwrap();
+        assert_eq!(log.0, expected, "{}", parser.name());
+    }
+}
+
+/// Reads one byte at a time, to split everything across buffer boundaries.
+struct Trickle<'a>(&'a [u8]);
+
+impl Read for Trickle<'_> {
+    fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
+        match (self.0.split_first(), buf.first_mut()) {
+            (Some((byte, rest)), Some(slot)) => {
+                *slot = *byte;
+                self.0 = rest;
+                Ok(1)
+            }
+            _ => Ok(0),
+        }
+    }
+}
+
+#[test]
+fn parse_from_reader() {
+    for (name, input) in corpus("y_") {
+        let mut tree = TreeBuilder::default();
+        if let Err(err) = parse_reader_events(Trickle(input.as_bytes()), &mut tree) {
+            panic!("rejected {} from a reader:\n{}", name, err);
+        }
+        assert_eq!(tree.finish(), Some(parse_all(&input)), "{}", name);
+    }
+    for (name, input) in corpus("n_") {
+        let mut tree = TreeBuilder::default();
+        if parse_reader_events(Trickle(input.as_bytes()), &mut tree).is_ok() {
+            panic!("accepted {} from a reader", name);
+        }
+    }
+
+    let mut tree = TreeBuilder::default();
+    let err = parse_reader_events(&b"[\"ok\",\n \"\xff\"]"[..], &mut tree).unwrap_err();
+    assert!(
+        err.starts_with("line 2, column 2: invalid utf-8"),
+        "{}",
+        err
+    );
+}
+
+#[test]
+fn deep_nesting_from_reader() {
+    /// Tracks how deeply nested the events are.
+    #[derive(Default)]
+    struct Depth {
+        depth: usize,
+        max: usize,
+        values: usize,
+    }
+
+    impl JsonHandler for Depth {
+        fn on_begin_object(&mut self) {
+            self.depth += 1;
+            self.max = self.max.max(self.depth);
+        }
+        fn on_key(&mut self, _key: &str) {}
+        fn on_end_object(&mut self) {
+            self.depth -= 1;
+        }
+        fn on_begin_array(&mut self) {
+            self.depth += 1;
+            self.max = self.max.max(self.depth);
+        }
+        fn on_end_array(&mut self) {
+            self.depth -= 1;
+        }
+        fn on_value(&mut self, _value: Json) {
+            self.values += 1;
+        }
+    }
+
+    // Far deeper than the call stack could hold, if each level were a call.
+    let n = 1_000_000;
+    let input = "[{\"a\":".repeat(n) + "0" + &"}]".repeat(n);
+    let mut depth = Depth::default();
+    parse_reader_events(input.as_bytes(), &mut depth).unwrap();
+    assert_eq!((depth.depth, depth.max, depth.values), (0, 2 * n, 1));
+
+    let input = "[".repeat(n) + &"]".repeat(n - 1);
+    let err = parse_reader_events(input.as_bytes(), &mut Depth::default()).unwrap_err();
+    assert!(
+        err.ends_with("expected `,` or `]` but found end of input"),
+        "{}",
+        err
+    );
+}
+
+#[test]
+fn parsers_agree() {
+    for (_, input) in corpus("y_") {
+        parse_all(&input);
+    }
+}
+
+#[test]
+fn numbers() {
+    use Json::Number;
+
+    assert_eq!(parse_all("0"), Number(0.0));
+    assert_eq!(parse_all("-123"), Number(-123.0));
+    assert_eq!(parse_all("-0.5"), Number(-0.5));
+    assert_eq!(parse_all("1E-2"), Number(0.01));
+    assert_eq!(parse_all("1e+3"), Number(1000.0));
+    assert_eq!(parse_all("-1.25e2"), Number(-125.0));
+    assert_eq!(parse_all("1e-400"), Number(0.0));
+    match parse_all("-0") {
+        Number(n) => assert!(n == 0.0 && n.is_sign_negative()),
+        json => panic!("expected a number, got {}", json),
+    }
+}
+
+#[test]
+fn strings() {
+    let string = |s: &str| Json::Array(vec![Json::String(s.to_owned())]);
+
+    assert_eq!(
+        parse_all(r#"["\" \\ \/ \b \f \n \r \t"]"#),
+        string("\" \\ / \u{8} \u{c} \n \r \t")
+    );
+    assert_eq!(parse_all(r#"["\u00e9\u00E9"]"#), string("éé"));
+    assert_eq!(parse_all(r#"["\ud83d\ude00"]"#), string("😀"));
+    assert_eq!(parse_all(r#"["\u0000"]"#), string("\0"));
+    assert_eq!(parse_all("[\"héllo, 世界\"]"), string("héllo, 世界"));
+}
+
+#[test]
+fn objects_keep_order_and_unescape_keys() {
+    assert_eq!(
+        parse_all(r#"{"b": 1, "a\n": [true, null]}"#),
+        Json::Object(vec![
+            ("b".to_owned(), Json::Number(1.0)),
+            (
+                "a\n".to_owned(),
+                Json::Array(vec![Json::Boolean(true), Json::Null])
+            ),
+        ])
+    );
+}
+
+#[test]
+fn display_escapes_strings() {
+    let json = Json::Object(vec![(
+        "k\"ey".to_owned(),
+        Json::String("a\\b\n\u{1}".to_owned()),
+    )]);
+    assert_eq!(
+        json.to_string(),
+        "{\n  \"k\\\"ey\": \"a\\\\b\\n\\u0001\"\n}\n"
+    );
+    assert_eq!(parse_all(&json.to_string()), json);
+}
+
+#[test]
+fn parse_benchmark_inputs() {
+    for (name, input) in generated_inputs(&[("2KB", 2_000)]) {
+        match parse_all(&input) {
+            Json::Array(elems) => assert!(elems.len() > 1, "{}", name),
+            json => panic!("{} isn't an array:\n{}", name, json),
+        }
+    }
+}
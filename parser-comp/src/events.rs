@@ -0,0 +1,132 @@
+//! Streaming JSON: instead of building a `Json`, parsers can send each part of a document to a
+//! `JsonHandler` as soon as it's parsed.
+
+use crate::Json;
+use std::cell::Cell;
+
+/// Receives the parts of a JSON document in order, as they're parsed. For example `{"a": [1]}`
+/// is `on_begin_object`, `on_key("a")`, `on_begin_array`, `on_value(1)`, `on_end_array`,
+/// `on_end_object`.
+pub trait JsonHandler {
+    fn on_begin_object(&mut self);
+    /// The key of the next entry of the innermost object.
+    fn on_key(&mut self, key: &str);
+    fn on_end_object(&mut self);
+    fn on_begin_array(&mut self);
+    fn on_end_array(&mut self);
+    /// A null, boolean, number, or string. Never an array or object.
+    fn on_value(&mut self, value: Json);
+}
+
+/// Builds a `Json` from the events, the same one `parse_json` would return. The tests use it to
+/// check that the two agree.
+#[cfg(test)]
+#[derive(Debug, Default)]
+pub struct TreeBuilder {
+    open: Vec<Partial>,
+    done: Option<Json>,
+}
+
+#[cfg(test)]
+/// An array or object that hasn't ended yet.
+#[derive(Debug)]
+enum Partial {
+    Array(Vec<Json>),
+    /// The entries so far, and the key of the next one.
+    Object(Vec<(String, Json)>, String),
+}
+
+#[cfg(test)]
+impl TreeBuilder {
+    /// The document, if all of it was sent.
+    pub fn finish(self) -> Option<Json> {
+        self.done
+    }
+
+    fn add(&mut self, json: Json) {
+        match self.open.last_mut() {
+            None => self.done = Some(json),
+            Some(Partial::Array(elems)) => elems.push(json),
+            Some(Partial::Object(entries, key)) => entries.push((std::mem::take(key), json)),
+        }
+    }
+}
+
+#[cfg(test)]
+impl JsonHandler for TreeBuilder {
+    fn on_begin_object(&mut self) {
+        self.open.push(Partial::Object(Vec::new(), String::new()));
+    }
+
+    fn on_key(&mut self, key: &str) {
+        if let Some(Partial::Object(_, next_key)) = self.open.last_mut() {
+            *next_key = key.to_owned();
+        }
+    }
+
+    fn on_end_object(&mut self) {
+        if let Some(Partial::Object(entries, _)) = self.open.pop() {
+            self.add(Json::Object(entries));
+        }
+    }
+
+    fn on_begin_array(&mut self) {
+        self.open.push(Partial::Array(Vec::new()));
+    }
+
+    fn on_end_array(&mut self) {
+        if let Some(Partial::Array(elems)) = self.open.pop() {
+            self.add(Json::Array(elems));
+        }
+    }
+
+    fn on_value(&mut self, value: Json) {
+        self.add(value);
+    }
+}
+
+/*=================*
+ * Library parsers *
+ *=================*/
+
+// The parser libraries' parsers are built ahead of time, so their actions can't borrow the
+// handler passed to each `parse_events` call. Instead, the handler is put somewhere they can
+// reach it for the duration of the call.
+
+thread_local! {
+    static HANDLER: Cell<Option<*mut (dyn JsonHandler + 'static)>> = const { Cell::new(None) };
+}
+
+/// Run `parse`, during which `emit` sends events to `handler`.
+pub fn with_handler<T>(handler: &mut dyn JsonHandler, parse: impl FnOnce() -> T) -> T {
+    /// Puts back the previous handler, even on panic.
+    struct Restore(Option<*mut (dyn JsonHandler + 'static)>);
+
+    impl Drop for Restore {
+        fn drop(&mut self) {
+            HANDLER.with(|slot| slot.set(self.0));
+        }
+    }
+
+    let ptr: *mut (dyn JsonHandler + '_) = handler;
+    // SAFETY: This only erases the lifetime. The pointer is only used by `emit`, which can only
+    // run while `handler` is borrowed by this call, because `Restore` removes it afterwards.
+    let ptr = unsafe {
+        std::mem::transmute::<*mut (dyn JsonHandler + '_), *mut (dyn JsonHandler + 'static)>(ptr)
+    };
+    let _restore = Restore(HANDLER.with(|slot| slot.replace(Some(ptr))));
+    parse()
+}
+
+/// Send an event to the handler given to the enclosing `with_handler`.
+pub fn emit(event: impl FnOnce(&mut dyn JsonHandler)) {
+    HANDLER.with(|slot| {
+        // Take it out while it's in use, so that it can't be aliased by a nested `emit`.
+        let ptr = slot
+            .take()
+            .expect("JSON event emitted outside of `with_handler`");
+        // SAFETY: See `with_handler`.
+        event(unsafe { &mut *ptr });
+        slot.set(Some(ptr));
+    })
+}
@@ -0,0 +1,555 @@
+//! A hand-written parser, as a baseline for the parser libraries. It builds trees by recursive
+//! descent, but sends events from a loop with an explicit stack, so that it can also parse from
+//! a reader, for documents too large to fit in memory and nested arbitrarily deep.
+
+use crate::events::JsonHandler;
+use crate::{parse_number, unescape_string, Json, JsonParser};
+use std::fmt;
+use std::io::{self, BufRead, BufReader, Read};
+use std::str::{self, Utf8Error};
+
+pub struct HandwrittenParser;
+
+impl JsonParser for HandwrittenParser {
+    fn new() -> HandwrittenParser {
+        HandwrittenParser
+    }
+
+    fn name(&self) -> &'static str {
+        "handwritten"
+    }
+
+    fn parse_json(&self, input: &str) -> Result<Json, String> {
+        let mut scanner = Scanner {
+            input: StrInput::new(input),
+        };
+        let json = scanner.value()?;
+        scanner.end()?;
+        Ok(json)
+    }
+
+    fn parse_events(&self, input: &str, handler: &mut dyn JsonHandler) -> Result<(), String> {
+        parse(StrInput::new(input), handler)
+    }
+}
+
+/// Parse a document from `reader`, sending its parts to `handler` as they're read. Only the
+/// current string or number and the kinds of the open containers are held in memory, so the
+/// document can be arbitrarily large.
+pub fn parse_reader_events(reader: impl Read, handler: &mut dyn JsonHandler) -> Result<(), String> {
+    parse(ReaderInput::new(reader), handler)
+}
+
+fn parse<I: Input, H: JsonHandler + ?Sized>(input: I, handler: &mut H) -> Result<(), String> {
+    let mut parser = Parser {
+        scanner: Scanner { input },
+        handler,
+        stack: Vec::new(),
+    };
+    parser.value()?;
+    parser.scanner.end()
+}
+
+/*=======*
+ * Input *
+ *=======*/
+
+/// Where the bytes being parsed come from.
+trait Input {
+    /// A position in the input, for error messages.
+    type Pos: Copy;
+
+    /// The next byte, or `None` at the end of the input.
+    fn peek(&mut self) -> Option<u8>;
+    /// Move past the next byte.
+    fn bump(&mut self);
+    /// Start keeping the bytes that are moved past, until `kept` is called.
+    fn keep(&mut self);
+    /// The bytes moved past since `keep`, which never end partway through a character.
+    fn kept(&mut self) -> Result<&str, Utf8Error>;
+    /// The position of the next byte.
+    fn pos(&self) -> Self::Pos;
+    /// Show a position, like "line 1, column 2".
+    fn show_pos(&self, pos: Self::Pos) -> String;
+    /// An error that ended the input early.
+    fn io_error(&self) -> Option<&io::Error>;
+}
+
+struct StrInput<'a> {
+    input: &'a str,
+    /// A byte offset into `input`. Only ever advanced past ASCII bytes or whole string
+    /// contents, so it's always at a char boundary when it matters.
+    pos: usize,
+    kept_from: usize,
+}
+
+impl StrInput<'_> {
+    fn new(input: &str) -> StrInput<'_> {
+        StrInput {
+            input,
+            pos: 0,
+            kept_from: 0,
+        }
+    }
+}
+
+impl Input for StrInput<'_> {
+    type Pos = usize;
+
+    fn peek(&mut self) -> Option<u8> {
+        self.input.as_bytes().get(self.pos).copied()
+    }
+
+    fn bump(&mut self) {
+        self.pos += 1;
+    }
+
+    fn keep(&mut self) {
+        self.kept_from = self.pos;
+    }
+
+    fn kept(&mut self) -> Result<&str, Utf8Error> {
+        Ok(&self.input[self.kept_from..self.pos])
+    }
+
+    fn pos(&self) -> usize {
+        self.pos
+    }
+
+    fn show_pos(&self, pos: usize) -> String {
+        let before = &self.input[..pos];
+        let line_start = before.rfind('\n').map_or(0, |i| i + 1);
+        let line = before.matches('\n').count() + 1;
+        let col = before[line_start..].chars().count() + 1;
+        format!("line {}, column {}", line, col)
+    }
+
+    fn io_error(&self) -> Option<&io::Error> {
+        None
+    }
+}
+
+struct ReaderInput<R: Read> {
+    reader: BufReader<R>,
+    keeping: bool,
+    kept: Vec<u8>,
+    /// The line and column (in characters) of the next byte.
+    line: usize,
+    col: usize,
+    error: Option<io::Error>,
+}
+
+impl<R: Read> ReaderInput<R> {
+    fn new(reader: R) -> ReaderInput<R> {
+        ReaderInput {
+            reader: BufReader::new(reader),
+            keeping: false,
+            kept: Vec::new(),
+            line: 1,
+            col: 1,
+            error: None,
+        }
+    }
+}
+
+impl<R: Read> Input for ReaderInput<R> {
+    type Pos = (usize, usize);
+
+    fn peek(&mut self) -> Option<u8> {
+        loop {
+            match self.reader.fill_buf() {
+                Ok(buf) => return buf.first().copied(),
+                Err(err) if err.kind() == io::ErrorKind::Interrupted => (),
+                Err(err) => {
+                    self.error = Some(err);
+                    return None;
+                }
+            }
+        }
+    }
+
+    fn bump(&mut self) {
+        let byte = match self.peek() {
+            Some(byte) => byte,
+            None => return,
+        };
+        if self.keeping {
+            self.kept.push(byte);
+        }
+        if byte == b'\n' {
+            self.line += 1;
+            self.col = 1;
+        } else if byte & 0b1100_0000 != 0b1000_0000 {
+            // (Not counting UTF-8 continuation bytes.)
+            self.col += 1;
+        }
+        self.reader.consume(1);
+    }
+
+    fn keep(&mut self) {
+        self.keeping = true;
+        self.kept.clear();
+    }
+
+    fn kept(&mut self) -> Result<&str, Utf8Error> {
+        self.keeping = false;
+        str::from_utf8(&self.kept)
+    }
+
+    fn pos(&self) -> (usize, usize) {
+        (self.line, self.col)
+    }
+
+    fn show_pos(&self, (line, col): (usize, usize)) -> String {
+        format!("line {}, column {}", line, col)
+    }
+
+    fn io_error(&self) -> Option<&io::Error> {
+        self.error.as_ref()
+    }
+}
+
+/*=========*
+ * Scanner *
+ *=========*/
+
+/// The parts of parsing that don't depend on whether a `Json` is being built or events are
+/// being sent.
+struct Scanner<I: Input> {
+    input: I,
+}
+
+impl<I: Input> Scanner<I> {
+    fn literal(&mut self, word: &str) -> Result<(), String> {
+        for byte in word.bytes() {
+            if !self.eat(byte) {
+                return Err(self.expected(&format!("`{}`", word)));
+            }
+        }
+        Ok(())
+    }
+
+    fn number(&mut self) -> Result<f64, String> {
+        let start = self.input.pos();
+        self.input.keep();
+        self.eat(b'-');
+        if !self.eat(b'0') {
+            if !matches!(self.input.peek(), Some(b'1'..=b'9')) {
+                return Err(self.expected("a digit"));
+            }
+            self.digits();
+        }
+        if self.eat(b'.') && !self.digits() {
+            return Err(self.expected("a digit"));
+        }
+        if let Some(b'e' | b'E') = self.input.peek() {
+            self.input.bump();
+            if let Some(b'+' | b'-') = self.input.peek() {
+                self.input.bump();
+            }
+            if !self.digits() {
+                return Err(self.expected("a digit"));
+            }
+        }
+        // The number is ASCII, so it's valid UTF-8.
+        parse_number(self.input.kept().unwrap()).map_err(|err| self.error_at(start, err))
+    }
+
+    /// A string literal, starting at its opening quote.
+    fn string(&mut self) -> Result<String, String> {
+        let start = self.input.pos();
+        let mut has_escapes = false;
+        self.input.bump();
+        self.input.keep();
+        loop {
+            match self.input.peek() {
+                Some(b'"') => break,
+                Some(b'\\') => {
+                    has_escapes = true;
+                    self.input.bump();
+                    match self.input.peek() {
+                        Some(b'"' | b'\\' | b'/' | b'b' | b'f' | b'n' | b'r' | b't') => {
+                            self.input.bump()
+                        }
+                        Some(b'u') => {
+                            self.input.bump();
+                            for _ in 0..4 {
+                                if !matches!(self.input.peek(), Some(b) if b.is_ascii_hexdigit()) {
+                                    return Err(self.expected("a hex digit"));
+                                }
+                                self.input.bump();
+                            }
+                        }
+                        _ => return Err(self.expected("an escape sequence")),
+                    }
+                }
+                Some(0x00..=0x1F) => {
+                    return Err(self.error("control characters in strings must be escaped"))
+                }
+                Some(_) => self.input.bump(),
+                None => return Err(self.expected("`\"`")),
+            }
+        }
+        let string = match self.input.kept() {
+            Ok(contents) if has_escapes => unescape_string(contents).map_err(|err| err.to_string()),
+            Ok(contents) => Ok(contents.to_owned()),
+            Err(err) => Err(err.to_string()),
+        };
+        self.input.bump();
+        string.map_err(|err| self.error_at(start, err))
+    }
+
+    /// Skip over digits, returning whether there were any.
+    fn digits(&mut self) -> bool {
+        let mut any = false;
+        while let Some(b'0'..=b'9') = self.input.peek() {
+            self.input.bump();
+            any = true;
+        }
+        any
+    }
+
+    fn skip_whitespace(&mut self) {
+        while let Some(b' ' | b'\t' | b'\r' | b'\n') = self.input.peek() {
+            self.input.bump();
+        }
+    }
+
+    fn eat(&mut self, byte: u8) -> bool {
+        if self.input.peek() == Some(byte) {
+            self.input.bump();
+            true
+        } else {
+            false
+        }
+    }
+
+    /// Check that only whitespace is left.
+    fn end(&mut self) -> Result<(), String> {
+        self.skip_whitespace();
+        match self.input.peek() {
+            Some(_) => Err(self.expected("end of input")),
+            None => Ok(()),
+        }
+    }
+
+    fn expected(&mut self, expected: &str) -> String {
+        match self.input.peek() {
+            None => self.error(format!("expected {} but found end of input", expected)),
+            Some(byte) if byte.is_ascii() => self.error(format!(
+                "expected {} but found {:?}",
+                expected, byte as char
+            )),
+            Some(_) => self.error(format!(
+                "expected {} but found a non-ASCII character",
+                expected
+            )),
+        }
+    }
+
+    /// An error message at the current position.
+    fn error(&self, message: impl fmt::Display) -> String {
+        self.error_at(self.input.pos(), message)
+    }
+
+    fn error_at(&self, pos: I::Pos, message: impl fmt::Display) -> String {
+        let pos = self.input.show_pos(pos);
+        match self.input.io_error() {
+            Some(err) => format!("{}: {}", pos, err),
+            None => format!("{}: {}", pos, message),
+        }
+    }
+}
+
+/*==============*
+ * Tree builder *
+ *==============*/
+
+// Builds each `Json` directly, rather than through a `TreeBuilder`, so that `parse_json` stays a
+// plain recursive descent baseline. Like the library parsers, it recurses once per level of
+// nesting; the event parser below doesn't.
+impl<I: Input> Scanner<I> {
+    fn value(&mut self) -> Result<Json, String> {
+        self.skip_whitespace();
+        match self.input.peek() {
+            Some(b'n') => self.literal("null").map(|()| Json::Null),
+            Some(b't') => self.literal("true").map(|()| Json::Boolean(true)),
+            Some(b'f') => self.literal("false").map(|()| Json::Boolean(false)),
+            Some(b'"') => self.string().map(Json::String),
+            Some(b'[') => self.array(),
+            Some(b'{') => self.object(),
+            Some(b'-' | b'0'..=b'9') => self.number().map(Json::Number),
+            _ => Err(self.expected("a JSON value")),
+        }
+    }
+
+    fn array(&mut self) -> Result<Json, String> {
+        self.input.bump();
+        let mut elems = Vec::new();
+        self.skip_whitespace();
+        if self.eat(b']') {
+            return Ok(Json::Array(elems));
+        }
+        loop {
+            elems.push(self.value()?);
+            self.skip_whitespace();
+            if self.eat(b']') {
+                return Ok(Json::Array(elems));
+            }
+            if !self.eat(b',') {
+                return Err(self.expected("`,` or `]`"));
+            }
+        }
+    }
+
+    fn object(&mut self) -> Result<Json, String> {
+        self.input.bump();
+        let mut entries = Vec::new();
+        self.skip_whitespace();
+        if self.eat(b'}') {
+            return Ok(Json::Object(entries));
+        }
+        loop {
+            self.skip_whitespace();
+            if self.input.peek() != Some(b'"') {
+                return Err(self.expected("a string key"));
+            }
+            let key = self.string()?;
+            self.skip_whitespace();
+            if !self.eat(b':') {
+                return Err(self.expected("`:`"));
+            }
+            entries.push((key, self.value()?));
+            self.skip_whitespace();
+            if self.eat(b'}') {
+                return Ok(Json::Object(entries));
+            }
+            if !self.eat(b',') {
+                return Err(self.expected("`,` or `}`"));
+            }
+        }
+    }
+}
+
+/*========*
+ * Events *
+ *========*/
+
+/// A container whose closing bracket hasn't been reached yet.
+#[derive(Debug, Clone, Copy)]
+enum Open {
+    Array,
+    Object,
+}
+
+/// Sends events without recursing, keeping the open containers in `stack` instead, so that
+/// deeply nested documents can't overflow the call stack.
+struct Parser<'h, I: Input, H: JsonHandler + ?Sized> {
+    scanner: Scanner<I>,
+    handler: &'h mut H,
+    stack: Vec<Open>,
+}
+
+impl<I: Input, H: JsonHandler + ?Sized> Parser<'_, I, H> {
+    /// Parse one value, including everything nested inside it.
+    fn value(&mut self) -> Result<(), String> {
+        loop {
+            if self.start_value()? {
+                continue;
+            }
+            // A value just ended. Close containers until one continues with a `,`.
+            loop {
+                let Some(&open) = self.stack.last() else {
+                    return Ok(());
+                };
+                self.scanner.skip_whitespace();
+                let (close, expected) = match open {
+                    Open::Array => (b']', "`,` or `]`"),
+                    Open::Object => (b'}', "`,` or `}`"),
+                };
+                if self.scanner.eat(close) {
+                    self.stack.pop();
+                    self.close(open);
+                } else if self.scanner.eat(b',') {
+                    if let Open::Object = open {
+                        self.key()?;
+                    }
+                    break;
+                } else {
+                    return Err(self.scanner.expected(expected));
+                }
+            }
+        }
+    }
+
+    /// Parse a scalar or an empty container, returning false, or open a non-empty container
+    /// and parse up to its first value, returning true.
+    fn start_value(&mut self) -> Result<bool, String> {
+        let scanner = &mut self.scanner;
+        scanner.skip_whitespace();
+        let json = match scanner.input.peek() {
+            Some(b'n') => scanner.literal("null").map(|()| Json::Null)?,
+            Some(b't') => scanner.literal("true").map(|()| Json::Boolean(true))?,
+            Some(b'f') => scanner.literal("false").map(|()| Json::Boolean(false))?,
+            Some(b'"') => Json::String(scanner.string()?),
+            Some(b'[') => return Ok(self.open(Open::Array)),
+            Some(b'{') => {
+                if !self.open(Open::Object) {
+                    return Ok(false);
+                }
+                self.key()?;
+                return Ok(true);
+            }
+            Some(b'-' | b'0'..=b'9') => Json::Number(scanner.number()?),
+            _ => return Err(scanner.expected("a JSON value")),
+        };
+        self.handler.on_value(json);
+        Ok(false)
+    }
+
+    /// Move past an opening bracket. If the container is empty, close it too and return false.
+    fn open(&mut self, open: Open) -> bool {
+        self.scanner.input.bump();
+        let close = match open {
+            Open::Array => {
+                self.handler.on_begin_array();
+                b']'
+            }
+            Open::Object => {
+                self.handler.on_begin_object();
+                b'}'
+            }
+        };
+        self.scanner.skip_whitespace();
+        if self.scanner.eat(close) {
+            self.close(open);
+            false
+        } else {
+            self.stack.push(open);
+            true
+        }
+    }
+
+    /// Send the end of a container, whose closing bracket was just moved past.
+    fn close(&mut self, open: Open) {
+        match open {
+            Open::Array => self.handler.on_end_array(),
+            Open::Object => self.handler.on_end_object(),
+        }
+    }
+
+    /// An object key and the `:` after it.
+    fn key(&mut self) -> Result<(), String> {
+        self.scanner.skip_whitespace();
+        if self.scanner.input.peek() != Some(b'"') {
+            return Err(self.scanner.expected("a string key"));
+        }
+        let key = self.scanner.string()?;
+        self.handler.on_key(&key);
+        self.scanner.skip_whitespace();
+        if !self.scanner.eat(b':') {
+            return Err(self.scanner.expected("`:`"));
+        }
+        Ok(())
+    }
+}
@@ -1,34 +1,48 @@
-use crate::{Json, JsonParser};
+use crate::events::{emit, with_handler, JsonHandler};
+use crate::{parse_number, unescape_string, Json, JsonParser};
 use parser_ll1::{
     choice, tuple, CompiledParser, Grammar, GrammarError, ParseError, Parser, Recursive,
 };
 
-pub struct LL1Parser(Box<dyn Fn(&str, &str) -> Result<Json, ParseError>>);
+const WHITESPACE_REGEX: &str = "[ \t\r\n]+";
+const NUMBER_REGEX: &str = r"-?(0|[1-9][0-9]*)(\.[0-9]+)?([eE][+-]?[0-9]+)?";
+/// Strings, which can't contain control characters.
+const STRING_REGEX: &str = r#""([^"\\\x00-\x1F]|\\["\\/bfnrt]|\\u[0-9a-fA-F]{4})*""#;
+
+pub struct LL1Parser {
+    parse_tree: Box<dyn Fn(&str, &str) -> Result<Json, ParseError>>,
+    parse_events: Box<dyn Fn(&str, &str) -> Result<(), ParseError>>,
+}
 
 impl JsonParser for LL1Parser {
     fn new() -> LL1Parser {
         let compiled_parser = make_json_parse_fn().unwrap();
-        LL1Parser(Box::new(move |filename: &str, input: &str| {
-            compiled_parser.parse(filename, input)
-        }))
+        let compiled_events_parser = make_json_events_parse_fn().unwrap();
+        LL1Parser {
+            parse_tree: Box::new(move |filename: &str, input: &str| {
+                compiled_parser.parse(filename, input)
+            }),
+            parse_events: Box::new(move |filename: &str, input: &str| {
+                compiled_events_parser.parse(filename, input)
+            }),
+        }
     }
 
     fn name(&self) -> &'static str {
         "ll1"
     }
 
-    fn parse_json(&self, input: &str) -> Json {
-        match (self.0)("stdin", input) {
-            Ok(json) => json,
-            Err(err) => panic!("{}", err),
-        }
+    fn parse_json(&self, input: &str) -> Result<Json, String> {
+        (self.parse_tree)("stdin", input).map_err(|err| err.to_string())
+    }
+
+    fn parse_events(&self, input: &str, handler: &mut dyn JsonHandler) -> Result<(), String> {
+        with_handler(handler, || (self.parse_events)("stdin", input)).map_err(|err| err.to_string())
     }
 }
 
 fn make_json_parse_fn() -> Result<impl CompiledParser<Json>, GrammarError> {
-    use std::str::FromStr;
-
-    let mut g = Grammar::with_whitespace("[ \t\r\n]+")?;
+    let mut g = Grammar::with_whitespace(WHITESPACE_REGEX)?;
 
     let json_p = Recursive::new("json value");
 
@@ -40,16 +54,16 @@ fn make_json_parse_fn() -> Result<impl CompiledParser<Json>, GrammarError> {
     let false_p = g.string("false")?.constant(Json::Boolean(false));
     let bool_p = choice("boolean", (true_p, false_p));
 
-    // Numbers. This is a bad regex that only works for some numbers
+    // Numbers
     let number_p = g
-        .regex("number", "[1-9][0-9]*(\\.[0-9]*)?|\\.[0-9]*")?
-        .try_span(|s| f64::from_str(s.substr))
+        .regex("number", NUMBER_REGEX)?
+        .try_span(|s| parse_number(s.substr))
         .map(Json::Number);
 
-    // Strings. Not implementing Json string escapes for this small test case.
+    // Strings
     let plain_string_p = g
-        .regex("string", r#""([^"\\]|\\.)*""#)?
-        .span(|span| span.substr[1..span.substr.len() - 1].to_owned());
+        .regex("string", STRING_REGEX)?
+        .try_span(|span| unescape_string(&span.substr[1..span.substr.len() - 1]));
     let string_p = plain_string_p.clone().map(Json::String);
 
     // Arrays
@@ -74,3 +88,60 @@ fn make_json_parse_fn() -> Result<impl CompiledParser<Json>, GrammarError> {
 
     g.compile_parser(json_p)
 }
+
+/// The same grammar, sending events instead of building a `Json`.
+fn make_json_events_parse_fn() -> Result<impl CompiledParser<()>, GrammarError> {
+    let mut g = Grammar::with_whitespace(WHITESPACE_REGEX)?;
+
+    let json_p = Recursive::new("json value");
+    let value = |json: Json| emit(|handler| handler.on_value(json));
+
+    // Null
+    let null_p = g.string("null")?.map(move |_| value(Json::Null));
+
+    // Bools
+    let true_p = g.string("true")?.map(move |_| value(Json::Boolean(true)));
+    let false_p = g.string("false")?.map(move |_| value(Json::Boolean(false)));
+    let bool_p = choice("boolean", (true_p, false_p));
+
+    // Numbers
+    let number_p = g
+        .regex("number", NUMBER_REGEX)?
+        .try_span(|s| parse_number(s.substr))
+        .map(move |n| value(Json::Number(n)));
+
+    // Strings
+    let plain_string_p = g
+        .regex("string", STRING_REGEX)?
+        .try_span(|span| unescape_string(&span.substr[1..span.substr.len() - 1]));
+    let string_p = plain_string_p.clone().map(move |s| value(Json::String(s)));
+
+    // Arrays
+    let begin_array_p = g
+        .string("[")?
+        .map(|_| emit(|handler| handler.on_begin_array()));
+    let end_array_p = g
+        .string("]")?
+        .map(|_| emit(|handler| handler.on_end_array()));
+    let array_elems_p = json_p.refn().many_sep0(g.string(",")?);
+    let array_p = tuple("array", (begin_array_p, array_elems_p, end_array_p)).map(|_| ());
+
+    // Objects
+    let key_p = plain_string_p.map(|key| emit(|handler| handler.on_key(&key)));
+    let entry_p = tuple("dictionary entry", (key_p, g.string(":")?, json_p.refn())).map(|_| ());
+    let entries_p = entry_p.many_sep0(g.string(",")?);
+    let begin_dict_p = g
+        .string("{")?
+        .map(|_| emit(|handler| handler.on_begin_object()));
+    let end_dict_p = g
+        .string("}")?
+        .map(|_| emit(|handler| handler.on_end_object()));
+    let dict_p = tuple("dictionary", (begin_dict_p, entries_p, end_dict_p)).map(|_| ());
+
+    let json_p = json_p.define(choice(
+        "json value",
+        (null_p, bool_p, number_p, string_p, array_p, dict_p),
+    ));
+
+    g.compile_parser(json_p)
+}
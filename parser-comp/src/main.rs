@@ -1,6 +1,13 @@
+mod bench;
+#[cfg(test)]
+mod conformance;
+mod events;
+mod handwritten;
 mod ll1;
 mod nomp;
+mod strnomp;
 
+use events::JsonHandler;
 use std::env;
 use std::fmt;
 use std::io;
@@ -10,10 +17,29 @@ trait JsonParser {
     where
         Self: Sized;
     fn name(&self) -> &'static str;
-    fn parse_json(&self, input: &str) -> Json;
+    /// Parse a JSON document, or return a message saying why it's invalid.
+    fn parse_json(&self, input: &str) -> Result<Json, String>;
+    /// Parse a JSON document, sending each part of it to `handler` as it's parsed instead of
+    /// building a `Json`. If the document is invalid, the parts before the error have already
+    /// been sent.
+    fn parse_events(&self, input: &str, handler: &mut dyn JsonHandler) -> Result<(), String>;
 }
 
-#[derive(Debug, Clone)]
+fn all_parsers() -> Vec<Box<dyn JsonParser>> {
+    use handwritten::HandwrittenParser;
+    use ll1::LL1Parser;
+    use nomp::NomParser;
+    use strnomp::StrnomParser;
+
+    vec![
+        Box::new(LL1Parser::new()),
+        Box::new(NomParser::new()),
+        Box::new(StrnomParser::new()),
+        Box::new(HandwrittenParser::new()),
+    ]
+}
+
+#[derive(Debug, Clone, PartialEq)]
 enum Json {
     Null,
     Boolean(bool),
@@ -34,7 +60,7 @@ impl Json {
             Boolean(false) => write!(f, "false"),
             Boolean(true) => write!(f, "true"),
             Number(n) => write!(f, "{}", n),
-            String(s) => write!(f, "\"{}\"", s),
+            String(s) => write_string(f, s),
             Array(elems) => {
                 writeln!(f, "[")?;
                 for (i, elem) in elems.iter().enumerate() {
@@ -53,7 +79,8 @@ impl Json {
                 writeln!(f, "{{")?;
                 for (i, entry) in entries.iter().enumerate() {
                     write!(f, "{:indent$}", "", indent = INDENT * (indent + 1))?;
-                    write!(f, "\"{}\": ", entry.0,)?;
+                    write_string(f, &entry.0)?;
+                    write!(f, ": ")?;
                     entry.1.write(f, indent + 1)?;
                     if i + 1 != entries.len() {
                         writeln!(f, ",")?;
@@ -71,19 +98,122 @@ impl Json {
 impl fmt::Display for Json {
     fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
         self.write(f, 0)?;
-        write!(f, "\n")
+        writeln!(f)
+    }
+}
+
+/// Write a JSON string literal, escaping quotes, backslashes, and control characters.
+fn write_string(f: &mut fmt::Formatter, s: &str) -> fmt::Result {
+    write!(f, "\"")?;
+    for ch in s.chars() {
+        match ch {
+            '"' => write!(f, "\\\"")?,
+            '\\' => write!(f, "\\\\")?,
+            '\n' => write!(f, "\\n")?,
+            '\r' => write!(f, "\\r")?,
+            '\t' => write!(f, "\\t")?,
+            '\u{8}' => write!(f, "\\b")?,
+            '\u{c}' => write!(f, "\\f")?,
+            ch if ch < ' ' => write!(f, "\\u{:04x}", ch as u32)?,
+            ch => write!(f, "{}", ch)?,
+        }
+    }
+    write!(f, "\"")
+}
+
+/// A number or string literal that matched the JSON grammar, but whose value is invalid.
+#[derive(Debug, Clone, PartialEq, Eq)]
+struct JsonError(String);
+
+impl fmt::Display for JsonError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "{}", self.0)
+    }
+}
+
+impl std::error::Error for JsonError {}
+
+/// Convert a JSON number literal, which must already match the JSON grammar, to an `f64`.
+/// Numbers too large to represent are errors.
+fn parse_number(literal: &str) -> Result<f64, JsonError> {
+    match literal.parse::<f64>() {
+        Ok(n) if n.is_finite() => Ok(n),
+        _ => Err(JsonError(format!("number out of range: {}", literal))),
+    }
+}
+
+/// Replace the escape sequences in the contents of a JSON string literal (without its quotes),
+/// which must already match the JSON grammar. Unpaired UTF-16 surrogates are errors.
+fn unescape_string(contents: &str) -> Result<String, JsonError> {
+    let mut result = String::with_capacity(contents.len());
+    let mut chars = contents.chars();
+    while let Some(ch) = chars.next() {
+        if ch != '\\' {
+            result.push(ch);
+            continue;
+        }
+        let escaped = match chars.next() {
+            Some('"') => '"',
+            Some('\\') => '\\',
+            Some('/') => '/',
+            Some('b') => '\u{8}',
+            Some('f') => '\u{c}',
+            Some('n') => '\n',
+            Some('r') => '\r',
+            Some('t') => '\t',
+            Some('u') => {
+                let unit = hex_escape(&mut chars)?;
+                let code_point = match unit {
+                    0xD800..=0xDBFF => {
+                        let low = match (chars.next(), chars.next()) {
+                            (Some('\\'), Some('u')) => hex_escape(&mut chars)?,
+                            _ => 0,
+                        };
+                        if !(0xDC00..=0xDFFF).contains(&low) {
+                            return Err(JsonError(format!("unpaired surrogate \\u{:04x}", unit)));
+                        }
+                        0x10000 + ((unit - 0xD800) << 10) + (low - 0xDC00)
+                    }
+                    0xDC00..=0xDFFF => {
+                        return Err(JsonError(format!("unpaired surrogate \\u{:04x}", unit)));
+                    }
+                    unit => unit,
+                };
+                char::from_u32(code_point).unwrap()
+            }
+            _ => return Err(JsonError("invalid escape sequence".to_owned())),
+        };
+        result.push(escaped);
+    }
+    Ok(result)
+}
+
+/// Read the four hex digits of a `\u` escape.
+fn hex_escape(chars: &mut std::str::Chars) -> Result<u32, JsonError> {
+    let digits = chars.as_str().get(..4).unwrap_or("");
+    match u32::from_str_radix(digits, 16) {
+        // (`from_str_radix` would allow a leading `+`.)
+        Ok(unit) if digits.chars().all(|c| c.is_ascii_hexdigit()) => {
+            chars.nth(3);
+            Ok(unit)
+        }
+        _ => Err(JsonError("invalid \\u escape".to_owned())),
     }
 }
 
 fn main() {
-    use ll1::LL1Parser;
-    use nomp::NomParser;
     use std::time::Instant;
 
+    let args = env::args().collect::<Vec<_>>();
+    match args.get(1).map(|arg| arg.as_str()) {
+        Some("bench") => return bench::run(&args[2..]),
+        Some("stream") => return stream_stdin(),
+        _ => (),
+    }
+
     // Construct parsers
     let now = Instant::now();
-    let parsers: Vec<Box<dyn JsonParser>> =
-        vec![Box::new(LL1Parser::new()), Box::new(NomParser::new())];
+    let parsers = all_parsers();
     println!(
         "Time to construct all parsers: {} μs",
         now.elapsed().as_micros()
@@ -94,10 +224,9 @@ fn main() {
         .collect::<Vec<_>>();
 
     // Find parser from command line args
-    let args = env::args().collect::<Vec<_>>();
     if args.len() != 2 {
         panic!(
-            "Must pass one argument saying which parser library to use: {}",
+            "Must pass one argument saying which parser library to use (or `bench` or `stream`): {}",
             parser_names.join(", ")
         );
     }
@@ -116,7 +245,13 @@ fn main() {
 
     // Parse json
     let now = Instant::now();
-    let json = parser.parse_json(&input);
+    let json = match parser.parse_json(&input) {
+        Ok(json) => json,
+        Err(err) => {
+            eprintln!("{}", err);
+            std::process::exit(1);
+        }
+    };
     println!("Time to parse json: {} μs", now.elapsed().as_micros());
 
     // Format json
@@ -140,3 +275,45 @@ fn main() {
         }
     }
 }
+
+/// Parse stdin as it's read, without keeping it in memory, and count its parts.
+fn stream_stdin() {
+    use std::time::Instant;
+
+    #[derive(Default)]
+    struct Counts {
+        objects: usize,
+        arrays: usize,
+        keys: usize,
+        values: usize,
+    }
+
+    impl JsonHandler for Counts {
+        fn on_begin_object(&mut self) {
+            self.objects += 1;
+        }
+        fn on_key(&mut self, _key: &str) {
+            self.keys += 1;
+        }
+        fn on_end_object(&mut self) {}
+        fn on_begin_array(&mut self) {
+            self.arrays += 1;
+        }
+        fn on_end_array(&mut self) {}
+        fn on_value(&mut self, _value: Json) {
+            self.values += 1;
+        }
+    }
+
+    let now = Instant::now();
+    let mut counts = Counts::default();
+    if let Err(err) = handwritten::parse_reader_events(io::stdin().lock(), &mut counts) {
+        eprintln!("{}", err);
+        std::process::exit(1);
+    }
+    println!("Time to stream json: {} μs", now.elapsed().as_micros());
+    println!(
+        "{} objects, {} arrays, {} keys, {} other values",
+        counts.objects, counts.arrays, counts.keys, counts.values
+    );
+}
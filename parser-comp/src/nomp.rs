@@ -1,16 +1,16 @@
 //! From https://github.com/rust-bakery/nom/blob/main/examples/json.rs
-//! with comments removed
+//! with comments removed, and extended to the full JSON grammar
 
-use crate::{Json, JsonParser};
+use crate::events::{emit, with_handler, JsonHandler};
+use crate::{parse_number, unescape_string, Json, JsonError, JsonParser};
 use nom::{
     branch::alt,
-    bytes::complete::{tag, take_until, take_while},
-    character::complete::char,
-    combinator::{cut, map, opt, value},
-    error::{context, convert_error, ContextError, ParseError, VerboseError},
-    multi::separated_list0,
-    number::complete::double,
-    sequence::{delimited, preceded, separated_pair, terminated},
+    bytes::complete::{tag, take_while, take_while1, take_while_m_n},
+    character::complete::{char, digit0, digit1, one_of, satisfy},
+    combinator::{all_consuming, cut, map, map_res, opt, recognize, value},
+    error::{context, convert_error, ContextError, FromExternalError, ParseError, VerboseError},
+    multi::{many0_count, separated_list0},
+    sequence::{pair, preceded, separated_pair, terminated, tuple},
     Err, IResult, Parser,
 };
 use std::str;
@@ -31,16 +31,57 @@ fn null<'a, E: ParseError<&'a str>>(input: &'a str) -> IResult<&'a str, (), E> {
     value((), tag("null")).parse(input)
 }
 
-fn string<'a, E: ParseError<&'a str> + ContextError<&'a str>>(
+fn number<'a, E: ParseError<&'a str> + FromExternalError<&'a str, JsonError>>(
     i: &'a str,
-) -> IResult<&'a str, &'a str, E> {
+) -> IResult<&'a str, f64, E> {
+    let integer = alt((
+        tag("0"),
+        recognize(pair(satisfy(|c| ('1'..='9').contains(&c)), digit0)),
+    ));
+    let fraction = pair(char('.'), digit1);
+    let exponent = tuple((one_of("eE"), opt(one_of("+-")), digit1));
+    map_res(
+        recognize(tuple((
+            opt(char('-')),
+            integer,
+            opt(fraction),
+            opt(exponent),
+        ))),
+        parse_number,
+    )(i)
+}
+
+fn string<
+    'a,
+    E: ParseError<&'a str> + ContextError<&'a str> + FromExternalError<&'a str, JsonError>,
+>(
+    i: &'a str,
+) -> IResult<&'a str, String, E> {
+    let unescaped = take_while1(|c: char| c != '"' && c != '\\' && c >= ' ');
+    let escape = preceded(
+        char('\\'),
+        alt((
+            recognize(one_of("\"\\/bfnrt")),
+            recognize(pair(
+                char('u'),
+                take_while_m_n(4, 4, |c: char| c.is_ascii_hexdigit()),
+            )),
+        )),
+    );
+    let contents = recognize(many0_count(alt((unescaped, escape))));
     context(
         "string",
-        preceded(char('\"'), cut(terminated(take_until("\""), char('\"')))),
+        preceded(
+            char('\"'),
+            cut(terminated(map_res(contents, unescape_string), char('\"'))),
+        ),
     )(i)
 }
 
-fn array<'a, E: ParseError<&'a str> + ContextError<&'a str>>(
+fn array<
+    'a,
+    E: ParseError<&'a str> + ContextError<&'a str> + FromExternalError<&'a str, JsonError>,
+>(
     i: &'a str,
 ) -> IResult<&'a str, Vec<Json>, E> {
     context(
@@ -55,9 +96,12 @@ fn array<'a, E: ParseError<&'a str> + ContextError<&'a str>>(
     )(i)
 }
 
-fn key_value<'a, E: ParseError<&'a str> + ContextError<&'a str>>(
+fn key_value<
+    'a,
+    E: ParseError<&'a str> + ContextError<&'a str> + FromExternalError<&'a str, JsonError>,
+>(
     i: &'a str,
-) -> IResult<&'a str, (&'a str, Json), E> {
+) -> IResult<&'a str, (String, Json), E> {
     separated_pair(
         preceded(sp, string),
         cut(preceded(sp, char(':'))),
@@ -66,7 +110,10 @@ fn key_value<'a, E: ParseError<&'a str> + ContextError<&'a str>>(
     .parse(i)
 }
 
-fn hash<'a, E: ParseError<&'a str> + ContextError<&'a str>>(
+fn hash<
+    'a,
+    E: ParseError<&'a str> + ContextError<&'a str> + FromExternalError<&'a str, JsonError>,
+>(
     i: &'a str,
 ) -> IResult<&'a str, Vec<(String, Json)>, E> {
     context(
@@ -74,22 +121,17 @@ fn hash<'a, E: ParseError<&'a str> + ContextError<&'a str>>(
         preceded(
             char('{'),
             cut(terminated(
-                map(
-                    separated_list0(preceded(sp, char(',')), key_value),
-                    |tuple_vec| {
-                        tuple_vec
-                            .into_iter()
-                            .map(|(k, v)| (String::from(k), v))
-                            .collect()
-                    },
-                ),
+                separated_list0(preceded(sp, char(',')), key_value),
                 preceded(sp, char('}')),
             )),
         ),
     )(i)
 }
 
-fn json_value<'a, E: ParseError<&'a str> + ContextError<&'a str>>(
+fn json_value<
+    'a,
+    E: ParseError<&'a str> + ContextError<&'a str> + FromExternalError<&'a str, JsonError>,
+>(
     i: &'a str,
 ) -> IResult<&'a str, Json, E> {
     preceded(
@@ -97,8 +139,8 @@ fn json_value<'a, E: ParseError<&'a str> + ContextError<&'a str>>(
         alt((
             map(hash, Json::Object),
             map(array, Json::Array),
-            map(string, |s| Json::String(String::from(s))),
-            map(double, Json::Number),
+            map(string, Json::String),
+            map(number, Json::Number),
             map(boolean, Json::Boolean),
             map(null, |_| Json::Null),
         )),
@@ -106,21 +148,123 @@ fn json_value<'a, E: ParseError<&'a str> + ContextError<&'a str>>(
     .parse(i)
 }
 
-fn root<'a, E: ParseError<&'a str> + ContextError<&'a str>>(
+fn root<
+    'a,
+    E: ParseError<&'a str> + ContextError<&'a str> + FromExternalError<&'a str, JsonError>,
+>(
     i: &'a str,
 ) -> IResult<&'a str, Json, E> {
-    delimited(
+    all_consuming(terminated(json_value, sp)).parse(i)
+}
+
+// The same grammar, sending events instead of building a `Json`
+
+fn array_events<
+    'a,
+    E: ParseError<&'a str> + ContextError<&'a str> + FromExternalError<&'a str, JsonError>,
+>(
+    i: &'a str,
+) -> IResult<&'a str, (), E> {
+    context(
+        "array",
+        preceded(
+            map(char('['), |_| emit(|handler| handler.on_begin_array())),
+            cut(terminated(
+                map(
+                    separated_list0(preceded(sp, char(',')), value_events),
+                    |_| (),
+                ),
+                map(preceded(sp, char(']')), |_| {
+                    emit(|handler| handler.on_end_array())
+                }),
+            )),
+        ),
+    )(i)
+}
+
+fn key_value_events<
+    'a,
+    E: ParseError<&'a str> + ContextError<&'a str> + FromExternalError<&'a str, JsonError>,
+>(
+    i: &'a str,
+) -> IResult<&'a str, (), E> {
+    map(
+        separated_pair(
+            map(preceded(sp, string), |key| {
+                emit(|handler| handler.on_key(&key))
+            }),
+            cut(preceded(sp, char(':'))),
+            value_events,
+        ),
+        |_| (),
+    )
+    .parse(i)
+}
+
+fn hash_events<
+    'a,
+    E: ParseError<&'a str> + ContextError<&'a str> + FromExternalError<&'a str, JsonError>,
+>(
+    i: &'a str,
+) -> IResult<&'a str, (), E> {
+    context(
+        "map",
+        preceded(
+            map(char('{'), |_| emit(|handler| handler.on_begin_object())),
+            cut(terminated(
+                map(
+                    separated_list0(preceded(sp, char(',')), key_value_events),
+                    |_| (),
+                ),
+                map(preceded(sp, char('}')), |_| {
+                    emit(|handler| handler.on_end_object())
+                }),
+            )),
+        ),
+    )(i)
+}
+
+fn value_events<
+    'a,
+    E: ParseError<&'a str> + ContextError<&'a str> + FromExternalError<&'a str, JsonError>,
+>(
+    i: &'a str,
+) -> IResult<&'a str, (), E> {
+    let value = |json: Json| emit(|handler| handler.on_value(json));
+    preceded(
         sp,
         alt((
-            map(hash, Json::Object),
-            map(array, Json::Array),
-            map(null, |_| Json::Null),
+            hash_events,
+            array_events,
+            map(string, move |s| value(Json::String(s))),
+            map(number, move |n| value(Json::Number(n))),
+            map(boolean, move |b| value(Json::Boolean(b))),
+            map(null, move |_| value(Json::Null)),
         )),
-        opt(sp),
     )
     .parse(i)
 }
 
+fn root_events<
+    'a,
+    E: ParseError<&'a str> + ContextError<&'a str> + FromExternalError<&'a str, JsonError>,
+>(
+    i: &'a str,
+) -> IResult<&'a str, (), E> {
+    all_consuming(terminated(value_events, sp)).parse(i)
+}
+
+fn show_result<T>(input: &str, result: IResult<&str, T, VerboseError<&str>>) -> Result<T, String> {
+    match result {
+        Err(Err::Error(e)) | Err(Err::Failure(e)) => Err(format!(
+            "verbose errors - `root::<VerboseError>(data)`:\n{}",
+            convert_error(input, e)
+        )),
+        Err(Err::Incomplete(_)) => unreachable!("complete parsers never return Incomplete"),
+        Ok((_, result)) => Ok(result),
+    }
+}
+
 pub struct NomParser;
 
 impl JsonParser for NomParser {
@@ -132,17 +276,13 @@ impl JsonParser for NomParser {
         "nom"
     }
 
-    fn parse_json(&self, input: &str) -> Json {
-        match root::<VerboseError<&str>>(input) {
-            Err(Err::Error(e)) | Err(Err::Failure(e)) => {
-                panic!(
-                    "verbose errors - `root::<VerboseError>(data)`:\n{}",
-                    convert_error(input, e)
-                );
-            }
-            Err(Err::Incomplete(_)) => todo!(),
-            Ok((_, json)) => json,
-        }
+    fn parse_json(&self, input: &str) -> Result<Json, String> {
+        show_result(input, root::<VerboseError<&str>>(input))
+    }
+
+    fn parse_events(&self, input: &str, handler: &mut dyn JsonHandler) -> Result<(), String> {
+        let result = with_handler(handler, || root_events::<VerboseError<&str>>(input));
+        show_result(input, result)
     }
 }
 
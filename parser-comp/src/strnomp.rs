@@ -0,0 +1,130 @@
+//! Based on strnom's `examples/json.rs`, extended to the full JSON grammar.
+
+use crate::events::{emit, with_handler, JsonHandler};
+use crate::{parse_number, unescape_string, Json, JsonParser};
+use strnom::{alt, parse_with_skip, regex, string, Cursor, ParseError, Parser};
+
+thread_local! {
+    // Built once, because compiling the regexes is much slower than parsing.
+    static VALUE: Box<dyn Parser<Json>> = Box::new(value_parser());
+    static VALUE_EVENTS: Box<dyn Parser<()>> = Box::new(value_events_parser());
+    static WHITESPACE: Box<dyn Parser<()>> = Box::new(regex("whitespace", "[ \t\r\n]+").unwrap());
+}
+
+/// Parsers are recursive through this function.
+fn value(cursor: &mut Cursor, required: bool) -> Result<Json, Option<ParseError>> {
+    VALUE.with(|parser| parser.parse(cursor, required))
+}
+
+fn value_events(cursor: &mut Cursor, required: bool) -> Result<(), Option<ParseError>> {
+    VALUE_EVENTS.with(|parser| parser.parse(cursor, required))
+}
+
+fn whitespace(cursor: &mut Cursor, required: bool) -> Result<(), Option<ParseError>> {
+    WHITESPACE.with(|parser| parser.parse(cursor, required))
+}
+
+/// Strings, which can't contain control characters.
+fn string_literal() -> impl Parser<String> {
+    regex(
+        "string",
+        r#""([^"\\\x00-\x1F]|\\["\\/bfnrt]|\\u[0-9a-fA-F]{4})*""#,
+    )
+    .unwrap()
+    .substr(|s| unescape_string(&s[1..s.len() - 1]))
+    .resolve()
+    .token()
+}
+
+/// Null, booleans, numbers, and strings.
+fn scalar() -> impl Parser<Json> {
+    let null = string("null").token().constant(Json::Null);
+    let boolean = alt(
+        "boolean",
+        (
+            string("true").token().constant(true),
+            string("false").token().constant(false),
+        ),
+    )
+    .map(Json::Boolean);
+    let number = regex("number", r"-?(0|[1-9][0-9]*)(\.[0-9]+)?([eE][+-]?[0-9]+)?")
+        .unwrap()
+        .substr(parse_number)
+        .resolve()
+        .token()
+        .map(Json::Number);
+    alt(
+        "scalar",
+        (null, boolean, number, string_literal().map(Json::String)),
+    )
+}
+
+fn value_parser() -> impl Parser<Json> {
+    let array = string("[")
+        .token()
+        .cut((value.many_sep0(string(",").token()), string("]").token()))
+        .map(|(_, (elems, _))| Json::Array(elems));
+    let member = string_literal()
+        .cut((string(":").token(), value))
+        .map(|(key, (_, val))| (key, val));
+    let object = string("{")
+        .token()
+        .cut((member.many_sep0(string(",").token()), string("}").token()))
+        .map(|(_, (members, _))| Json::Object(members));
+    alt("value", (scalar(), array, object))
+}
+
+/// The same grammar, sending events instead of building a `Json`.
+fn value_events_parser() -> impl Parser<()> {
+    let scalar = scalar().map(|json| emit(|handler| handler.on_value(json)));
+    let array = string("[")
+        .token()
+        .map(|()| emit(|handler| handler.on_begin_array()))
+        .cut((
+            value_events.many_sep0(string(",").token()),
+            string("]")
+                .token()
+                .map(|()| emit(|handler| handler.on_end_array())),
+        ))
+        .constant(());
+    let member = string_literal()
+        .map(|key| emit(|handler| handler.on_key(&key)))
+        .cut((string(":").token(), value_events));
+    let object = string("{")
+        .token()
+        .map(|()| emit(|handler| handler.on_begin_object()))
+        .cut((
+            member.many_sep0(string(",").token()),
+            string("}")
+                .token()
+                .map(|()| emit(|handler| handler.on_end_object())),
+        ))
+        .constant(());
+    alt("value", (scalar, array, object))
+}
+
+pub struct StrnomParser;
+
+impl JsonParser for StrnomParser {
+    fn new() -> StrnomParser {
+        VALUE.with(|_| ());
+        VALUE_EVENTS.with(|_| ());
+        WHITESPACE.with(|_| ());
+        StrnomParser
+    }
+
+    fn name(&self) -> &'static str {
+        "strnom"
+    }
+
+    fn parse_json(&self, input: &str) -> Result<Json, String> {
+        parse_with_skip("stdin", input, whitespace, value).map_err(|err| err.to_string())
+    }
+
+    fn parse_events(&self, input: &str, handler: &mut dyn JsonHandler) -> Result<(), String> {
+        with_handler(handler, || {
+            parse_with_skip("stdin", input, whitespace, value_events)
+        })
+        .map_err(|err| err.to_string())
+    }
+}
@@ -1,9 +1,12 @@
-use strnom::{alt, parse, regex, string, Parser};
+use std::fmt;
+use std::io::{self, Read};
+use strnom::{alt, parse_with_skip, regex, string, Cursor, ParseError, Parser};
 
-// A simple JSON parser. Does not handle things like string escapes or numbers with
-// exponents in them.
+// A JSON parser, for the full JSON grammar.
 
 // cat examples/sample.json | cargo run --release --example json
+//
+// Or show the grammar, with `-- --ebnf` or `-- --svg > json.svg`.
 
 #[derive(Debug, Clone)]
 pub enum Json {
@@ -15,6 +18,143 @@ pub enum Json {
     Object(Vec<(String, Json)>),
 }
 
+thread_local! {
+    // Built once, because compiling the regexes is much slower than parsing.
+    static VALUE: Box<dyn Parser<Json>> = Box::new(value_parser());
+}
+
+/// Parsers are recursive through this function.
+fn value(cursor: &mut Cursor, required: bool) -> Result<Json, Option<ParseError>> {
+    VALUE.with(|parser| parser.parse(cursor, required))
+}
+
+#[derive(Debug)]
+struct JsonError(String);
+
+impl fmt::Display for JsonError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "{}", self.0)
+    }
+}
+
+impl std::error::Error for JsonError {}
+
+/// Replace the escape sequences in the contents of a string literal, which already match the
+/// grammar. Escaped UTF-16 surrogates must come in pairs.
+fn unescape(contents: &str) -> Result<String, JsonError> {
+    let mut result = String::with_capacity(contents.len());
+    let mut chars = contents.chars();
+    let hex = |chars: &mut std::str::Chars| {
+        let digits = chars.as_str().get(..4).unwrap_or("");
+        chars.nth(3);
+        u32::from_str_radix(digits, 16).unwrap_or(0)
+    };
+    while let Some(ch) = chars.next() {
+        if ch != '\\' {
+            result.push(ch);
+            continue;
+        }
+        result.push(match chars.next() {
+            Some('b') => '\u{8}',
+            Some('f') => '\u{c}',
+            Some('n') => '\n',
+            Some('r') => '\r',
+            Some('t') => '\t',
+            Some('u') => {
+                let mut code_point = hex(&mut chars);
+                if (0xD800..=0xDBFF).contains(&code_point) && chars.as_str().starts_with("\\u") {
+                    let mut rest = chars.clone();
+                    rest.nth(1);
+                    let low = hex(&mut rest);
+                    if (0xDC00..=0xDFFF).contains(&low) {
+                        code_point = 0x10000 + ((code_point - 0xD800) << 10) + (low - 0xDC00);
+                        chars = rest;
+                    }
+                }
+                char::from_u32(code_point)
+                    .ok_or_else(|| JsonError(format!("invalid \\u escape: {:x}", code_point)))?
+            }
+            // `"`, `\`, and `/` stand for themselves.
+            Some(ch) => ch,
+            None => unreachable!(),
+        });
+    }
+    Ok(result)
+}
+
+/// Strings, which can't contain control characters.
+fn string_literal() -> impl Parser<String> {
+    regex(
+        "string",
+        r#""([^"\\\x00-\x1F]|\\["\\/bfnrt]|\\u[0-9a-fA-F]{4})*""#,
+    )
+    .unwrap()
+    .substr(|s| unescape(&s[1..s.len() - 1]))
+    .resolve()
+    .token()
+}
+
+fn value_parser() -> impl Parser<Json> {
+    let null = string("null").token().constant(Json::Null);
+    let boolean = alt(
+        "boolean",
+        (
+            string("true").token().constant(true),
+            string("false").token().constant(false),
+        ),
+    )
+    .map(Json::Bool);
+    let number = regex("number", r"-?(0|[1-9][0-9]*)(\.[0-9]+)?([eE][+-]?[0-9]+)?")
+        .unwrap()
+        .substr(|s| s.parse::<f64>().unwrap())
+        .token()
+        .map(Json::Number);
+    let array = string("[")
+        .token()
+        .cut((
+            value.rule("value").many_sep0(string(",").token()),
+            string("]").token(),
+        ))
+        .map(|(_, (elems, _))| Json::Array(elems));
+    let member = string_literal()
+        .cut((string(":").token(), value.rule("value")))
+        .map(|(key, (_, val))| (key, val))
+        .rule("member");
+    let object = string("{")
+        .token()
+        .cut((member.many_sep0(string(",").token()), string("}").token()))
+        .map(|(_, (members, _))| Json::Object(members));
+    alt(
+        "value",
+        (
+            null,
+            boolean,
+            number,
+            string_literal().map(Json::String),
+            array,
+            object,
+        ),
+    )
+    .rule("value")
+}
+
 fn main() {
-    println!("ok")
+    match std::env::args().nth(1).as_deref() {
+        Some("--ebnf") => print!("{}", value_parser().grammar().to_ebnf()),
+        Some("--svg") => print!("{}", value_parser().grammar().to_svg()),
+        _ => parse_stdin(),
+    }
+}
+
+fn parse_stdin() {
+    let mut source = String::new();
+    io::stdin().read_to_string(&mut source).unwrap();
+    let whitespace = regex("whitespace", r"[ \t\r\n]+").unwrap();
+    match parse_with_skip("<stdin>", &source, whitespace, value) {
+        Ok(json) => println!("{:?}", json),
+        Err(err) => {
+            eprintln!("{}", err);
+            std::process::exit(1);
+        }
+    }
 }
@@ -0,0 +1,83 @@
+use std::time::Instant;
+use strnom::{alt, parse, regex, string, Cursor, ParseError, Parser};
+
+// Times backtracking, memoized, and left-recursive parsing as the input grows:
+//
+// cargo run --release --bin memo_speed_test
+
+/// `nested = "a" nested "b" | "a" nested "c" | "d"`. On "a^n d c^n", the first option parses
+/// all of the rest of the input before failing, so without memoization this takes 2^n time.
+fn nested_parser(
+    nested: fn(&mut Cursor, bool) -> Result<usize, Option<ParseError>>,
+) -> impl Parser<usize> {
+    alt(
+        "nested",
+        (
+            (string("a"), nested, string("b")).map(|(_, n, _)| n + 1),
+            (string("a"), nested, string("c")).map(|(_, n, _)| n + 1),
+            string("d").constant(0),
+        ),
+    )
+}
+
+thread_local! {
+    static NESTED: Box<dyn Parser<usize>> = Box::new(nested_parser(nested));
+    static NESTED_MEMO: Box<dyn Parser<usize>> = Box::new(nested_parser(nested_memo).memo());
+    static SUB: Box<dyn Parser<i64>> = Box::new(
+        alt(
+            "expression",
+            ((sub, string("-"), number()).map(|(x, _, y)| x - y), number()),
+        )
+        .memo(),
+    );
+}
+
+fn nested(cursor: &mut Cursor, required: bool) -> Result<usize, Option<ParseError>> {
+    NESTED.with(|parser| parser.parse(cursor, required))
+}
+
+fn nested_memo(cursor: &mut Cursor, required: bool) -> Result<usize, Option<ParseError>> {
+    NESTED_MEMO.with(|parser| parser.parse(cursor, required))
+}
+
+fn number() -> impl Parser<i64> {
+    regex("number", "[0-9]+")
+        .unwrap()
+        .substr(|s| s.parse().unwrap())
+}
+
+/// `sub = sub "-" number | number`
+fn sub(cursor: &mut Cursor, required: bool) -> Result<i64, Option<ParseError>> {
+    SUB.with(|parser| parser.parse(cursor, required))
+}
+
+fn time_nested(label: &str, n: usize, parser: impl Parser<usize>) {
+    let source = "a".repeat(n) + "d" + &"c".repeat(n);
+    let now = Instant::now();
+    assert_eq!(parse("<bench>", &source, parser), Ok(n));
+    let elapsed = now.elapsed().as_millis();
+    println!("{:<20} {}ms", format!("{}, n={}:", label, n), elapsed);
+}
+
+fn main() {
+    // Parsing "a^n d c^n" recurses n deep.
+    let thread = std::thread::Builder::new().stack_size(1 << 30);
+    let handle = thread
+        .spawn(|| {
+            for n in [16, 18, 20, 22] {
+                time_nested("Backtracking", n, nested);
+            }
+            for n in [2000, 4000, 8000, 16000] {
+                time_nested("Memoized", n, nested_memo);
+            }
+            for n in [25000, 50000, 100000, 200000] {
+                let source = "1".to_owned() + &"-1".repeat(n - 1);
+                let now = Instant::now();
+                assert_eq!(parse("<bench>", &source, sub), Ok(2 - n as i64));
+                let elapsed = now.elapsed().as_millis();
+                println!("{:<26} {}ms", format!("Left recursive, n={}:", n), elapsed);
+            }
+        })
+        .unwrap();
+    handle.join().unwrap();
+}
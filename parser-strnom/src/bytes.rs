@@ -0,0 +1,97 @@
+//! Parsers for binary input, of type [`Bytes`]. Use them with [`parse`](crate::parse) on a
+//! `&[u8]`, and the usual combinators:
+//!
+//! ```
+//! use strnom::bytes::{byte_if, tag};
+//! use strnom::{parse, Parser};
+//!
+//! let digit = byte_if("digit", |b| b.is_ascii_digit()).map(|b| b - b'0');
+//! let version = tag(b"\x7fVER").cut(digit);
+//! assert_eq!(parse("<example>", b"\x7fVER3", version.refn()), Ok(((), 3)));
+//! assert!(parse("<example>", b"\x7fVERx", version.refn()).is_err());
+//! ```
+
+use crate::{described, Bytes, Cursor, Grammar, Parser};
+use regex::bytes::Regex;
+use regex::Error as RegexError;
+
+/// Match exactly the bytes `expected`.
+pub fn tag(expected: &[u8]) -> impl Parser<(), Bytes> {
+    let text = expected.escape_ascii().to_string();
+    let label = format!("`{}`", text);
+    let expected = expected.to_owned();
+    let grammar = Grammar::Literal(text);
+    described(
+        move || grammar.clone(),
+        move |cursor: &mut Cursor<Bytes>, required: bool| {
+            if cursor.bytes().starts_with(&expected) {
+                cursor.advance(expected.len());
+                return Ok(());
+            }
+            cursor.expected(&label);
+            Err(required.then(|| cursor.expected_error()))
+        },
+    )
+}
+
+/// Match any `len` bytes. Use [`Parser::substr`] to get them.
+pub fn take(len: usize) -> impl Parser<(), Bytes> {
+    let label = format!("{} bytes", len);
+    let grammar = Grammar::Label(label.clone());
+    described(
+        move || grammar.clone(),
+        move |cursor: &mut Cursor<Bytes>, required: bool| {
+            if cursor.bytes().len() >= len {
+                cursor.advance(len);
+                return Ok(());
+            }
+            cursor.expected(&label);
+            Err(required.then(|| cursor.expected_error()))
+        },
+    )
+}
+
+/// Match a single byte for which `pred` is true, and return it.
+pub fn byte_if(label: &str, pred: impl Fn(u8) -> bool) -> impl Parser<u8, Bytes> {
+    let label = label.to_owned();
+    let grammar = Grammar::Label(label.clone());
+    described(
+        move || grammar.clone(),
+        move |cursor: &mut Cursor<Bytes>, required: bool| match cursor.bytes().first() {
+            Some(byte) if pred(*byte) => {
+                cursor.advance(1);
+                Ok(*byte)
+            }
+            _ => {
+                cursor.expected(&label);
+                Err(required.then(|| cursor.expected_error()))
+            }
+        },
+    )
+}
+
+/// Match a [byte regex](regex::bytes::Regex), which may match arbitrary bytes with `(?-u)`.
+pub fn regex(label: &str, regex_str: &str) -> Result<impl Parser<(), Bytes>, RegexError> {
+    let regex = new_regex(regex_str)?;
+    let label = label.to_owned();
+    let grammar = Grammar::Label(label.clone());
+    Ok(described(
+        move || grammar.clone(),
+        move |cursor: &mut Cursor<Bytes>, required: bool| {
+            if let Some(re_match) = regex.find(cursor.bytes()) {
+                cursor.advance(re_match.len());
+                return Ok(());
+            }
+            cursor.expected(&label);
+            Err(required.then(|| cursor.expected_error()))
+        },
+    ))
+}
+
+fn new_regex(regex_str: &str) -> Result<Regex, RegexError> {
+    match Regex::new(&format!("^({})", regex_str)) {
+        Ok(regex) => Ok(regex),
+        // This error message is better because it doesn't have the ^({}) wrapper in it.
+        Err(err) => Err(Regex::new(regex_str).err().unwrap_or(err)),
+    }
+}
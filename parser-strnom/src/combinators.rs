@@ -0,0 +1,727 @@
+//! The parsers returned by the methods of [`Parser`]. Each keeps the parsers it's made from, so
+//! that its grammar is only built (from theirs) when asked for.
+
+use crate::cursor::MemoEntry;
+use crate::pos::Offset;
+use crate::{AltTuple, Cursor, Grammar, Input, IsResult, ParseError, Parser, Span};
+use std::marker::PhantomData;
+
+/// The output type of a parser that a combinator doesn't otherwise mention, so that its
+/// `Parser` impl can name it.
+type Output<T> = PhantomData<fn() -> T>;
+
+macro_rules! repeat {
+    ($var:ident = $parse:expr => $on_success:block) => {
+        loop {
+            match $parse {
+                Ok($var) => $on_success,
+                Err(None) => break,
+                Err(Some(err)) => return Err(Some(err)),
+            }
+        }
+    };
+}
+
+/*=========*
+ * Mapping *
+ *=========*/
+
+pub(crate) struct Rule<P> {
+    pub(crate) parser: P,
+    pub(crate) name: String,
+}
+
+impl<T, I: Input, P: Parser<T, I>> Parser<T, I> for Rule<P> {
+    fn parse(&self, cursor: &mut Cursor<I>, required: bool) -> Result<T, Option<ParseError>> {
+        self.parser.parse(cursor, required)
+    }
+
+    fn grammar(&self) -> Grammar {
+        Grammar::Rule {
+            name: self.name.clone(),
+            definition: Box::new(self.parser.grammar()),
+        }
+    }
+}
+
+pub(crate) struct Constant<P, T, T2> {
+    pub(crate) parser: P,
+    pub(crate) val: T2,
+    pub(crate) output: Output<T>,
+}
+
+impl<T, T2: Clone, I: Input, P: Parser<T, I>> Parser<T2, I> for Constant<P, T, T2> {
+    fn parse(&self, cursor: &mut Cursor<I>, required: bool) -> Result<T2, Option<ParseError>> {
+        match self.parser.parse(cursor, required) {
+            Ok(_) => Ok(self.val.clone()),
+            Err(err) => Err(err),
+        }
+    }
+
+    fn grammar(&self) -> Grammar {
+        self.parser.grammar()
+    }
+}
+
+pub(crate) struct MakeConstant<P, T, F> {
+    pub(crate) parser: P,
+    pub(crate) make_val: F,
+    pub(crate) output: Output<T>,
+}
+
+impl<T, T2, I: Input, P, F> Parser<T2, I> for MakeConstant<P, T, F>
+where
+    P: Parser<T, I>,
+    F: Fn() -> T2,
+{
+    fn parse(&self, cursor: &mut Cursor<I>, required: bool) -> Result<T2, Option<ParseError>> {
+        match self.parser.parse(cursor, required) {
+            Ok(_) => Ok((self.make_val)()),
+            Err(err) => Err(err),
+        }
+    }
+
+    fn grammar(&self) -> Grammar {
+        self.parser.grammar()
+    }
+}
+
+pub(crate) struct Fail<P> {
+    pub(crate) parser: P,
+    pub(crate) message: String,
+}
+
+impl<T, I: Input, P: Parser<T, I>> Parser<T, I> for Fail<P> {
+    fn parse(&self, cursor: &mut Cursor<I>, required: bool) -> Result<T, Option<ParseError>> {
+        let start = cursor.pos;
+        self.parser.parse(cursor, required)?;
+        Err(Some(cursor.error_from(self.message.clone(), start)))
+    }
+
+    fn grammar(&self) -> Grammar {
+        self.parser.grammar()
+    }
+}
+
+pub(crate) struct Cut<P, Q> {
+    pub(crate) first: P,
+    pub(crate) second: Q,
+}
+
+impl<T, T2, I: Input, P, Q> Parser<(T, T2), I> for Cut<P, Q>
+where
+    P: Parser<T, I>,
+    Q: Parser<T2, I>,
+{
+    fn parse(&self, cursor: &mut Cursor<I>, required: bool) -> Result<(T, T2), Option<ParseError>> {
+        let res_1 = self.first.parse(cursor, required)?;
+        let res_2 = self.second.parse(cursor, true)?;
+        Ok((res_1, res_2))
+    }
+
+    fn grammar(&self) -> Grammar {
+        Grammar::seq([self.first.grammar(), self.second.grammar()])
+    }
+}
+
+pub(crate) struct MapErr<P, F> {
+    pub(crate) parser: P,
+    pub(crate) func: F,
+}
+
+impl<T, I: Input, P, F> Parser<T, I> for MapErr<P, F>
+where
+    P: Parser<T, I>,
+    F: Fn(String) -> String,
+{
+    fn parse(&self, cursor: &mut Cursor<I>, required: bool) -> Result<T, Option<ParseError>> {
+        self.parser
+            .parse(cursor, required)
+            .map_err(|opt| opt.map(|err| err.map(&self.func)))
+    }
+
+    fn grammar(&self) -> Grammar {
+        self.parser.grammar()
+    }
+}
+
+pub(crate) struct Map<P, T, F> {
+    pub(crate) parser: P,
+    pub(crate) func: F,
+    pub(crate) output: Output<T>,
+}
+
+impl<T, T2, I: Input, P, F> Parser<T2, I> for Map<P, T, F>
+where
+    P: Parser<T, I>,
+    F: Fn(T) -> T2,
+{
+    fn parse(&self, cursor: &mut Cursor<I>, required: bool) -> Result<T2, Option<ParseError>> {
+        self.parser.parse(cursor, required).map(&self.func)
+    }
+
+    fn grammar(&self) -> Grammar {
+        self.parser.grammar()
+    }
+}
+
+pub(crate) struct Resolve<P, T, E> {
+    pub(crate) parser: P,
+    pub(crate) output: Output<(T, E)>,
+}
+
+impl<T, T2, E, I: Input, P> Parser<T2, I> for Resolve<P, T, E>
+where
+    P: Parser<T, I>,
+    T: IsResult<T2, E>,
+    E: std::error::Error,
+{
+    fn parse(&self, cursor: &mut Cursor<I>, required: bool) -> Result<T2, Option<ParseError>> {
+        let start = cursor.pos;
+        let result = self.parser.parse(cursor, required)?;
+        // The parser matched, so report the error rather than let an `alt` try something else.
+        result
+            .into_result()
+            .map_err(|err| Some(cursor.error_from(err.to_string(), start)))
+    }
+
+    fn grammar(&self) -> Grammar {
+        self.parser.grammar()
+    }
+}
+
+pub(crate) struct Substr<P, T, F> {
+    pub(crate) parser: P,
+    pub(crate) func: F,
+    pub(crate) output: Output<T>,
+}
+
+impl<T, T2, I: Input, P, F> Parser<T2, I> for Substr<P, T, F>
+where
+    P: Parser<T, I>,
+    F: Fn(&I::Source<'_>) -> T2,
+{
+    fn parse(&self, cursor: &mut Cursor<I>, required: bool) -> Result<T2, Option<ParseError>> {
+        let start = cursor.pos;
+        self.parser.parse(cursor, required)?;
+        Ok((self.func)(cursor.substr_from(start)))
+    }
+
+    fn grammar(&self) -> Grammar {
+        self.parser.grammar()
+    }
+}
+
+pub(crate) struct SpanOf<P, T, F> {
+    pub(crate) parser: P,
+    pub(crate) func: F,
+    pub(crate) output: Output<T>,
+}
+
+impl<T, T2, I: Input, P, F> Parser<T2, I> for SpanOf<P, T, F>
+where
+    P: Parser<T, I>,
+    F: Fn(Span<I>) -> T2,
+{
+    fn parse(&self, cursor: &mut Cursor<I>, required: bool) -> Result<T2, Option<ParseError>> {
+        let start = cursor.pos;
+        self.parser.parse(cursor, required)?;
+        Ok((self.func)(cursor.span_from(start)))
+    }
+
+    fn grammar(&self) -> Grammar {
+        self.parser.grammar()
+    }
+}
+
+pub(crate) struct MapSpan<P, T, F> {
+    pub(crate) parser: P,
+    pub(crate) func: F,
+    pub(crate) output: Output<T>,
+}
+
+impl<T, T2, I: Input, P, F> Parser<T2, I> for MapSpan<P, T, F>
+where
+    P: Parser<T, I>,
+    F: Fn(T, Span<I>) -> T2,
+{
+    fn parse(&self, cursor: &mut Cursor<I>, required: bool) -> Result<T2, Option<ParseError>> {
+        let start = cursor.pos;
+        let val = self.parser.parse(cursor, required)?;
+        Ok((self.func)(val, cursor.span_from(start)))
+    }
+
+    fn grammar(&self) -> Grammar {
+        self.parser.grammar()
+    }
+}
+
+/*============*
+ * Whitespace *
+ *============*/
+
+pub(crate) struct Token<P> {
+    pub(crate) parser: P,
+}
+
+impl<T, I: Input, P: Parser<T, I>> Parser<T, I> for Token<P> {
+    fn parse(&self, cursor: &mut Cursor<I>, required: bool) -> Result<T, Option<ParseError>> {
+        let start = cursor.pos;
+        cursor.skip()?;
+        let result = self.parser.parse(cursor, required);
+        // Don't consume the skipped text if there was no token after it, so that it isn't
+        // included in the span of whatever came before.
+        if let Err(None) = result {
+            cursor.pos = start;
+        }
+        result
+    }
+
+    fn grammar(&self) -> Grammar {
+        self.parser.grammar()
+    }
+}
+
+/*================*
+ * Error Recovery *
+ *================*/
+
+pub(crate) struct RecoverUntil<P, S, T2> {
+    pub(crate) parser: P,
+    pub(crate) sync: S,
+    pub(crate) sync_output: Output<T2>,
+}
+
+impl<T, T2, I: Input, P, S> Parser<Option<T>, I> for RecoverUntil<P, S, T2>
+where
+    P: Parser<T, I>,
+    S: Parser<T2, I>,
+{
+    fn parse(
+        &self,
+        cursor: &mut Cursor<I>,
+        required: bool,
+    ) -> Result<Option<T>, Option<ParseError>> {
+        let start = cursor.pos;
+        let num_errors = cursor.errors.len();
+        let (err, is_hard_error) = match self.parser.parse(cursor, required) {
+            Ok(succ) => return Ok(Some(succ)),
+            Err(Some(err)) => (err, true),
+            Err(None) if cursor.no_failure_since(start) => {
+                cursor.pos = start;
+                (cursor.error("unexpected input".to_owned()), false)
+            }
+            Err(None) => (cursor.expected_error(), false),
+        };
+
+        cursor.pos = start.max(err.start());
+        while !cursor.is_at_end() {
+            let here = cursor.pos;
+            let synced = self.sync.parse(cursor, false).is_ok();
+            cursor.pos = here;
+            if synced {
+                break;
+            }
+            cursor.skip_char();
+        }
+
+        if cursor.pos == start && !required {
+            // Recovering wouldn't make progress, so any surrounding repetition would loop.
+            cursor.pos = start;
+            cursor.errors.truncate(num_errors);
+            return if is_hard_error {
+                Err(Some(err))
+            } else {
+                Err(None)
+            };
+        }
+        cursor.errors.push(err);
+        Ok(None)
+    }
+
+    fn grammar(&self) -> Grammar {
+        self.parser.grammar()
+    }
+}
+
+pub(crate) struct RecoverWith<P, T> {
+    pub(crate) parser: P,
+    pub(crate) default: T,
+}
+
+impl<T: Clone, I: Input, P: Parser<T, I>> Parser<T, I> for RecoverWith<P, T> {
+    fn parse(&self, cursor: &mut Cursor<I>, required: bool) -> Result<T, Option<ParseError>> {
+        let start = cursor.pos;
+        match self.parser.parse(cursor, required) {
+            Ok(succ) => Ok(succ),
+            Err(None) => Err(None),
+            Err(Some(err)) => {
+                cursor.pos = start.max(err.start());
+                cursor.errors.push(err);
+                Ok(self.default.clone())
+            }
+        }
+    }
+
+    fn grammar(&self) -> Grammar {
+        self.parser.grammar()
+    }
+}
+
+/*=============*
+ * Memoization *
+ *=============*/
+
+pub(crate) struct Memo<P> {
+    pub(crate) parser: P,
+    /// Distinguishes this parser's entries in the cursor's memo table.
+    pub(crate) id: usize,
+}
+
+fn store<T: 'static, I: Input>(
+    cursor: &mut Cursor<I>,
+    key: (usize, Offset),
+    result: Result<T, Option<ParseError>>,
+    required: bool,
+    in_progress: bool,
+    num_errors: usize,
+) {
+    let entry = MemoEntry {
+        result: Box::new(result),
+        end: cursor.pos,
+        errors: cursor.errors[num_errors..].to_vec(),
+        required,
+        in_progress,
+        left_recursive: false,
+    };
+    cursor.memo.insert(key, entry);
+}
+
+impl<T: Clone + 'static, I: Input, P: Parser<T, I>> Parser<T, I> for Memo<P> {
+    fn parse(&self, cursor: &mut Cursor<I>, required: bool) -> Result<T, Option<ParseError>> {
+        let start = cursor.pos;
+        let key = (self.id, start.offset);
+        if let Some(entry) = cursor.memo.get_mut(&key) {
+            let result = entry.result.downcast_ref::<Result<T, Option<ParseError>>>();
+            let result = result.unwrap().clone();
+            // Whether a failure would mean the same thing as it did for this call
+            let reusable = match &result {
+                Ok(_) => true,
+                Err(None) => !required,
+                Err(Some(_)) => required || !entry.required,
+            };
+            if entry.in_progress {
+                entry.left_recursive = true;
+            }
+            if entry.in_progress || reusable {
+                cursor.pos = entry.end;
+                let errors = entry.errors.clone();
+                cursor.errors.extend(errors);
+                return match result {
+                    Err(None) if required => {
+                        Err(Some(cursor.error("unexpected left recursion".to_owned())))
+                    }
+                    result => result,
+                };
+            }
+        }
+
+        let num_errors = cursor.errors.len();
+        store::<T, I>(cursor, key, Err(None), required, true, num_errors);
+        let result = self.parser.parse(cursor, required);
+        if !cursor.memo[&key].left_recursive {
+            store(cursor, key, result.clone(), required, false, num_errors);
+            return result;
+        }
+
+        // Grow the seed
+        let mut best = (result, cursor.pos);
+        while best.0.is_ok() {
+            store(cursor, key, best.0.clone(), required, true, num_errors);
+            cursor.pos = start;
+            cursor.errors.truncate(num_errors);
+            let result = self.parser.parse(cursor, required);
+            if result.is_err() || cursor.pos.offset <= best.1.offset {
+                let errors = cursor.memo[&key].errors.clone();
+                cursor.errors.truncate(num_errors);
+                cursor.errors.extend(errors);
+                break;
+            }
+            best = (result, cursor.pos);
+        }
+        cursor.pos = best.1;
+        store(cursor, key, best.0.clone(), required, false, num_errors);
+        best.0
+    }
+
+    fn grammar(&self) -> Grammar {
+        self.parser.grammar()
+    }
+}
+
+/*============*
+ * Repetition *
+ *============*/
+
+pub(crate) struct Opt<P> {
+    pub(crate) parser: P,
+}
+
+impl<T, I: Input, P: Parser<T, I>> Parser<Option<T>, I> for Opt<P> {
+    fn parse(
+        &self,
+        cursor: &mut Cursor<I>,
+        _required: bool,
+    ) -> Result<Option<T>, Option<ParseError>> {
+        match self.parser.parse(cursor, false) {
+            Ok(succ) => Ok(Some(succ)),
+            Err(None) => Ok(None),
+            Err(err) => Err(err),
+        }
+    }
+
+    fn grammar(&self) -> Grammar {
+        Grammar::Opt(Box::new(self.parser.grammar()))
+    }
+}
+
+pub(crate) struct Many0<P> {
+    pub(crate) parser: P,
+}
+
+impl<T, I: Input, P: Parser<T, I>> Parser<Vec<T>, I> for Many0<P> {
+    fn parse(&self, cursor: &mut Cursor<I>, _required: bool) -> Result<Vec<T>, Option<ParseError>> {
+        let mut succs = Vec::new();
+        repeat!(succ = self.parser.parse(cursor, false) => {
+            succs.push(succ);
+        });
+        Ok(succs)
+    }
+
+    fn grammar(&self) -> Grammar {
+        Grammar::Many0(Box::new(self.parser.grammar()))
+    }
+}
+
+pub(crate) struct Many1<P> {
+    pub(crate) parser: P,
+}
+
+impl<T, I: Input, P: Parser<T, I>> Parser<Vec<T>, I> for Many1<P> {
+    fn parse(&self, cursor: &mut Cursor<I>, required: bool) -> Result<Vec<T>, Option<ParseError>> {
+        let first_succ = self.parser.parse(cursor, required)?;
+        let mut succs = vec![first_succ];
+        repeat!(succ = self.parser.parse(cursor, false) => {
+            succs.push(succ);
+        });
+        Ok(succs)
+    }
+
+    fn grammar(&self) -> Grammar {
+        Grammar::Many1(Box::new(self.parser.grammar()))
+    }
+}
+
+pub(crate) struct Fold<P, Q, T2, F> {
+    pub(crate) first: P,
+    pub(crate) rest: Q,
+    pub(crate) combine: F,
+    pub(crate) rest_output: Output<T2>,
+}
+
+impl<T, T2, I: Input, P, Q, F> Parser<T, I> for Fold<P, Q, T2, F>
+where
+    P: Parser<T, I>,
+    Q: Parser<T2, I>,
+    F: Fn(T, T2) -> T,
+{
+    fn parse(&self, cursor: &mut Cursor<I>, required: bool) -> Result<T, Option<ParseError>> {
+        let mut result = self.first.parse(cursor, required)?;
+        repeat!(succ = self.rest.parse(cursor, false) => {
+            result = (self.combine)(result, succ);
+        });
+        Ok(result)
+    }
+
+    fn grammar(&self) -> Grammar {
+        Grammar::seq([
+            self.first.grammar(),
+            Grammar::Many0(Box::new(self.rest.grammar())),
+        ])
+    }
+}
+
+pub(crate) struct ManySep<P, S, T2> {
+    pub(crate) elem: P,
+    pub(crate) sep: S,
+    pub(crate) at_least_one: bool,
+    pub(crate) sep_output: Output<T2>,
+}
+
+impl<T, T2, I: Input, P, S> Parser<Vec<T>, I> for ManySep<P, S, T2>
+where
+    P: Parser<T, I>,
+    S: Parser<T2, I>,
+{
+    fn parse(&self, cursor: &mut Cursor<I>, required: bool) -> Result<Vec<T>, Option<ParseError>> {
+        let first_succ = match self.elem.parse(cursor, required && self.at_least_one) {
+            Ok(succ) => succ,
+            Err(None) if !self.at_least_one => return Ok(Vec::new()),
+            Err(err) => return Err(err),
+        };
+        let mut succs = vec![first_succ];
+        let elem_parser = self.sep.refn().cut(self.elem.refn()).map(|(_, x)| x);
+        repeat!(succ = elem_parser.parse(cursor, false) => {
+            succs.push(succ);
+        });
+        Ok(succs)
+    }
+
+    fn grammar(&self) -> Grammar {
+        Grammar::SepBy {
+            elem: Box::new(self.elem.grammar()),
+            sep: Box::new(self.sep.grammar()),
+            at_least_one: self.at_least_one,
+        }
+    }
+}
+
+/*========*
+ * Choice *
+ *========*/
+
+pub(crate) struct Alt<Ps> {
+    pub(crate) options: Ps,
+    pub(crate) label: String,
+}
+
+pub(crate) struct AltLongest<Ps> {
+    pub(crate) options: Ps,
+    pub(crate) label: String,
+}
+
+macro_rules! define_alt {
+    ($type:ident, $( ($idx:tt, $parser:ident) ),*) => {
+        impl<$type, I: Input, $( $parser ),*> AltTuple<$type, I> for ($( $parser ),*)
+        where $( $parser : Parser<$type, I> ),* {
+            fn make_alt(self, label: String) -> impl Parser<T, I> {
+                Alt { options: self, label }
+            }
+
+            fn make_alt_longest(self, label: String) -> impl Parser<T, I> {
+                AltLongest { options: self, label }
+            }
+        }
+
+        impl<$type, I: Input, $( $parser ),*> Parser<$type, I> for Alt<($( $parser ),*)>
+        where $( $parser : Parser<$type, I> ),* {
+            fn parse(&self, cursor: &mut Cursor<I>, required: bool) -> Result<T, Option<ParseError>> {
+                let start = cursor.pos;
+                let num_errors = cursor.errors.len();
+                $(
+                    match self.options.$idx.parse(cursor, false) {
+                        Ok(succ) => return Ok(succ),
+                        Err(Some(err)) => return Err(Some(err)),
+                        Err(None) => {
+                            cursor.pos = start;
+                            cursor.errors.truncate(num_errors);
+                        }
+                    }
+                )*
+                // Use the label only if the options didn't say what they expected.
+                if cursor.no_failure_since(start) {
+                    cursor.expected(&self.label);
+                }
+                if required {
+                    Err(Some(cursor.expected_error()))
+                } else {
+                    Err(None)
+                }
+            }
+
+            fn grammar(&self) -> Grammar {
+                Grammar::Alt {
+                    label: self.label.clone(),
+                    options: vec![$( self.options.$idx.grammar() ),*],
+                }
+            }
+        }
+
+        impl<$type, I: Input, $( $parser ),*> Parser<$type, I> for AltLongest<($( $parser ),*)>
+        where $( $parser : Parser<$type, I> ),* {
+            fn parse(&self, cursor: &mut Cursor<I>, required: bool) -> Result<T, Option<ParseError>> {
+                let start = cursor.pos;
+                let num_errors = cursor.errors.len();
+                let mut best = None;
+                $(
+                    match self.options.$idx.parse(cursor, false) {
+                        Ok(succ) => {
+                            let len = cursor.pos.offset - start.offset;
+                            let errors = cursor.errors.split_off(num_errors);
+                            if let Some((_, best_len, _, _)) = &best {
+                                if len > *best_len {
+                                    best = Some((succ, len, cursor.pos, errors));
+                                }
+                            } else {
+                                best = Some((succ, len, cursor.pos, errors));
+                            }
+                            cursor.pos = start;
+                        }
+                        Err(Some(err)) => return Err(Some(err)),
+                        Err(None) => {
+                            cursor.pos = start;
+                            cursor.errors.truncate(num_errors);
+                        }
+                    }
+                )*
+                if let Some((succ, _, end_pos, errors)) = best {
+                    cursor.pos = end_pos;
+                    cursor.errors.extend(errors);
+                    return Ok(succ);
+                }
+                if cursor.no_failure_since(start) {
+                    cursor.expected(&self.label);
+                }
+                if required {
+                    Err(Some(cursor.expected_error()))
+                } else {
+                    Err(None)
+                }
+            }
+
+            fn grammar(&self) -> Grammar {
+                Grammar::Alt {
+                    label: self.label.clone(),
+                    options: vec![$( self.options.$idx.grammar() ),*],
+                }
+            }
+        }
+    }
+}
+
+define_alt!(T, (0, P0), (1, P1));
+define_alt!(T, (0, P0), (1, P1), (2, P2));
+define_alt!(T, (0, P0), (1, P1), (2, P2), (3, P3));
+define_alt!(T, (0, P0), (1, P1), (2, P2), (3, P3), (4, P4));
+define_alt!(T, (0, P0), (1, P1), (2, P2), (3, P3), (4, P4), (5, P5));
+define_alt!(
+    T,
+    (0, P0),
+    (1, P1),
+    (2, P2),
+    (3, P3),
+    (4, P4),
+    (5, P5),
+    (6, P6)
+);
+define_alt!(
+    T,
+    (0, P0),
+    (1, P1),
+    (2, P2),
+    (3, P3),
+    (4, P4),
+    (5, P5),
+    (6, P6),
+    (7, P7)
+);
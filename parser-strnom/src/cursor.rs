@@ -1,45 +1,137 @@
+use crate::input::{Bytes, Input, Text, Tokens};
 use crate::parse_error::ParseError;
-use crate::pos::{Pos, Span};
+use crate::pos::{Offset, Pos, Span};
+use crate::Parser;
+use lexer::Lexeme;
 use regex::Regex;
+use std::any::Any;
+use std::collections::HashMap;
 
-pub struct Cursor<'a> {
+pub struct Cursor<'a, I: Input = Text> {
     pub filename: String,
-    pub source: &'a str,
+    pub source: &'a I::Source<'a>,
     pub pos: Pos,
+    /// Whitespace and comments to skip before each [`Parser::token`].
+    pub(crate) skip: Option<&'a dyn Parser<(), I>>,
+    /// Results of [`Parser::memo`] parsers, keyed by parser id and start offset.
+    pub(crate) memo: HashMap<(usize, Offset), MemoEntry>,
+    /// The furthest position at which a parser failed, and the labels of what was expected
+    /// there.
+    pub(crate) furthest_failure: Pos,
+    pub(crate) expected: Vec<String>,
+    /// Errors that were recovered from.
+    pub(crate) errors: Vec<ParseError>,
 }
 
-impl<'a> Cursor<'a> {
-    #[must_use]
-    pub(crate) fn consume_str(&mut self, prefix: &str, delta: Pos) -> bool {
-        if self.str().starts_with(prefix) {
-            self.pos = self.pos + delta;
-            true
-        } else {
-            false
+pub(crate) struct MemoEntry {
+    /// A `Result<T, Option<ParseError>>`.
+    pub(crate) result: Box<dyn Any>,
+    pub(crate) end: Pos,
+    /// The errors that were recovered from while computing `result`.
+    pub(crate) errors: Vec<ParseError>,
+    /// The `required` argument that `result` was computed with.
+    pub(crate) required: bool,
+    /// Whether `result` is still being computed, and is a seed for left recursion.
+    pub(crate) in_progress: bool,
+    /// Whether the parser was called again at the same position while `in_progress`.
+    pub(crate) left_recursive: bool,
+}
+
+impl<'a, I: Input> Cursor<'a, I> {
+    pub(crate) fn new(
+        filename: &str,
+        source: &'a I::Source<'a>,
+        skip: Option<&'a dyn Parser<(), I>>,
+    ) -> Self {
+        Cursor {
+            filename: filename.to_owned(),
+            source,
+            pos: Pos::new(),
+            skip,
+            memo: HashMap::new(),
+            furthest_failure: Pos::new(),
+            expected: Vec::new(),
+            errors: Vec::new(),
         }
     }
 
-    #[must_use]
-    pub(crate) fn consume_regex(&mut self, regex: &Regex) -> bool {
-        if let Some(re_match) = regex.find(self.str()) {
-            let delta = Pos::delta(re_match.as_str());
-            self.pos = self.pos + delta;
-            true
-        } else {
-            false
+    /// Run the skip parser until it fails or stops consuming input.
+    pub(crate) fn skip(&mut self) -> Result<(), ParseError> {
+        // Take the skip parser while it runs, so that any tokens inside it don't recurse.
+        let Some(skip) = self.skip.take() else {
+            return Ok(());
+        };
+        // Whitespace and comments are never what's expected.
+        let furthest_failure = self.furthest_failure;
+        let expected = std::mem::take(&mut self.expected);
+        let result = loop {
+            let start = self.pos;
+            match skip.parse(self, false) {
+                Ok(()) if self.pos != start => (),
+                Ok(()) => break Ok(()),
+                Err(None) => {
+                    self.pos = start;
+                    break Ok(());
+                }
+                Err(Some(err)) => break Err(err),
+            }
+        };
+        self.skip = Some(skip);
+        self.furthest_failure = furthest_failure;
+        self.expected = expected;
+        result
+    }
+
+    /// Record that `label` was expected at the current position.
+    pub(crate) fn expected(&mut self, label: &str) {
+        if self.pos.offset > self.furthest_failure.offset || self.expected.is_empty() {
+            self.furthest_failure = self.pos;
+            self.expected.clear();
+        }
+        if self.pos.offset == self.furthest_failure.offset
+            && !self.expected.iter().any(|l| l == label)
+        {
+            self.expected.push(label.to_owned());
+        }
+    }
+
+    /// Whether nothing has been expected at or after `start`.
+    pub(crate) fn no_failure_since(&self, start: Pos) -> bool {
+        self.expected.is_empty() || self.furthest_failure.offset < start.offset
+    }
+
+    /// An error at the furthest failure, listing everything that was expected there.
+    pub(crate) fn expected_error(&self) -> ParseError {
+        ParseError::new_expected::<I>(
+            self.filename.clone(),
+            self.source,
+            self.expected.clone(),
+            self.furthest_failure,
+        )
+    }
+
+    /// Move forward `len` units.
+    pub(crate) fn advance(&mut self, len: Offset) {
+        self.pos = I::advance(self.source, self.pos, len);
+    }
+
+    /// Move past the next character (or byte or lexeme), if any.
+    pub(crate) fn skip_char(&mut self) {
+        if let Some(len) = I::next_len(self.source, self.pos.offset) {
+            self.advance(len);
         }
     }
 
     pub(crate) fn is_at_end(&self) -> bool {
-        self.pos.offset == self.source.len()
+        self.pos.offset == I::len(self.source)
     }
 
     pub(crate) fn error(&self, message: String) -> ParseError {
-        ParseError::new(self.filename.clone(), self.source, message, self.pos, None)
+        ParseError::new::<I>(self.filename.clone(), self.source, message, self.pos, None)
     }
 
     pub(crate) fn error_from(&self, message: String, start: Pos) -> ParseError {
-        ParseError::new(
+        ParseError::new::<I>(
             self.filename.clone(),
             self.source,
             message,
@@ -48,15 +140,38 @@ impl<'a> Cursor<'a> {
         )
     }
 
-    pub(crate) fn substr_from(&self, start: Pos) -> &'a str {
-        &self.source[start.offset..self.pos.offset]
+    pub(crate) fn substr_from(&self, start: Pos) -> &'a I::Source<'a> {
+        I::slice(self.source, start.offset, self.pos.offset)
     }
 
-    pub(crate) fn span_from(&self, start: Pos) -> Span<'a> {
+    pub(crate) fn span_from(&self, start: Pos) -> Span<'a, I> {
         Span {
             start,
             end: self.pos,
-            substr: &self.source[start.offset..self.pos.offset],
+            substr: self.substr_from(start),
+        }
+    }
+}
+
+impl<'a> Cursor<'a, Text> {
+    #[must_use]
+    pub(crate) fn consume_str(&mut self, prefix: &str, delta: Pos) -> bool {
+        if self.str().starts_with(prefix) {
+            self.pos = self.pos + delta;
+            true
+        } else {
+            false
+        }
+    }
+
+    #[must_use]
+    pub(crate) fn consume_regex(&mut self, regex: &Regex) -> bool {
+        if let Some(re_match) = regex.find(self.str()) {
+            let delta = Pos::delta(re_match.as_str());
+            self.pos = self.pos + delta;
+            true
+        } else {
+            false
         }
     }
 
@@ -64,3 +179,17 @@ impl<'a> Cursor<'a> {
         &self.source[self.pos.offset..]
     }
 }
+
+impl<'a> Cursor<'a, Bytes> {
+    /// The rest of the input.
+    pub(crate) fn bytes(&self) -> &'a [u8] {
+        &self.source[self.pos.offset..]
+    }
+}
+
+impl<'a> Cursor<'a, Tokens> {
+    /// The next lexeme, if any.
+    pub(crate) fn lexeme(&self) -> Option<&'a Lexeme<'a>> {
+        self.source.get(self.pos.offset)
+    }
+}
@@ -0,0 +1,268 @@
+use crate::{Cursor, Grammar, Input, ParseError, Parser, Text};
+use std::rc::Rc;
+
+/// The associativity of an infix operator.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum Assoc {
+    /// `a - b - c` means `(a - b) - c`
+    Left,
+    /// `a ^ b ^ c` means `a ^ (b ^ c)`
+    Right,
+}
+
+type Prefix<T, I> = Box<dyn Parser<Box<dyn FnOnce(T) -> T>, I>>;
+type Infix<T, I> = Box<dyn Parser<Box<dyn FnOnce(T, T) -> T>, I>>;
+/// What `accept` returned for an operator, its builder, and its text.
+type MatchedOp<A, B> = (A, B, String);
+
+/// A parser for expressions built from atoms and operators, made by [`expr_parser`].
+pub struct ExprParser<T, I: Input = Text> {
+    label: String,
+    atom: Box<dyn Parser<T, I>>,
+    prefix: Vec<(u32, Prefix<T, I>)>,
+    postfix: Vec<(u32, Prefix<T, I>)>,
+    infix: Vec<(u32, Assoc, Infix<T, I>)>,
+}
+
+/// Parse expressions made of `atom`s and the operators added with [`ExprParser::prefix`],
+/// [`ExprParser::postfix`], and [`ExprParser::infix`]. Operators with higher precedence bind
+/// tighter. If several operators match at the same place, the one added first wins (as with
+/// `alt`), so add `"**"` before `"*"`.
+///
+/// The `label` is used in errors, like "expected expression after `+`".
+///
+/// ```
+/// use strnom::{expr_parser, parse, regex, string, Assoc, Parser};
+///
+/// let number = regex("number", "[0-9]+").unwrap().substr(|s| s.parse::<i64>().unwrap());
+/// let expr = expr_parser("expression", number)
+///     .infix(1, Assoc::Left, string("-"), |x, _, y| x - y)
+///     .infix(2, Assoc::Left, string("*"), |x, _, y| x * y)
+///     .prefix(3, string("-"), |_, x| -x);
+/// assert_eq!(parse("<example>", "2*-3-4", expr.refn()), Ok(-10));
+/// assert!(parse("<example>", "2*", expr.refn()).is_err());
+/// ```
+pub fn expr_parser<T: 'static, I: Input>(
+    label: &str,
+    atom: impl Parser<T, I> + 'static,
+) -> ExprParser<T, I> {
+    ExprParser {
+        label: label.to_owned(),
+        atom: Box::new(atom),
+        prefix: Vec::new(),
+        postfix: Vec::new(),
+        infix: Vec::new(),
+    }
+}
+
+impl<T: 'static, I: Input> ExprParser<T, I> {
+    /// Add a prefix operator. Its operand can contain operators of the same or higher
+    /// precedence: `-x.y` is `-(x.y)` if `.` has higher precedence than `-`.
+    pub fn prefix<O: 'static>(
+        mut self,
+        prec: u32,
+        op: impl Parser<O, I> + 'static,
+        build: impl Fn(O, T) -> T + 'static,
+    ) -> Self {
+        let build = Rc::new(build);
+        let op = op.map(move |o| {
+            let build = build.clone();
+            Box::new(move |x| build(o, x)) as Box<dyn FnOnce(T) -> T>
+        });
+        self.prefix.push((prec, Box::new(op)));
+        self
+    }
+
+    /// Add a postfix operator, which applies to operands made of operators of the same or higher
+    /// precedence.
+    pub fn postfix<O: 'static>(
+        mut self,
+        prec: u32,
+        op: impl Parser<O, I> + 'static,
+        build: impl Fn(T, O) -> T + 'static,
+    ) -> Self {
+        let build = Rc::new(build);
+        let op = op.map(move |o| {
+            let build = build.clone();
+            Box::new(move |x| build(x, o)) as Box<dyn FnOnce(T) -> T>
+        });
+        self.postfix.push((prec, Box::new(op)));
+        self
+    }
+
+    /// Add an infix operator.
+    pub fn infix<O: 'static>(
+        mut self,
+        prec: u32,
+        assoc: Assoc,
+        op: impl Parser<O, I> + 'static,
+        build: impl Fn(T, O, T) -> T + 'static,
+    ) -> Self {
+        let build = Rc::new(build);
+        let op = op.map(move |o| {
+            let build = build.clone();
+            Box::new(move |x, y| build(x, o, y)) as Box<dyn FnOnce(T, T) -> T>
+        });
+        self.infix.push((prec, assoc, Box::new(op)));
+        self
+    }
+
+    /// Parse an expression whose operators all have precedence at least `min_prec`.
+    fn parse_prec(
+        &self,
+        cursor: &mut Cursor<I>,
+        min_prec: u32,
+        required: bool,
+    ) -> Result<T, Option<ParseError>> {
+        let mut lhs = match self.parse_op(cursor, &self.prefix, Some)? {
+            Some((prec, build, op)) => build(self.parse_operand(cursor, prec, &op)?),
+            None => {
+                let start = cursor.pos;
+                match self.atom.parse(cursor, false) {
+                    Ok(atom) => atom,
+                    Err(Some(err)) => return Err(Some(err)),
+                    Err(None) => {
+                        if cursor.no_failure_since(start) {
+                            cursor.expected(&self.label);
+                        }
+                        return Err(required.then(|| cursor.expected_error()));
+                    }
+                }
+            }
+        };
+
+        loop {
+            let postfix = self.parse_op(cursor, &self.postfix, |prec| {
+                (prec >= min_prec).then_some(())
+            })?;
+            if let Some(((), build, _)) = postfix {
+                lhs = build(lhs);
+                continue;
+            }
+
+            let infix = self.parse_op(cursor, &self.infix, |(prec, assoc)| {
+                (prec >= min_prec).then_some(match assoc {
+                    Assoc::Left => prec + 1,
+                    Assoc::Right => prec,
+                })
+            })?;
+            match infix {
+                Some((rhs_prec, build, op)) => {
+                    let rhs = self.parse_operand(cursor, rhs_prec, &op)?;
+                    lhs = build(lhs, rhs);
+                }
+                None => return Ok(lhs),
+            }
+        }
+    }
+
+    /// Parse the operand of an operator, which must be there.
+    fn parse_operand(
+        &self,
+        cursor: &mut Cursor<I>,
+        min_prec: u32,
+        op: &str,
+    ) -> Result<T, Option<ParseError>> {
+        match self.parse_prec(cursor, min_prec, false) {
+            Ok(operand) => Ok(operand),
+            Err(Some(err)) => Err(Some(err)),
+            Err(None) => Err(Some(
+                cursor.error(format!("expected {} after `{}`", self.label, op)),
+            )),
+        }
+    }
+
+    /// Try each operator in turn, stopping at the first that matches. If `accept` rejects it
+    /// (because its precedence is too low), back up and return `None`. Otherwise return what
+    /// `accept` returned, the operator's builder, and its text.
+    fn parse_op<Op: OpTable<I>, A>(
+        &self,
+        cursor: &mut Cursor<I>,
+        ops: &[Op],
+        accept: impl Fn(Op::Info) -> Option<A>,
+    ) -> Result<Option<MatchedOp<A, Op::Build>>, Option<ParseError>> {
+        let start = cursor.pos;
+        for op in ops {
+            match op.parser().parse(cursor, false) {
+                Ok(build) => match accept(op.info()) {
+                    Some(accepted) => {
+                        let text = I::show(cursor.substr_from(start)).trim().to_owned();
+                        return Ok(Some((accepted, build, text)));
+                    }
+                    None => {
+                        cursor.pos = start;
+                        return Ok(None);
+                    }
+                },
+                Err(None) => cursor.pos = start,
+                Err(Some(err)) => return Err(Some(err)),
+            }
+        }
+        Ok(None)
+    }
+}
+
+/// An entry in one of the operator tables.
+trait OpTable<I: Input> {
+    type Info;
+    type Build;
+    fn info(&self) -> Self::Info;
+    fn parser(&self) -> &dyn Parser<Self::Build, I>;
+}
+
+impl<T, I: Input> OpTable<I> for (u32, Prefix<T, I>) {
+    type Info = u32;
+    type Build = Box<dyn FnOnce(T) -> T>;
+
+    fn info(&self) -> u32 {
+        self.0
+    }
+
+    fn parser(&self) -> &dyn Parser<Self::Build, I> {
+        self.1.as_ref()
+    }
+}
+
+impl<T, I: Input> OpTable<I> for (u32, Assoc, Infix<T, I>) {
+    type Info = (u32, Assoc);
+    type Build = Box<dyn FnOnce(T, T) -> T>;
+
+    fn info(&self) -> (u32, Assoc) {
+        (self.0, self.1)
+    }
+
+    fn parser(&self) -> &dyn Parser<Self::Build, I> {
+        self.2.as_ref()
+    }
+}
+
+impl<T: 'static, I: Input> Parser<T, I> for ExprParser<T, I> {
+    fn parse(&self, cursor: &mut Cursor<I>, required: bool) -> Result<T, Option<ParseError>> {
+        self.parse_prec(cursor, 0, required)
+    }
+
+    /// A rule named by the label: operands separated by infix operators, where each operand is
+    /// an atom with any prefix and postfix operators. This ignores precedence.
+    fn grammar(&self) -> Grammar {
+        let many0 = |ops: Vec<Grammar>| match Grammar::choice(ops) {
+            Grammar::Empty => Grammar::Empty,
+            ops => Grammar::Many0(Box::new(ops)),
+        };
+        let prefix = many0(self.prefix.iter().map(|(_, op)| op.grammar()).collect());
+        let postfix = many0(self.postfix.iter().map(|(_, op)| op.grammar()).collect());
+        let operand = Grammar::seq([prefix, self.atom.grammar(), postfix]);
+        let infix = self.infix.iter().map(|(_, _, op)| op.grammar()).collect();
+        let definition = match Grammar::choice(infix) {
+            Grammar::Empty => operand,
+            infix => Grammar::SepBy {
+                elem: Box::new(operand),
+                sep: Box::new(infix),
+                at_least_one: true,
+            },
+        };
+        Grammar::Rule {
+            name: self.label.clone(),
+            definition: Box::new(definition),
+        }
+    }
+}
@@ -0,0 +1,268 @@
+use crate::railroad;
+use std::fmt::Write;
+
+/// A description of what a parser matches, from [`Parser::grammar`](crate::Parser::grammar).
+///
+/// The combinators describe themselves, so the grammar of a parser built from them mirrors its
+/// structure. Mapping combinators like `map` and `token` are transparent. Parsers written as
+/// closures or functions are [`Grammar::Opaque`], so give recursive parsers a name with
+/// [`Parser::rule`](crate::Parser::rule).
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum Grammar {
+    /// Matches the empty string.
+    Empty,
+    /// An exact string, like `"["`.
+    Literal(String),
+    /// A class of lexemes described by a label, like a regex labeled "number".
+    Label(String),
+    /// A parser that doesn't describe itself.
+    Opaque,
+    Seq(Vec<Grammar>),
+    /// A choice. If `label` isn't empty, it's shown as a rule of that name.
+    Alt {
+        label: String,
+        options: Vec<Grammar>,
+    },
+    Opt(Box<Grammar>),
+    Many0(Box<Grammar>),
+    Many1(Box<Grammar>),
+    /// Elements separated by separators.
+    SepBy {
+        elem: Box<Grammar>,
+        sep: Box<Grammar>,
+        at_least_one: bool,
+    },
+    /// A named rule. Its definition is `Opaque` if this is just a reference to the rule, as for
+    /// the function that a recursive parser calls itself through.
+    Rule {
+        name: String,
+        definition: Box<Grammar>,
+    },
+}
+
+impl Grammar {
+    /// Split the grammar into named rules, starting with this grammar itself (named "start"
+    /// unless it's a rule). Every other rule and labeled `alt` becomes a [`Grammar::Rule`] whose
+    /// definition is `Opaque`, referring to another entry in the list. If different rules share
+    /// a name, later ones get a numeric suffix.
+    pub fn rules(&self) -> Vec<(String, Grammar)> {
+        let mut rules = Rules::default();
+        let (name, definition) = match self {
+            Grammar::Rule { name, definition } if **definition != Grammar::Opaque => {
+                (name.as_str(), definition.as_ref())
+            }
+            Grammar::Alt { label, .. } if !label.is_empty() => (label.as_str(), self),
+            _ => ("start", self),
+        };
+        rules.define(name, definition);
+        rules
+            .rules
+            .into_iter()
+            .map(|rule| (rule.unique_name, rule.extracted))
+            .collect()
+    }
+
+    /// Show the grammar as [ISO 14977](https://www.iso.org/standard/26153.html) EBNF, one rule
+    /// per line. Labels and opaque parsers are shown as special sequences, like `? number ?`.
+    pub fn to_ebnf(&self) -> String {
+        let mut ebnf = String::new();
+        for (name, definition) in self.rules() {
+            writeln!(ebnf, "{} = {} ;", name, Ebnf(&definition, Context::Top)).unwrap();
+        }
+        ebnf
+    }
+
+    /// Draw the grammar as a railroad diagram for each rule, as a standalone SVG document.
+    pub fn to_svg(&self) -> String {
+        railroad::to_svg(&self.rules())
+    }
+
+    /// A sequence, without nested sequences or empty elements.
+    pub(crate) fn seq(elems: impl IntoIterator<Item = Grammar>) -> Grammar {
+        let mut flat = Vec::new();
+        for elem in elems {
+            match elem {
+                Grammar::Empty => (),
+                Grammar::Seq(inner) => flat.extend(inner),
+                elem => flat.push(elem),
+            }
+        }
+        match flat.len() {
+            0 => Grammar::Empty,
+            1 => flat.pop().unwrap(),
+            _ => Grammar::Seq(flat),
+        }
+    }
+
+    /// An unlabeled choice, which is just the option if there's only one.
+    pub(crate) fn choice(mut options: Vec<Grammar>) -> Grammar {
+        match options.len() {
+            0 => Grammar::Empty,
+            1 => options.pop().unwrap(),
+            _ => Grammar::Alt {
+                label: String::new(),
+                options,
+            },
+        }
+    }
+
+    /// A reference to the rule `name`.
+    pub(crate) fn reference(name: &str) -> Grammar {
+        Grammar::Rule {
+            name: name.to_owned(),
+            definition: Box::new(Grammar::Opaque),
+        }
+    }
+}
+
+#[derive(Default)]
+struct Rules {
+    rules: Vec<RuleEntry>,
+}
+
+struct RuleEntry {
+    /// The name the rule was given, and its unique name.
+    name: String,
+    unique_name: String,
+    definition: Grammar,
+    /// The definition, with nested rules replaced by references.
+    extracted: Grammar,
+}
+
+impl Rules {
+    /// Add a rule (if there isn't an identical one already), returning its unique name.
+    fn define(&mut self, name: &str, definition: &Grammar) -> String {
+        let name = name.replace(' ', "_");
+        let existing = self
+            .rules
+            .iter()
+            .find(|rule| rule.name == name && rule.definition == *definition);
+        if let Some(rule) = existing {
+            return rule.unique_name.clone();
+        }
+        let mut unique_name = name.clone();
+        let mut suffix = 1;
+        while self
+            .rules
+            .iter()
+            .any(|rule| rule.unique_name == unique_name)
+        {
+            suffix += 1;
+            unique_name = format!("{}_{}", name, suffix);
+        }
+        let index = self.rules.len();
+        self.rules.push(RuleEntry {
+            name,
+            unique_name: unique_name.clone(),
+            definition: definition.clone(),
+            extracted: Grammar::Empty,
+        });
+        self.rules[index].extracted = self.extract(definition, true);
+        unique_name
+    }
+
+    /// Replace nested rules with references to them.
+    fn extract(&mut self, grammar: &Grammar, top: bool) -> Grammar {
+        use Grammar::*;
+
+        let mut extract = |g: &Grammar| Box::new(self.extract(g, false));
+        match grammar {
+            Empty | Literal(_) | Label(_) | Opaque => grammar.clone(),
+            Rule { name, definition } if **definition == Opaque => {
+                Grammar::reference(&name.replace(' ', "_"))
+            }
+            Rule { name, definition } => Grammar::reference(&self.define(name, definition)),
+            Alt { label, .. } if !top && !label.is_empty() => {
+                Grammar::reference(&self.define(label, grammar))
+            }
+            Alt { label, options } => Alt {
+                label: label.clone(),
+                options: options.iter().map(|g| self.extract(g, false)).collect(),
+            },
+            Seq(elems) => Seq(elems.iter().map(|g| self.extract(g, false)).collect()),
+            Opt(g) => Opt(extract(g)),
+            Many0(g) => Many0(extract(g)),
+            Many1(g) => Many1(extract(g)),
+            SepBy {
+                elem,
+                sep,
+                at_least_one,
+            } => SepBy {
+                elem: extract(elem),
+                sep: extract(sep),
+                at_least_one: *at_least_one,
+            },
+        }
+    }
+}
+
+/// Where a grammar is being shown, to decide whether it needs parentheses.
+#[derive(Clone, Copy, PartialEq, Eq)]
+enum Context {
+    Top,
+    Seq,
+}
+
+struct Ebnf<'a>(&'a Grammar, Context);
+
+impl std::fmt::Display for Ebnf<'_> {
+    fn fmt(&self, f: &mut std::fmt::Formatter) -> std::fmt::Result {
+        use Grammar::*;
+
+        let Ebnf(grammar, context) = *self;
+        match grammar {
+            Empty => write!(f, "\"\""),
+            Literal(s) if s.contains('"') => write!(f, "'{}'", s),
+            Literal(s) => write!(f, "\"{}\"", s),
+            Label(label) => write!(f, "? {} ?", label),
+            Opaque => write!(f, "? unknown ?"),
+            Rule { name, .. } => write!(f, "{}", name),
+            Seq(elems) => {
+                for (i, elem) in elems.iter().enumerate() {
+                    if i > 0 {
+                        write!(f, " , ")?;
+                    }
+                    write!(f, "{}", Ebnf(elem, Context::Seq))?;
+                }
+                Ok(())
+            }
+            Alt { options, .. } => {
+                let parens = context == Context::Seq;
+                if parens {
+                    write!(f, "( ")?;
+                }
+                for (i, option) in options.iter().enumerate() {
+                    if i > 0 {
+                        write!(f, " | ")?;
+                    }
+                    write!(f, "{}", Ebnf(option, Context::Top))?;
+                }
+                if parens {
+                    write!(f, " )")?;
+                }
+                Ok(())
+            }
+            Opt(g) => write!(f, "[ {} ]", Ebnf(g, Context::Top)),
+            Many0(g) => write!(f, "{{ {} }}", Ebnf(g, Context::Top)),
+            Many1(g) => write!(
+                f,
+                "{} , {{ {} }}",
+                Ebnf(g, Context::Seq),
+                Ebnf(g, Context::Top)
+            ),
+            SepBy {
+                elem,
+                sep,
+                at_least_one,
+            } => {
+                let elem = Ebnf(elem, Context::Seq);
+                let sep = Ebnf(sep, Context::Seq);
+                if *at_least_one {
+                    write!(f, "{} , {{ {} , {} }}", elem, sep, elem)
+                } else {
+                    write!(f, "[ {} , {{ {} , {} }} ]", elem, sep, elem)
+                }
+            }
+        }
+    }
+}
@@ -0,0 +1,197 @@
+use crate::pos::{Offset, Pos};
+use lexer::Lexeme;
+use std::fmt;
+use std::hash::Hash;
+
+/// A kind of input that parsers can read. Every [`Parser`](crate::Parser) is for one kind of
+/// input, [`Text`] by default.
+///
+/// A [`Pos`]'s `offset` counts in the units of the input: bytes for [`Text`] and [`Bytes`],
+/// and lexemes for [`Tokens`].
+pub trait Input: 'static {
+    /// The input being parsed.
+    type Source<'s>: ?Sized + fmt::Debug + Eq + Hash + 's;
+
+    /// Shorten the lifetime inside `source`. Every kind of source is covariant, but the
+    /// compiler can't see that through the associated type.
+    fn shorten<'a, 's: 'a>(source: &'a Self::Source<'s>) -> &'a Self::Source<'a>;
+
+    /// The length of `source`, in units of `Pos::offset`.
+    fn len(source: &Self::Source<'_>) -> Offset;
+
+    /// The part of `source` from offset `start` to offset `end`.
+    fn slice<'a>(source: &'a Self::Source<'a>, start: Offset, end: Offset) -> &'a Self::Source<'a>;
+
+    /// The position `len` units after `pos`.
+    fn advance(source: &Self::Source<'_>, pos: Pos, len: Offset) -> Pos;
+
+    /// The length of the smallest piece of input at `offset` that can be skipped: a character
+    /// for text. `None` at the end of the input.
+    fn next_len(source: &Self::Source<'_>, offset: Offset) -> Option<Offset>;
+
+    /// The line containing `pos`, to show in error messages.
+    fn line_contents(source: &Self::Source<'_>, pos: Pos) -> String;
+
+    /// Show part of the input in an error message.
+    fn show(source: &Self::Source<'_>) -> String;
+}
+
+/// Text input: a `&str`. This is the default kind of [`Input`].
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub struct Text;
+
+/// Binary input: a `&[u8]`. See the [`bytes`](crate::bytes) module for parsers. Lines are
+/// separated by `\n`, and columns are counted in bytes.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub struct Bytes;
+
+/// A slice of lexemes from the `lexer` crate. See the [`tokens`](crate::tokens) module for
+/// parsers. Positions come from the lexemes' line and (utf8) column.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub struct Tokens;
+
+impl Input for Text {
+    type Source<'s> = str;
+
+    fn shorten<'a, 's: 'a>(source: &'a str) -> &'a str {
+        source
+    }
+
+    fn len(source: &str) -> Offset {
+        source.len()
+    }
+
+    fn slice(source: &str, start: Offset, end: Offset) -> &str {
+        &source[start..end]
+    }
+
+    fn advance(source: &str, pos: Pos, len: Offset) -> Pos {
+        pos + Pos::delta(&source[pos.offset..pos.offset + len])
+    }
+
+    fn next_len(source: &str, offset: Offset) -> Option<Offset> {
+        source[offset..].chars().next().map(char::len_utf8)
+    }
+
+    fn line_contents(source: &str, pos: Pos) -> String {
+        source
+            .lines()
+            .nth(pos.line as usize)
+            .unwrap_or("")
+            .to_owned()
+    }
+
+    fn show(source: &str) -> String {
+        source.to_owned()
+    }
+}
+
+impl Input for Bytes {
+    type Source<'s> = [u8];
+
+    fn shorten<'a, 's: 'a>(source: &'a [u8]) -> &'a [u8] {
+        source
+    }
+
+    fn len(source: &[u8]) -> Offset {
+        source.len()
+    }
+
+    fn slice(source: &[u8], start: Offset, end: Offset) -> &[u8] {
+        &source[start..end]
+    }
+
+    fn advance(source: &[u8], mut pos: Pos, len: Offset) -> Pos {
+        for byte in &source[pos.offset..pos.offset + len] {
+            if *byte == b'\n' {
+                pos.line += 1;
+                pos.col = 0;
+            } else {
+                pos.col += 1;
+            }
+        }
+        pos.offset += len;
+        pos
+    }
+
+    fn next_len(source: &[u8], offset: Offset) -> Option<Offset> {
+        (offset < source.len()).then_some(1)
+    }
+
+    fn line_contents(source: &[u8], pos: Pos) -> String {
+        let line = source.split(|byte| *byte == b'\n').nth(pos.line as usize);
+        Bytes::show(line.unwrap_or(&[]))
+    }
+
+    /// Printable ASCII as is, and other bytes as `.`, so that columns line up.
+    fn show(source: &[u8]) -> String {
+        source
+            .iter()
+            .map(|byte| match byte {
+                b' '..=b'~' => *byte as char,
+                _ => '.',
+            })
+            .collect()
+    }
+}
+
+impl Input for Tokens {
+    type Source<'s> = [Lexeme<'s>];
+
+    fn shorten<'a, 's: 'a>(source: &'a [Lexeme<'s>]) -> &'a [Lexeme<'a>] {
+        source
+    }
+
+    fn len(source: &[Lexeme]) -> Offset {
+        source.len()
+    }
+
+    fn slice<'a>(source: &'a [Lexeme<'a>], start: Offset, end: Offset) -> &'a [Lexeme<'a>] {
+        &source[start..end]
+    }
+
+    fn advance(source: &[Lexeme], pos: Pos, len: Offset) -> Pos {
+        let offset = pos.offset + len;
+        let position = match source.get(offset) {
+            Some(lexeme) => lexeme.start,
+            None => match source.last() {
+                Some(lexeme) => lexeme.end,
+                None => return Pos::new(),
+            },
+        };
+        Pos {
+            offset,
+            line: position.line as u32,
+            col: position.utf8_col as u32,
+        }
+    }
+
+    fn next_len(source: &[Lexeme], offset: Offset) -> Option<Offset> {
+        (offset < source.len()).then_some(1)
+    }
+
+    /// Rebuilt from the lexemes on the line, so anything the lexer skipped (like comments) is
+    /// shown as spaces.
+    fn line_contents(source: &[Lexeme], pos: Pos) -> String {
+        let mut line = String::new();
+        let mut col = 0;
+        for lexeme in source {
+            if lexeme.start.line != pos.line as usize {
+                continue;
+            }
+            while col < lexeme.start.utf8_col {
+                line.push(' ');
+                col += 1;
+            }
+            let text = lexeme.lexeme.lines().next().unwrap_or("");
+            line.push_str(text);
+            col += text.chars().count();
+        }
+        line
+    }
+
+    fn show(source: &[Lexeme]) -> String {
+        let lexemes = source.iter().map(|lexeme| lexeme.lexeme);
+        lexemes.collect::<Vec<_>>().join(" ")
+    }
+}
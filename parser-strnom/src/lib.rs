@@ -10,6 +10,17 @@
 //! p_string()           ()
 //! p_regex()            ()
 //!
+//! ~~ other inputs ~~
+//! Parsers are for text (Parser<T> = Parser<T, Text>) unless they say otherwise. For the other
+//! kinds of Input, pass a &[u8] or &[Lexeme] to parse(), and use these lexemes:
+//! bytes::tag(b)        ()             Bytes
+//! bytes::take(n)       ()             Bytes
+//! bytes::byte_if(f)    u8             Bytes
+//! bytes::regex()       ()             Bytes
+//! tokens::token(tok)   ()             Tokens; a lexeme of token `tok`
+//! tokens::text(s)      ()             Tokens; a lexeme whose text is `s`
+//! P.substr(f) passes f a &[u8] or &[Lexeme] instead of &str.
+//!
 //! ~~ mapping ~~
 //! P.constant(V)        V
 //! P.fail(msg)          !
@@ -25,11 +36,21 @@
 //! (P, Q, R)            (P, Q, R)
 //! ...                  ...
 //!
+//! ~~ whitespace ~~
+//! P.token()            P              skip whitespace first (see parse_with_skip)
+//!
 //! ~~ Error Handling ~~
 //! P.resolve()          eliminate Result
+//! P.recover_until(Q)   Option<P>      on error, skip to Q (see parse_all_errors)
+//! P.recover_with(V)    P              on error, use V
 //!
 //! ~~ Sharing ~~
 //! P.refn()             P
+//! P.memo()             P              T: Clone; packrat, allows left recursion
+//!
+//! ~~ Grammars ~~
+//! P.rule(name)         P              name P in its grammar
+//! P.grammar()          Grammar        .to_ebnf(), or .to_svg() for a railroad diagram
 //!
 //! ~~ repetition ~~
 //! P.opt()              Option<P>
@@ -43,52 +64,148 @@
 //! ~~ other ~~
 //! (P1, ..., Pn)
 //!   Makes a parser of output type (P1, ..., Pn)
-//!   Backtracks! Use P1.cut((P2, ..., Pn)) to not backtrack if P1 succeeds.
+//!   Backtracks! Use P1.cut((P2, ..., Pn)) to not backtrack if P1 succeeds,
+//!   or P.memo() so that backtracking doesn't re-parse P.
 //! alt(name, (P1, ..., Pn))
 //!   Try each parser in turn, using the first that succeeds.
 //!   Requires that they all have the same output type.
+//!   On failure, errors list what the options expected (or the name,
+//!   if they don't say).
 //! alt_longest(name, (P1, ..., Pn))
 //!   Try each parser in turn, using the longest successful match,
 //!   with ties won by the earlier parser.
 //!   Requires that they all have the same output type.
 //! P.resolve()
 //!   Converts Parser<Result<T, E>> into Parser<E> by shifting the error
-//!   into the parse result.
+//!   into the parse result. An error is reported even inside an alt,
+//!   since P has already matched.
+//!
+//! ~~ expressions ~~
+//! expr_parser(name, A)
+//!   .prefix(prec, Op, f)                 f: Fn(Op, A) -> A
+//!   .postfix(prec, Op, f)                f: Fn(A, Op) -> A
+//!   .infix(prec, assoc, Op, f)           f: Fn(A, Op, A) -> A
+//!   Parses atoms A combined with operators, by precedence climbing.
+//!   Higher precedences bind tighter. Output type A.
 //!
 //! ~~ recursion ~~
 //! For recursion, use the impl of Parser for functions:
-//!     impl<T, F> Parser<T> for F
-//!     where F: Fn(&mut Cursor, bool) -> Result<T, Option<ParseError>>,
+//!     impl<T, I, F> Parser<T, I> for F
+//!     where F: Fn(&mut Cursor<I>, bool) -> Result<T, Option<ParseError>>,
 //! ```
 
+pub mod bytes;
+mod combinators;
 mod cursor;
+mod expr;
+mod grammar;
+mod input;
 mod parse_error;
 mod pos;
+mod railroad;
+pub mod tokens;
 
 pub use cursor::Cursor;
+pub use expr::{expr_parser, Assoc, ExprParser};
+pub use grammar::Grammar;
+pub use input::{Bytes, Input, Text, Tokens};
 pub use parse_error::ParseError;
 pub use pos::{Pos, Span};
 
+use combinators::{
+    Constant, Cut, Fail, Fold, MakeConstant, Many0, Many1, ManySep, Map, MapErr, MapSpan, Memo,
+    Opt, RecoverUntil, RecoverWith, Resolve, Rule, SpanOf, Substr, Token,
+};
 use regex::{Error as RegexError, Regex};
+use std::marker::PhantomData;
+use std::sync::atomic::{AtomicUsize, Ordering};
+
+/// Parse all of `source` with `parser`. The source is text by default, or bytes or a slice of
+/// lexemes for parsers of [`Bytes`] or [`Tokens`].
+pub fn parse<'s, T, I: Input>(
+    filename: &str,
+    source: &(impl AsRef<I::Source<'s>> + ?Sized),
+    parser: impl Parser<T, I>,
+) -> Result<T, ParseError> {
+    parse_cursor(
+        Cursor::new(filename, I::shorten(source.as_ref()), None),
+        parser,
+    )
+}
 
-pub fn parse<T>(filename: &str, source: &str, parser: impl Parser<T>) -> Result<T, ParseError> {
-    let mut cursor = Cursor {
-        filename: filename.to_owned(),
-        source,
-        pos: Pos::new(),
-    };
-    match parser.parse(&mut cursor, true) {
+/// Like [`parse`], but skip over whatever `skip` matches (typically whitespace and comments)
+/// before each [`Parser::token`], and at the end of the input. `skip` is run repeatedly, so it
+/// only needs to match a single space or comment.
+pub fn parse_with_skip<'s, T, I: Input>(
+    filename: &str,
+    source: &(impl AsRef<I::Source<'s>> + ?Sized),
+    skip: impl Parser<(), I>,
+    parser: impl Parser<T, I>,
+) -> Result<T, ParseError> {
+    parse_cursor(
+        Cursor::new(filename, I::shorten(source.as_ref()), Some(&skip)),
+        parser,
+    )
+}
+
+/// Like [`parse`], but keep going after errors where the parser recovers from them (see
+/// [`Parser::recover_until`] and [`Parser::recover_with`]). Returns every error, and the result
+/// if there is one, even if there were errors.
+pub fn parse_all_errors<'s, T, I: Input>(
+    filename: &str,
+    source: &(impl AsRef<I::Source<'s>> + ?Sized),
+    parser: impl Parser<T, I>,
+) -> (Option<T>, Vec<ParseError>) {
+    let mut cursor = Cursor::new(filename, I::shorten(source.as_ref()), None);
+    let result = run_parser(&mut cursor, parser);
+    (result, cursor.errors)
+}
+
+/// [`parse_all_errors`], skipping whitespace as in [`parse_with_skip`].
+pub fn parse_all_errors_with_skip<'s, T, I: Input>(
+    filename: &str,
+    source: &(impl AsRef<I::Source<'s>> + ?Sized),
+    skip: impl Parser<(), I>,
+    parser: impl Parser<T, I>,
+) -> (Option<T>, Vec<ParseError>) {
+    let mut cursor = Cursor::new(filename, I::shorten(source.as_ref()), Some(&skip));
+    let result = run_parser(&mut cursor, parser);
+    (result, cursor.errors)
+}
+
+fn parse_cursor<T, I: Input>(
+    mut cursor: Cursor<I>,
+    parser: impl Parser<T, I>,
+) -> Result<T, ParseError> {
+    let result = run_parser(&mut cursor, parser);
+    match cursor.errors.into_iter().next() {
+        Some(err) => Err(err),
+        None => Ok(result.unwrap()),
+    }
+}
+
+/// Parse the whole input, adding any errors to `cursor.errors`.
+fn run_parser<T, I: Input>(cursor: &mut Cursor<I>, parser: impl Parser<T, I>) -> Option<T> {
+    match parser.parse(cursor, true) {
         Ok(succ) => {
-            if cursor.is_at_end() {
-                Ok(succ)
-            } else {
-                Err(cursor.error("expected end of file".to_owned()))
+            if let Err(err) = cursor.skip() {
+                cursor.errors.push(err);
+            } else if !cursor.is_at_end() {
+                cursor.expected("end of file");
+                let err = cursor.expected_error();
+                cursor.errors.push(err);
             }
+            Some(succ)
+        }
+        Err(Some(err)) => {
+            cursor.errors.push(err);
+            None
         }
-        Err(Some(err)) => Err(err),
         Err(None) => {
-            Err(cursor
-                .error("Invalid parser returned no error even though required=true".to_owned()))
+            let err = cursor
+                .error("Invalid parser returned no error even though required=true".to_owned());
+            cursor.errors.push(err);
+            None
         }
     }
 }
@@ -106,166 +223,273 @@ impl<T, E> IsResult<T, E> for Result<T, E> {
 
 pub struct Refn<'a, P>(&'a P);
 
-macro_rules! repeat {
-    ($var:ident = $parse:expr => $on_success:block) => {
-        loop {
-            match $parse {
-                Ok($var) => $on_success,
-                Err(None) => break,
-                Err(Some(err)) => return Err(Some(err)),
-            }
-        }
-    };
+impl<'a, P> Clone for Refn<'a, P> {
+    fn clone(&self) -> Refn<'a, P> {
+        *self
+    }
+}
+
+impl<'a, P> Copy for Refn<'a, P> {}
+
+impl<'a, T, I: Input, P> Parser<T, I> for Refn<'a, P>
+where
+    P: Parser<T, I>,
+{
+    fn parse(&self, cursor: &mut Cursor<I>, required: bool) -> Result<T, Option<ParseError>> {
+        self.0.parse(cursor, required)
+    }
+
+    fn grammar(&self) -> Grammar {
+        self.0.grammar()
+    }
+}
+
+/// A parser along with a description of its grammar, which is only built when asked for.
+struct Described<G, P> {
+    grammar: G,
+    parser: P,
+}
+
+impl<T, I: Input, G, P> Parser<T, I> for Described<G, P>
+where
+    G: Fn() -> Grammar,
+    P: Parser<T, I>,
+{
+    fn parse(&self, cursor: &mut Cursor<I>, required: bool) -> Result<T, Option<ParseError>> {
+        self.parser.parse(cursor, required)
+    }
+
+    fn grammar(&self) -> Grammar {
+        (self.grammar)()
+    }
+}
+
+pub(crate) fn described<T, I: Input>(
+    grammar: impl Fn() -> Grammar,
+    parser: impl Parser<T, I>,
+) -> impl Parser<T, I> {
+    Described { grammar, parser }
 }
 
 /*==============*
  * Parser Trait *
  *==============*/
 
-pub trait Parser<T> {
+pub trait Parser<T, I: Input = Text> {
     #[doc(hidden)]
-    fn parse(&self, cursor: &mut Cursor, required: bool) -> Result<T, Option<ParseError>>;
+    fn parse(&self, cursor: &mut Cursor<I>, required: bool) -> Result<T, Option<ParseError>>;
+
+    /// Describe what this parser matches, to show as EBNF or a railroad diagram. Parsers that
+    /// aren't built from combinators are [`Grammar::Opaque`].
+    fn grammar(&self) -> Grammar {
+        Grammar::Opaque
+    }
+
+    /// Name this parser, so that its [`grammar`](Parser::grammar) shows it as a rule. To show
+    /// a recursive grammar, also name the function that the parser calls itself through, like
+    /// `value.rule("value")`.
+    fn rule(self, name: &str) -> impl Parser<T, I>
+    where
+        Self: Sized,
+    {
+        Rule {
+            parser: self,
+            name: name.to_owned(),
+        }
+    }
 
     /*=========*
      * Mapping *
      *=========*/
 
-    fn constant<T2>(self, val: T2) -> impl Parser<T2>
+    fn constant<T2>(self, val: T2) -> impl Parser<T2, I>
     where
         Self: Sized,
         T2: Clone,
     {
-        move |cursor: &mut Cursor, required: bool| match self.parse(cursor, required) {
-            Ok(_) => Ok(val.clone()),
-            Err(err) => Err(err),
+        Constant {
+            parser: self,
+            val,
+            output: PhantomData,
         }
     }
 
-    fn make_constant<T2>(self, make_val: impl Fn() -> T2) -> impl Parser<T2>
+    fn make_constant<T2>(self, make_val: impl Fn() -> T2) -> impl Parser<T2, I>
     where
         Self: Sized,
     {
-        move |cursor: &mut Cursor, required: bool| match self.parse(cursor, required) {
-            Ok(_) => Ok(make_val()),
-            Err(err) => Err(err),
+        MakeConstant {
+            parser: self,
+            make_val,
+            output: PhantomData,
         }
     }
 
-    fn fail(self, message: &str) -> impl Parser<T>
+    fn fail(self, message: &str) -> impl Parser<T, I>
     where
         Self: Sized,
     {
-        let message = message.to_owned();
-        move |cursor: &mut Cursor, required: bool| {
-            let start = cursor.pos;
-            self.parse(cursor, required)?;
-            Err(Some(cursor.error_from(message.clone(), start)))
+        Fail {
+            parser: self,
+            message: message.to_owned(),
         }
     }
 
-    fn cut<T2>(self, other: impl Parser<T2>) -> impl Parser<(T, T2)>
+    fn cut<T2>(self, other: impl Parser<T2, I>) -> impl Parser<(T, T2), I>
     where
         Self: Sized,
     {
-        move |cursor: &mut Cursor, required: bool| {
-            let res_1 = self.parse(cursor, required)?;
-            let res_2 = other.parse(cursor, true)?;
-            Ok((res_1, res_2))
+        Cut {
+            first: self,
+            second: other,
         }
     }
 
-    fn map_err(self, func: impl Fn(String) -> String) -> impl Parser<T>
+    fn map_err(self, func: impl Fn(String) -> String) -> impl Parser<T, I>
     where
         Self: Sized,
     {
-        move |cursor: &mut Cursor, required: bool| {
-            self.parse(cursor, required)
-                .map_err(|opt| opt.map(|err| err.map(&func)))
-        }
+        MapErr { parser: self, func }
     }
 
-    fn map<T2>(self, func: impl Fn(T) -> T2) -> impl Parser<T2>
+    fn map<T2>(self, func: impl Fn(T) -> T2) -> impl Parser<T2, I>
     where
         Self: Sized,
     {
-        move |cursor: &mut Cursor, required: bool| self.parse(cursor, required).map(&func)
+        Map {
+            parser: self,
+            func,
+            output: PhantomData,
+        }
     }
 
-    fn resolve<T2, E>(self) -> impl Parser<T2>
+    fn resolve<T2, E>(self) -> impl Parser<T2, I>
     where
         T: IsResult<T2, E>,
         E: std::error::Error,
         Self: Sized,
     {
-        move |cursor: &mut Cursor, required: bool| {
-            let start = cursor.pos;
-            let result = self.parse(cursor, required)?;
-            match result.into_result() {
-                Ok(succ) => Ok(succ),
-                Err(err) if required => Err(Some(cursor.error_from(err.to_string(), start))),
-                Err(_) => Err(None),
-            }
+        Resolve {
+            parser: self,
+            output: PhantomData,
         }
     }
 
-    fn substr<T2>(self, func: impl Fn(&str) -> T2) -> impl Parser<T2>
+    fn substr<T2>(self, func: impl Fn(&I::Source<'_>) -> T2) -> impl Parser<T2, I>
     where
         Self: Sized,
     {
-        move |cursor: &mut Cursor, required: bool| {
-            let start = cursor.pos;
-            self.parse(cursor, required)?;
-            Ok(func(cursor.substr_from(start)))
+        Substr {
+            parser: self,
+            func,
+            output: PhantomData,
         }
     }
 
-    fn span<T2>(self, func: impl Fn(Span) -> T2) -> impl Parser<T2>
+    fn span<T2>(self, func: impl Fn(Span<I>) -> T2) -> impl Parser<T2, I>
     where
         Self: Sized,
     {
-        move |cursor: &mut Cursor, required: bool| {
-            let start = cursor.pos;
-            self.parse(cursor, required)?;
-            Ok(func(cursor.span_from(start)))
+        SpanOf {
+            parser: self,
+            func,
+            output: PhantomData,
         }
     }
 
-    fn map_span<T2>(self, func: impl Fn(T, Span) -> T2) -> impl Parser<T2>
+    fn map_span<T2>(self, func: impl Fn(T, Span<I>) -> T2) -> impl Parser<T2, I>
     where
         Self: Sized,
     {
-        move |cursor: &mut Cursor, required: bool| {
-            let start = cursor.pos;
-            let val = self.parse(cursor, required)?;
-            Ok(func(val, cursor.span_from(start)))
+        MapSpan {
+            parser: self,
+            func,
+            output: PhantomData,
         }
     }
 
-    /*=========*
-     * Sharing *
-     *=========*/
+    /*============*
+     * Whitespace *
+     *============*/
 
-    fn refn<'a>(&'a self) -> impl Parser<T> + Copy + 'a
+    /// Skip whitespace and comments (as given to [`parse_with_skip`]) before running this
+    /// parser. Put it on the outside of `substr`, `span`, etc., so that they don't include the
+    /// skipped text.
+    fn token(self) -> impl Parser<T, I>
     where
         Self: Sized,
     {
-        impl<'a, P> Clone for Refn<'a, P> {
-            fn clone(&self) -> Refn<'a, P> {
-                Refn(self.0)
-            }
+        Token { parser: self }
+    }
+
+    /*================*
+     * Error Recovery *
+     *================*/
+
+    /// If this parser fails, record the error and skip ahead to where `sync` matches (without
+    /// consuming it), or to the end of the input. Then carry on as if this parser had
+    /// succeeded, returning `None`. Use [`parse_all_errors`] to see every error.
+    ///
+    /// This recovers even when the parser isn't required, so use it where there are no other
+    /// alternatives, such as on each item of a list. Include the list's closing delimiter in
+    /// `sync`: if nothing would be skipped, this fails as usual instead of recovering.
+    fn recover_until<T2>(self, sync: impl Parser<T2, I>) -> impl Parser<Option<T>, I>
+    where
+        Self: Sized,
+    {
+        RecoverUntil {
+            parser: self,
+            sync,
+            sync_output: PhantomData,
         }
+    }
 
-        impl<'a, P> Copy for Refn<'a, P> {}
+    /// If this parser fails with an error, record it and carry on from where the error was,
+    /// returning `default`. For example, `string(";").recover_with(())` reports a missing
+    /// semicolon but parses as if it were there. Use [`parse_all_errors`] to see every error.
+    fn recover_with(self, default: T) -> impl Parser<T, I>
+    where
+        Self: Sized,
+        T: Clone,
+    {
+        RecoverWith {
+            parser: self,
+            default,
+        }
+    }
 
-        impl<'a, T, P> Parser<T> for Refn<'a, P>
-        where
-            P: Parser<T>,
-        {
-            fn parse(&self, cursor: &mut Cursor, required: bool) -> Result<T, Option<ParseError>> {
-                self.0.parse(cursor, required)
-            }
+    /*=============*
+     * Memoization *
+     *=============*/
+
+    /// Cache this parser's result at each position, so that backtracking never runs it twice
+    /// at the same place. This makes parsing linear time for grammars where every
+    /// backtracking parser is memoized, at the cost of memory for the cache.
+    ///
+    /// A memoized parser may also be left recursive: if it calls itself at the same position,
+    /// the inner call first fails, and then the parser is re-run with the previous result as
+    /// the inner call's, for as long as that makes the match longer. For indirect left
+    /// recursion, memoize only one parser in the cycle.
+    fn memo(self) -> impl Parser<T, I>
+    where
+        Self: Sized,
+        T: Clone + 'static,
+    {
+        static NEXT_ID: AtomicUsize = AtomicUsize::new(0);
+        Memo {
+            parser: self,
+            id: NEXT_ID.fetch_add(1, Ordering::Relaxed),
         }
+    }
+
+    /*=========*
+     * Sharing *
+     *=========*/
 
+    fn refn<'a>(&'a self) -> impl Parser<T, I> + Copy + 'a
+    where
+        Self: Sized,
+    {
         Refn(self)
     }
 
@@ -273,84 +497,60 @@ pub trait Parser<T> {
      * Repetition *
      *============*/
 
-    fn opt(self) -> impl Parser<Option<T>>
+    fn opt(self) -> impl Parser<Option<T>, I>
     where
         Self: Sized,
     {
-        move |cursor: &mut Cursor, _required: bool| match self.parse(cursor, false) {
-            Ok(succ) => Ok(Some(succ)),
-            Err(None) => Ok(None),
-            Err(err) => Err(err),
-        }
+        Opt { parser: self }
     }
 
-    fn many0(self) -> impl Parser<Vec<T>>
+    fn many0(self) -> impl Parser<Vec<T>, I>
     where
         Self: Sized,
     {
-        move |cursor: &mut Cursor, _required: bool| {
-            let mut succs = Vec::new();
-            repeat!(succ = self.parse(cursor, false) => {
-                succs.push(succ);
-            });
-            Ok(succs)
-        }
+        Many0 { parser: self }
     }
 
-    fn many1(self) -> impl Parser<Vec<T>>
+    fn many1(self) -> impl Parser<Vec<T>, I>
     where
         Self: Sized + 'static,
     {
-        move |cursor: &mut Cursor, required: bool| {
-            let first_succ = self.parse(cursor, required)?;
-            let mut succs = vec![first_succ];
-            repeat!(succ = self.parse(cursor, false) => {
-                succs.push(succ);
-            });
-            Ok(succs)
-        }
+        Many1 { parser: self }
     }
 
-    fn fold<T2>(self, parser: impl Parser<T2>, combine: impl Fn(T, T2) -> T) -> impl Parser<T>
+    fn fold<T2>(self, parser: impl Parser<T2, I>, combine: impl Fn(T, T2) -> T) -> impl Parser<T, I>
     where
         Self: Sized,
     {
-        move |cursor: &mut Cursor, required: bool| {
-            let mut result = self.parse(cursor, required)?;
-            repeat!(succ = parser.parse(cursor, false) => {
-                result = combine(result, succ);
-            });
-            Ok(result)
+        Fold {
+            first: self,
+            rest: parser,
+            combine,
+            rest_output: PhantomData,
         }
     }
 
-    fn many_sep0<T2>(self, sep: impl Parser<T2>) -> impl Parser<Vec<T>>
+    fn many_sep0<T2>(self, sep: impl Parser<T2, I>) -> impl Parser<Vec<T>, I>
     where
         Self: Sized,
     {
-        move |cursor: &mut Cursor, _required: bool| {
-            let first_succ = self.parse(cursor, false)?;
-            let mut succs = vec![first_succ];
-            let elem_parser = sep.refn().cut(self.refn()).map(|(_, x)| x);
-            repeat!(succ = elem_parser.parse(cursor, false) => {
-                succs.push(succ);
-            });
-            Ok(succs)
+        ManySep {
+            elem: self,
+            sep,
+            at_least_one: false,
+            sep_output: PhantomData,
         }
     }
 
-    fn many_sep1<T2>(self, sep: impl Parser<T2>) -> impl Parser<Vec<T>>
+    fn many_sep1<T2>(self, sep: impl Parser<T2, I>) -> impl Parser<Vec<T>, I>
     where
-        Self: Sized + Clone,
+        Self: Sized,
     {
-        let elem_parser = sep.cut(self.clone()).map(|(_, x)| x);
-        move |cursor: &mut Cursor, required: bool| {
-            let first_succ = self.parse(cursor, required)?;
-            let mut succs = vec![first_succ];
-            repeat!(succ = elem_parser.parse(cursor, false) => {
-                succs.push(succ);
-            });
-            Ok(succs)
+        ManySep {
+            elem: self,
+            sep,
+            at_least_one: true,
+            sep_output: PhantomData,
         }
     }
 }
@@ -359,11 +559,11 @@ pub trait Parser<T> {
  * Parsers *
  *=========*/
 
-impl<T, F> Parser<T> for F
+impl<T, I: Input, F> Parser<T, I> for F
 where
-    F: Fn(&mut Cursor, bool) -> Result<T, Option<ParseError>>,
+    F: Fn(&mut Cursor<I>, bool) -> Result<T, Option<ParseError>>,
 {
-    fn parse(&self, cursor: &mut Cursor, required: bool) -> Result<T, Option<ParseError>> {
+    fn parse(&self, cursor: &mut Cursor<I>, required: bool) -> Result<T, Option<ParseError>> {
         self(cursor, required)
     }
 }
@@ -372,161 +572,82 @@ where
  * Lexemes *
  *=========*/
 
-pub fn nothing() -> impl Parser<()> {
-    move |_cursor: &mut Cursor, _required: bool| Ok(())
+pub fn nothing<I: Input>() -> impl Parser<(), I> {
+    described(
+        || Grammar::Empty,
+        move |_cursor: &mut Cursor<I>, _required: bool| Ok(()),
+    )
 }
 
 pub fn string(expected: &str) -> impl Parser<()> {
     let delta_pos = Pos::delta(expected);
+    let label = format!("`{expected}`");
+    let grammar = Grammar::Literal(expected.to_owned());
     let expected = expected.to_owned();
-    move |cursor: &mut Cursor, required: bool| {
-        if cursor.consume_str(&expected, delta_pos) {
-            Ok(())
-        } else if required {
-            Err(Some(cursor.error(format!("expected {expected}"))))
-        } else {
-            Err(None)
-        }
-    }
+    described(
+        move || grammar.clone(),
+        move |cursor: &mut Cursor, required: bool| {
+            if cursor.consume_str(&expected, delta_pos) {
+                return Ok(());
+            }
+            cursor.expected(&label);
+            if required {
+                Err(Some(cursor.expected_error()))
+            } else {
+                Err(None)
+            }
+        },
+    )
 }
 
 pub fn regex(label: &str, regex_str: &str) -> Result<impl Parser<()>, RegexError> {
     let regex = new_regex(regex_str)?;
     let label = label.to_owned();
-    Ok(move |cursor: &mut Cursor, required: bool| {
-        if cursor.consume_regex(&regex) {
-            Ok(())
-        } else if required {
-            Err(Some(cursor.error(format!("expected {label}"))))
-        } else {
-            Err(None)
-        }
-    })
+    let grammar = Grammar::Label(label.clone());
+    Ok(described(
+        move || grammar.clone(),
+        move |cursor: &mut Cursor, required: bool| {
+            if cursor.consume_regex(&regex) {
+                return Ok(());
+            }
+            cursor.expected(&label);
+            if required {
+                Err(Some(cursor.expected_error()))
+            } else {
+                Err(None)
+            }
+        },
+    ))
 }
 
 /*========*
  * Choice *
  *========*/
 
-pub fn alt<T>(label: &str, options: impl AltTuple<T>) -> impl Parser<T> {
+pub fn alt<T, I: Input>(label: &str, options: impl AltTuple<T, I>) -> impl Parser<T, I> {
     options.make_alt(label.to_owned())
 }
 
-pub fn alt_longest<T>(label: &str, options: impl AltTuple<T>) -> impl Parser<T> {
+pub fn alt_longest<T, I: Input>(label: &str, options: impl AltTuple<T, I>) -> impl Parser<T, I> {
     options.make_alt_longest(label.to_owned())
 }
 
-pub trait AltTuple<T> {
-    fn make_alt(self, label: String) -> impl Parser<T>;
-    fn make_alt_longest(self, label: String) -> impl Parser<T>;
+pub trait AltTuple<T, I: Input = Text> {
+    fn make_alt(self, label: String) -> impl Parser<T, I>;
+    fn make_alt_longest(self, label: String) -> impl Parser<T, I>;
 }
 
-macro_rules! define_alt {
-    ($struct:ident, $type:ident, $( ($idx:tt, $parser:ident) ),*) => {
-        impl<$type, $( $parser ),*> AltTuple<$type> for ($( $parser ),*)
-        where $( $parser : Parser<$type> ),* {
-            fn make_alt(self, label: String) -> impl Parser<T> {
-                move |cursor: &mut Cursor, required: bool| {
-                    let start = cursor.pos;
-                    $(
-                        match self.$idx.parse(cursor, false) {
-                            Ok(succ) => return Ok(succ),
-                            Err(Some(err)) => return Err(Some(err)),
-                            Err(None) => cursor.pos = start,
-                        }
-                    )*
-                    if required {
-                        Err(Some(cursor.error(format!("expected {label}"))))
-                    } else {
-                        Err(None)
-                    }
-                }
-            }
-
-            fn make_alt_longest(self, label: String) -> impl Parser<T> {
-                move |cursor: &mut Cursor, required: bool| {
-                    let start = cursor.pos;
-                    let mut best = None;
-                    $(
-                        match self.$idx.parse(cursor, false) {
-                            Ok(succ) => {
-                                let len = cursor.pos.offset - start.offset;
-                                if let Some((_, best_len, _)) = &best {
-                                    if len > *best_len {
-                                        best = Some((succ, len, cursor.pos));
-                                    }
-                                } else {
-                                    best = Some((succ, len, cursor.pos));
-                                }
-                                cursor.pos = start;
-                            }
-                            Err(Some(err)) => return Err(Some(err)),
-                            Err(None) => cursor.pos = start,
-                        }
-                    )*
-                    if let Some((succ, _, end_pos)) = best {
-                        cursor.pos = end_pos;
-                        Ok(succ)
-                    } else if required {
-                        Err(Some(cursor.error(format!("expected {label}"))))
-                    } else {
-                        Err(None)
-                    }
-                }
-            }
-        }
-    }
-}
-
-define_alt!(AltTuple2, T, (0, P0), (1, P1));
-define_alt!(AltTuple3, T, (0, P0), (1, P1), (2, P2));
-define_alt!(AltTuple4, T, (0, P0), (1, P1), (2, P2), (3, P3));
-define_alt!(AltTuple5, T, (0, P0), (1, P1), (2, P2), (3, P3), (4, P4));
-define_alt!(
-    AltTuple6,
-    T,
-    (0, P0),
-    (1, P1),
-    (2, P2),
-    (3, P3),
-    (4, P4),
-    (5, P5)
-);
-define_alt!(
-    AltTuple7,
-    T,
-    (0, P0),
-    (1, P1),
-    (2, P2),
-    (3, P3),
-    (4, P4),
-    (5, P5),
-    (6, P6)
-);
-define_alt!(
-    AltTuple8,
-    T,
-    (0, P0),
-    (1, P1),
-    (2, P2),
-    (3, P3),
-    (4, P4),
-    (5, P5),
-    (6, P6),
-    (7, P7)
-);
-
 /*==========*
  * Sequence *
  *==========*/
 
 macro_rules! define_seq {
     ($( ($var:ident, $idx:tt, $type:ident, $parser:ident) ),*) => {
-        impl<$( $type, $parser ),*> Parser<($( $type ),*)> for ($( $parser ),*)
-        where $( $parser : Parser<$type>),* {
+        impl<I: Input, $( $type, $parser ),*> Parser<($( $type ),*), I> for ($( $parser ),*)
+        where $( $parser : Parser<$type, I>),* {
             fn parse(
                 &self,
-                cursor: &mut Cursor,
+                cursor: &mut Cursor<I>,
                 required: bool
             ) -> Result<($( $type ),*), Option<ParseError>> {
                 $(
@@ -534,6 +655,10 @@ macro_rules! define_seq {
                 )*
                 Ok(($( $var ),*))
             }
+
+            fn grammar(&self) -> Grammar {
+                Grammar::seq([$( self.$idx.grammar() ),*])
+            }
         }
     };
 }
@@ -1,37 +1,67 @@
+use crate::input::Input;
 use crate::pos::Pos;
 use std::fmt;
 
-#[derive(Debug, PartialEq, Eq)]
+#[derive(Debug, Clone, PartialEq, Eq)]
 pub struct ParseError {
     filename: String,
     message: String,
+    // Boxed slices rather than `Vec` and `String`, to keep `Result<T, Option<ParseError>>` (the
+    // return type of every parser) small enough that clippy doesn't flag it as too large.
+    expected: Box<[String]>,
     start: Pos,
     end: Option<Pos>,
-    line_contents: String,
+    line_contents: Box<str>,
 }
 
 impl ParseError {
-    pub(crate) fn new(
+    pub(crate) fn new<I: Input>(
         filename: String,
-        source: &str,
+        source: &I::Source<'_>,
         message: String,
         start: Pos,
         end: Option<Pos>,
     ) -> ParseError {
-        let line_contents = match source.lines().nth(start.line as usize) {
-            Some(line) => line.to_owned(),
-            None => "".to_owned(),
-        };
+        let line_contents = I::line_contents(source, start).into_boxed_str();
 
         ParseError {
             filename,
             message,
+            expected: Box::new([]),
             start,
             end,
             line_contents,
         }
     }
 
+    /// An error saying that one of `expected` was expected at `pos`.
+    pub(crate) fn new_expected<I: Input>(
+        filename: String,
+        source: &I::Source<'_>,
+        expected: Vec<String>,
+        pos: Pos,
+    ) -> ParseError {
+        let message = match expected.as_slice() {
+            [] => "unexpected input".to_owned(),
+            [expected] => format!("expected {}", expected),
+            expected => format!("expected one of: {}", expected.join(", ")),
+        };
+        let mut error = ParseError::new::<I>(filename, source, message, pos, None);
+        error.expected = expected.into_boxed_slice();
+        error
+    }
+
+    /// The labels of what was expected where parsing failed: names like "number" for regexes
+    /// and `alt`s, and quoted strings like "`[`". Empty for errors with a custom message.
+    pub fn expected(&self) -> &[String] {
+        &self.expected
+    }
+
+    /// Where the error is.
+    pub fn start(&self) -> Pos {
+        self.start
+    }
+
     pub(crate) fn map(mut self, func: impl Fn(String) -> String) -> ParseError {
         self.message = func(self.message);
         self
@@ -118,9 +148,11 @@ impl std::error::Error for ParseError {}
 
 #[test]
 fn test_parse_errors() {
+    use crate::input::Text;
+
     colored::control::set_override(false);
 
-    let error = ParseError::new(
+    let error = ParseError::new::<Text>(
         "<test>".to_owned(),
         "123\n456\n7@9\n123",
         "bad number".to_owned(),
@@ -138,7 +170,7 @@ fn test_parse_errors() {
   |"
     );
 
-    let error = ParseError::new(
+    let error = ParseError::new::<Text>(
         "<test>".to_owned(),
         "123\n456\n7@9\n123",
         "bad number".to_owned(),
@@ -156,7 +188,7 @@ fn test_parse_errors() {
   |"
     );
 
-    let error = ParseError::new(
+    let error = ParseError::new::<Text>(
         "<test>".to_owned(),
         "123\n456\n7@9\n123",
         "bad stuff".to_owned(),
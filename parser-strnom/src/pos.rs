@@ -1,4 +1,6 @@
+use crate::input::{Input, Text};
 use std::fmt;
+use std::hash::{Hash, Hasher};
 use std::ops::Add;
 
 pub type Offset = usize;
@@ -6,19 +8,56 @@ pub type Line = u32;
 pub type Col = u32;
 
 /// A region of the input text, provided by method [`Parser::span`] and friends.
-#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
-pub struct Span<'s> {
+///
+/// [`Parser::span`]: crate::Parser::span
+pub struct Span<'s, I: Input = Text> {
     /// The input text from `start` to `end`.
-    pub substr: &'s str,
+    pub substr: &'s I::Source<'s>,
     /// The start of the span, just before its first character.
     pub start: Pos,
     /// The end of the span, just after its last character.
     pub end: Pos,
 }
 
+// These can't be derived, because they'd require `I` (rather than `I::Source`) to implement them.
+
+impl<'s, I: Input> fmt::Debug for Span<'s, I> {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.debug_struct("Span")
+            .field("substr", &self.substr)
+            .field("start", &self.start)
+            .field("end", &self.end)
+            .finish()
+    }
+}
+
+impl<'s, I: Input> Clone for Span<'s, I> {
+    fn clone(&self) -> Self {
+        *self
+    }
+}
+
+impl<'s, I: Input> Copy for Span<'s, I> {}
+
+impl<'s, I: Input> PartialEq for Span<'s, I> {
+    fn eq(&self, other: &Self) -> bool {
+        self.substr == other.substr && self.start == other.start && self.end == other.end
+    }
+}
+
+impl<'s, I: Input> Eq for Span<'s, I> {}
+
+impl<'s, I: Input> Hash for Span<'s, I> {
+    fn hash<H: Hasher>(&self, state: &mut H) {
+        self.substr.hash(state);
+        self.start.hash(state);
+        self.end.hash(state);
+    }
+}
+
 /// A position in the input text, _between_ two characters (or at the
 /// start or end of a line). For example, "xyz" has 4 possible positions.
-#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash)]
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash, Default)]
 pub struct Pos {
     /// Byte offset from the beginning of the source string.
     pub offset: Offset,
@@ -64,9 +103,9 @@ impl Add<Pos> for Pos {
             offset: self.offset + other.offset,
             line: self.line + other.line,
             col: if other.line == 0 {
-                other.col
-            } else {
                 self.col + other.col
+            } else {
+                other.col
             },
         }
     }
@@ -77,3 +116,13 @@ impl fmt::Display for Pos {
         write!(f, "{}:{}", self.line, self.col)
     }
 }
+
+#[test]
+fn test_pos_add() {
+    // Adding a delta on the same line moves the column; a delta with a newline resets it.
+    let start = Pos::delta("ab\ncd");
+    assert_eq!(start + Pos::delta("ef"), Pos::delta("ab\ncdef"));
+    assert_eq!(start + Pos::delta("e\nf"), Pos::delta("ab\ncde\nf"));
+    assert_eq!(start + Pos::delta(""), start);
+    assert_eq!(Pos::new() + start, start);
+}
@@ -0,0 +1,333 @@
+//! Drawing railroad diagrams as SVG.
+//!
+//! Every diagram has a horizontal line through it, which it's entered from on the left and
+//! exited from on the right. Its size is its width, and its height above and below that line.
+
+use crate::grammar::Grammar;
+use std::fmt::Write;
+
+/// The radius of the curves where lines branch.
+const R: f64 = 10.0;
+/// The space between the elements of a sequence.
+const GAP: f64 = 10.0;
+/// The vertical space between branches.
+const V_GAP: f64 = 8.0;
+/// Half the height of a box.
+const BOX_HALF_HEIGHT: f64 = 11.0;
+/// The approximate width of a character, in a 14px monospace font.
+const CHAR_WIDTH: f64 = 8.5;
+/// The space around each rule's diagram.
+const MARGIN: f64 = 20.0;
+/// The height of each rule's title.
+const TITLE_HEIGHT: f64 = 16.0;
+
+const STYLE: &str = "
+    path { stroke: #333; stroke-width: 2; fill: none; }
+    rect { stroke: #333; stroke-width: 2; fill: #f4f4ff; }
+    rect.literal { fill: #fffbe0; }
+    rect.opaque { stroke-dasharray: 4 2; }
+    text { font: 14px monospace; text-anchor: middle; }
+    text.label { font-style: italic; }
+    text.title { font-weight: bold; text-anchor: start; }
+";
+
+enum Diagram {
+    Box {
+        text: String,
+        kind: BoxKind,
+    },
+    Seq(Vec<Diagram>),
+    /// The first option is on the line, and the rest are below it.
+    Choice(Vec<Diagram>),
+    /// One or more `item`s, with a `sep` on the way back between each.
+    Loop {
+        item: Box<Diagram>,
+        sep: Box<Diagram>,
+    },
+}
+
+#[derive(Clone, Copy)]
+enum BoxKind {
+    Literal,
+    Label,
+    Rule,
+    Opaque,
+}
+
+impl Diagram {
+    fn new(grammar: &Grammar) -> Diagram {
+        use Grammar::*;
+
+        let skip = || Diagram::Seq(Vec::new());
+        let boxed = |text: &str, kind: BoxKind| Diagram::Box {
+            text: text.to_owned(),
+            kind,
+        };
+        let one_or_more = |item: &Grammar, sep: Diagram| Diagram::Loop {
+            item: Box::new(Diagram::new(item)),
+            sep: Box::new(sep),
+        };
+        match grammar {
+            Empty => skip(),
+            Literal(s) => boxed(s, BoxKind::Literal),
+            Label(label) => boxed(label, BoxKind::Label),
+            Rule { name, .. } => boxed(name, BoxKind::Rule),
+            Opaque => boxed("?", BoxKind::Opaque),
+            Seq(elems) => Diagram::Seq(elems.iter().map(Diagram::new).collect()),
+            Alt { options, .. } if options.is_empty() => skip(),
+            Alt { options, .. } => Diagram::Choice(options.iter().map(Diagram::new).collect()),
+            Opt(g) => Diagram::Choice(vec![skip(), Diagram::new(g)]),
+            Many0(g) => Diagram::Choice(vec![skip(), one_or_more(g, skip())]),
+            Many1(g) => one_or_more(g, skip()),
+            SepBy {
+                elem,
+                sep,
+                at_least_one,
+            } => {
+                let items = one_or_more(elem, Diagram::new(sep));
+                if *at_least_one {
+                    items
+                } else {
+                    Diagram::Choice(vec![skip(), items])
+                }
+            }
+        }
+    }
+
+    /// The width, and the height above and below the line.
+    fn size(&self) -> (f64, f64, f64) {
+        match self {
+            Diagram::Box { text, .. } => (box_width(text), BOX_HALF_HEIGHT, BOX_HALF_HEIGHT),
+            Diagram::Seq(elems) => {
+                let sizes = elems.iter().map(Diagram::size).collect::<Vec<_>>();
+                let gaps = GAP * elems.len().saturating_sub(1) as f64;
+                let width = sizes.iter().map(|s| s.0).sum::<f64>() + gaps;
+                let up = sizes.iter().map(|s| s.1).fold(0.0, f64::max);
+                let down = sizes.iter().map(|s| s.2).fold(0.0, f64::max);
+                (width, up, down)
+            }
+            Diagram::Choice(options) => {
+                let (width, ys) = choice_layout(options);
+                let (_, up, _) = options[0].size();
+                let (_, _, last_down) = options[options.len() - 1].size();
+                (width + 4.0 * R, up, ys[ys.len() - 1] + last_down)
+            }
+            Diagram::Loop { item, sep } => {
+                let (width, sep_y) = loop_layout(item, sep);
+                let (_, up, _) = item.size();
+                let (_, _, sep_down) = sep.size();
+                (width + 2.0 * R, up, sep_y + sep_down)
+            }
+        }
+    }
+
+    /// Draw the diagram with its line entering at `(x, y)`, and extend the line to `x + width`.
+    fn draw(&self, svg: &mut String, x: f64, y: f64, width: f64) {
+        let (own_width, _, _) = self.size();
+        match self {
+            Diagram::Box { text, kind } => {
+                let (class, rx) = match kind {
+                    BoxKind::Literal => ("literal", R),
+                    BoxKind::Label => ("label", R),
+                    BoxKind::Rule => ("rule", 0.0),
+                    BoxKind::Opaque => ("opaque", 0.0),
+                };
+                writeln!(
+                    svg,
+                    r#"<rect class="{}" x="{}" y="{}" width="{}" height="{}" rx="{}"/>"#,
+                    class,
+                    x,
+                    y - BOX_HALF_HEIGHT,
+                    own_width,
+                    2.0 * BOX_HALF_HEIGHT,
+                    rx
+                )
+                .unwrap();
+                writeln!(
+                    svg,
+                    r#"<text class="{}" x="{}" y="{}">{}</text>"#,
+                    class,
+                    x + own_width / 2.0,
+                    y + 5.0,
+                    escape(text)
+                )
+                .unwrap();
+            }
+            Diagram::Seq(elems) => {
+                let mut x = x;
+                for (i, elem) in elems.iter().enumerate() {
+                    if i > 0 {
+                        line(svg, x, y, x + GAP);
+                        x += GAP;
+                    }
+                    let (elem_width, _, _) = elem.size();
+                    elem.draw(svg, x, y, elem_width);
+                    x += elem_width;
+                }
+            }
+            Diagram::Choice(options) => {
+                let (inner_width, ys) = choice_layout(options);
+                let left = x + 2.0 * R;
+                let right = left + inner_width;
+                for (i, (option, option_y)) in options.iter().zip(ys).enumerate() {
+                    if i == 0 {
+                        line(svg, x, y, left);
+                        line(svg, right, y, right + 2.0 * R);
+                    } else {
+                        let y = y + option_y;
+                        writeln!(
+                            svg,
+                            r#"<path d="M{x} {y0} a{R} {R} 0 0 1 {R} {R} V{y1} a{R} {R} 0 0 0 {R} {R}"/>"#,
+                            x = x,
+                            y0 = y - option_y,
+                            y1 = y - R,
+                        )
+                        .unwrap();
+                        writeln!(
+                            svg,
+                            r#"<path d="M{right} {y} a{R} {R} 0 0 0 {R} -{R} V{y1} a{R} {R} 0 0 1 {R} -{R}"/>"#,
+                            right = right,
+                            y = y,
+                            y1 = y - option_y + R,
+                        )
+                        .unwrap();
+                    }
+                    option.draw(svg, left, y + option_y, inner_width);
+                }
+            }
+            Diagram::Loop { item, sep } => {
+                let (inner_width, sep_y) = loop_layout(item, sep);
+                let left = x + R;
+                let right = left + inner_width;
+                let sep_y = y + sep_y;
+                line(svg, x, y, left);
+                item.draw(svg, left, y, inner_width);
+                line(svg, right, y, right + R);
+                writeln!(
+                    svg,
+                    r#"<path d="M{right} {y} a{R} {R} 0 0 1 {R} {R} V{y1} a{R} {R} 0 0 1 -{R} {R}"/>"#,
+                    right = right,
+                    y = y,
+                    y1 = sep_y - R,
+                )
+                .unwrap();
+                writeln!(
+                    svg,
+                    r#"<path d="M{left} {sep_y} a{R} {R} 0 0 1 -{R} -{R} V{y1} a{R} {R} 0 0 1 {R} -{R}"/>"#,
+                    left = left,
+                    sep_y = sep_y,
+                    y1 = y + R,
+                )
+                .unwrap();
+                sep.draw(svg, left, sep_y, inner_width);
+            }
+        }
+        line(svg, x + own_width, y, x + width);
+    }
+}
+
+/// The width of the widest option, and each option's line's height below the first's.
+fn choice_layout(options: &[Diagram]) -> (f64, Vec<f64>) {
+    let mut width: f64 = 0.0;
+    let mut ys = Vec::new();
+    let mut y: f64 = 0.0;
+    let mut prev_down: Option<f64> = None;
+    for option in options {
+        let (option_width, up, down) = option.size();
+        width = width.max(option_width);
+        if let Some(prev_down) = prev_down {
+            // The curves need to fit between the first option and each of the others.
+            y = (y + prev_down + V_GAP + up).max(2.0 * R);
+        }
+        ys.push(y);
+        prev_down = Some(down);
+    }
+    (width, ys)
+}
+
+/// The width of the wider of `item` and `sep`, and the height of `sep`'s line below `item`'s.
+fn loop_layout(item: &Diagram, sep: &Diagram) -> (f64, f64) {
+    let (item_width, _, item_down) = item.size();
+    let (sep_width, sep_up, _) = sep.size();
+    let sep_y = (item_down + V_GAP + sep_up).max(2.0 * R);
+    (item_width.max(sep_width), sep_y)
+}
+
+fn box_width(text: &str) -> f64 {
+    text.chars().count() as f64 * CHAR_WIDTH + 2.0 * R
+}
+
+fn line(svg: &mut String, x0: f64, y: f64, x1: f64) {
+    if x1 > x0 {
+        writeln!(svg, r#"<path d="M{} {} H{}"/>"#, x0, y, x1).unwrap();
+    }
+}
+
+fn escape(text: &str) -> String {
+    text.replace('&', "&amp;")
+        .replace('<', "&lt;")
+        .replace('>', "&gt;")
+}
+
+/// Draw each rule's diagram under its name, one above the other.
+pub(crate) fn to_svg(rules: &[(String, Grammar)]) -> String {
+    let mut body = String::new();
+    let mut top = 0.0;
+    let mut max_width: f64 = 0.0;
+    for (name, definition) in rules {
+        let diagram = Diagram::new(definition);
+        let (width, up, down) = diagram.size();
+        let title_y = top + MARGIN;
+        writeln!(
+            body,
+            r#"<text class="title" x="{}" y="{}">{}</text>"#,
+            MARGIN,
+            title_y,
+            escape(name)
+        )
+        .unwrap();
+
+        // Start and end with a short line and a bar across it.
+        let y = title_y + TITLE_HEIGHT + up;
+        let x = MARGIN + GAP;
+        writeln!(
+            body,
+            r#"<path d="M{} {} v{} M{} {} H{}"/>"#,
+            MARGIN,
+            y - BOX_HALF_HEIGHT / 2.0,
+            BOX_HALF_HEIGHT,
+            MARGIN,
+            y,
+            x
+        )
+        .unwrap();
+        diagram.draw(&mut body, x, y, width);
+        writeln!(
+            body,
+            r#"<path d="M{} {} h{} m0 {} v{}"/>"#,
+            x + width,
+            y,
+            GAP,
+            -BOX_HALF_HEIGHT / 2.0,
+            BOX_HALF_HEIGHT
+        )
+        .unwrap();
+
+        top = y + down;
+        max_width = max_width.max(x + width + GAP + MARGIN);
+    }
+    let height = top + MARGIN;
+
+    let mut svg = String::new();
+    writeln!(
+        svg,
+        r#"<svg xmlns="http://www.w3.org/2000/svg" width="{w}" height="{h}" viewBox="0 0 {w} {h}">"#,
+        w = max_width,
+        h = height
+    )
+    .unwrap();
+    writeln!(svg, "<style>{}</style>", STYLE).unwrap();
+    svg.push_str(&body);
+    svg.push_str("</svg>\n");
+    svg
+}
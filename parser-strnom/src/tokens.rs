@@ -0,0 +1,64 @@
+//! Parsers for a stream of lexemes from the `lexer` crate, of type [`Tokens`]. Lex the source,
+//! collect the lexemes, and [`parse`](crate::parse) the slice. The lexer has already removed
+//! whitespace, so there's no need for [`Parser::token`]. Error positions come from the
+//! lexemes.
+//!
+//! ```
+//! use lexer::LexerBuilder;
+//! use strnom::tokens::{text, token};
+//! use strnom::{parse, Parser};
+//!
+//! let mut builder = LexerBuilder::new("[ \t\n]+").unwrap();
+//! let tok_num = builder.regex("[0-9]+").unwrap();
+//! builder.string("+").unwrap();
+//! let lexer = builder.finish().unwrap();
+//!
+//! let number = token("number", tok_num).substr(|lexemes| lexemes[0].lexeme.parse().unwrap());
+//! let sum = number.refn().many_sep1(text("+")).map(|nums| nums.iter().sum::<u32>());
+//!
+//! let lexemes = lexer.lex("1 + 2 + 3").collect::<Vec<_>>();
+//! assert_eq!(parse("<example>", &lexemes, sum.refn()), Ok(6));
+//! ```
+
+use crate::{described, Cursor, Grammar, Parser, Tokens};
+use lexer::Token;
+
+/// Match one lexeme of the given token. The `label` (typically the token's name) is used in
+/// errors.
+pub fn token(label: &str, token: Token) -> impl Parser<(), Tokens> {
+    let label = label.to_owned();
+    let grammar = Grammar::Label(label.clone());
+    described(
+        move || grammar.clone(),
+        move |cursor: &mut Cursor<Tokens>, required: bool| match cursor.lexeme() {
+            Some(lexeme) if lexeme.token == token => {
+                cursor.advance(1);
+                Ok(())
+            }
+            _ => {
+                cursor.expected(&label);
+                Err(required.then(|| cursor.expected_error()))
+            }
+        },
+    )
+}
+
+/// Match one lexeme whose text is exactly `expected`, whatever its token.
+pub fn text(expected: &str) -> impl Parser<(), Tokens> {
+    let label = format!("`{expected}`");
+    let grammar = Grammar::Literal(expected.to_owned());
+    let expected = expected.to_owned();
+    described(
+        move || grammar.clone(),
+        move |cursor: &mut Cursor<Tokens>, required: bool| match cursor.lexeme() {
+            Some(lexeme) if lexeme.lexeme == expected => {
+                cursor.advance(1);
+                Ok(())
+            }
+            _ => {
+                cursor.expected(&label);
+                Err(required.then(|| cursor.expected_error()))
+            }
+        },
+    )
+}
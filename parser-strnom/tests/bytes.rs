@@ -0,0 +1,65 @@
+use strnom::bytes::{byte_if, regex, tag, take};
+use strnom::{parse, Bytes, Cursor, ParseError, Parser};
+
+/// A made-up format: a header, then records that are each a length byte followed by that many
+/// bytes, then a zero byte.
+fn archive() -> impl Parser<Vec<Vec<u8>>, Bytes> {
+    let length = byte_if("length", |b| b != 0);
+    let record = move |cursor: &mut Cursor<Bytes>, required: bool| {
+        let len = length.parse(cursor, required)?;
+        take(len as usize)
+            .substr(|bytes| bytes.to_vec())
+            .parse(cursor, true)
+    };
+    tag(b"\x89ARC")
+        .cut((record.many0(), tag(b"\0")))
+        .map(|(_, (records, _))| records)
+}
+
+fn parse_archive(source: &[u8]) -> Result<Vec<Vec<u8>>, ParseError> {
+    parse("<test>", source, archive())
+}
+
+#[test]
+fn test_bytes() {
+    assert_eq!(parse_archive(b"\x89ARC\0"), Ok(vec![]));
+    assert_eq!(
+        parse_archive(b"\x89ARC\x02hi\x03\xff\x00\x01\0"),
+        Ok(vec![b"hi".to_vec(), vec![0xff, 0x00, 0x01]])
+    );
+
+    let expected = |source: &[u8]| parse_archive(source).unwrap_err().expected().to_vec();
+    assert_eq!(expected(b"ARC\0"), ["`\\x89ARC`"]);
+    assert_eq!(expected(b"\x89ARC\x03hi"), ["3 bytes"]);
+    assert_eq!(expected(b"\x89ARC\x02hi"), ["length", "`\\x00`"]);
+    assert_eq!(expected(b"\x89ARC\0\0"), ["end of file"]);
+}
+
+#[test]
+fn test_bytes_regex() {
+    let header = regex("header", r"(?-u)\xCA\xFE[\x00-\x0F]+").unwrap();
+    let parser = header.span(|span| (span.substr.len(), span.end.offset));
+    assert_eq!(
+        parse("<test>", b"\xca\xfe\x01\x02", parser.refn()),
+        Ok((4, 4))
+    );
+    assert!(parse("<test>", b"\xca\xfe\x10", parser.refn()).is_err());
+    assert!(regex("bad", "(").is_err());
+}
+
+#[test]
+fn test_bytes_error_message() {
+    colored::control::set_override(false);
+
+    let source = b"\x89ARC\x05ab";
+    assert_eq!(
+        parse_archive(source).unwrap_err().to_string(),
+        "parse error: expected 5 bytes
+ --> <test>:1:6
+  |
+1 |.ARC.ab
+  |     ^
+  |     expected 5 bytes
+  |"
+    );
+}
@@ -0,0 +1,89 @@
+use strnom::{alt, parse, parse_with_skip, regex, string, Cursor, ParseError, Parser};
+
+thread_local! {
+    static VALUE: Box<dyn Parser<()>> = Box::new(value_parser());
+}
+
+fn value(cursor: &mut Cursor, required: bool) -> Result<(), Option<ParseError>> {
+    VALUE.with(|parser| parser.parse(cursor, required))
+}
+
+/// JSON-ish values, without objects
+fn value_parser() -> impl Parser<()> {
+    let number = regex("number", "[0-9]+").unwrap().token();
+    let string_lit = regex("string", r#""[^"]*""#).unwrap().token();
+    let keyword = alt("keyword", (string("true"), string("false"))).token();
+    let array = string("[")
+        .token()
+        .cut((value.many_sep0(string(",").token()), string("]").token()))
+        .constant(());
+    alt("value", (number, string_lit, keyword, array))
+}
+
+fn parse_value(source: &str) -> Result<(), ParseError> {
+    let whitespace = regex("whitespace", "[ \t\n]+").unwrap();
+    parse_with_skip("<test>", source, whitespace, value)
+}
+
+#[test]
+fn test_expected_sets() {
+    colored::control::set_override(false);
+
+    let expected = |source: &str| parse_value(source).unwrap_err().expected().to_vec();
+    assert_eq!(
+        expected(""),
+        ["number", "string", "`true`", "`false`", "`[`"]
+    );
+    assert_eq!(expected("[1 2]"), ["`,`", "`]`"]);
+    assert_eq!(
+        expected("[1, ]"),
+        ["number", "string", "`true`", "`false`", "`[`"]
+    );
+    assert_eq!(expected("[] []"), ["end of file"]);
+
+    assert_eq!(
+        parse_value("[1,\n [true, @]]").unwrap_err().to_string(),
+        "parse error: expected one of: number, string, `true`, `false`, `[`
+ --> <test>:2:9
+  |
+2 | [true, @]]
+  |        ^
+  |        expected one of: number, string, `true`, `false`, `[`
+  |"
+    );
+}
+
+#[test]
+fn test_expected_alt_label() {
+    // Parsers that don't say what they expect are described by the `alt`'s label.
+    let nothing = |_: &mut Cursor, _: bool| Err::<(), _>(None);
+    let parser = alt("digit or letter", (nothing, nothing));
+    let err = parse("<test>", "x", parser).unwrap_err();
+    assert_eq!(err.expected(), ["digit or letter"]);
+}
+
+#[test]
+fn test_expected_resolve_in_alt() {
+    colored::control::set_override(false);
+
+    // Once the number has matched, a failure to convert it is the error, even inside an `alt`.
+    let byte = regex("number", "[0-9]+")
+        .unwrap()
+        .substr(|s| s.parse::<u8>())
+        .resolve();
+    let parser = alt("value", (byte, string("x").constant(0)));
+    let parser = parser.refn();
+    let err = parse("<test>", "300", parser).unwrap_err();
+    assert_eq!(err.expected(), [] as [String; 0]);
+    assert_eq!(
+        err.to_string(),
+        "parse error: number too large to fit in target type
+ --> <test>:1:1
+  |
+1 |300
+  |^^^
+  |number too large to fit in target type
+  |"
+    );
+    assert_eq!(parse("<test>", "x", parser), Ok(0));
+}
@@ -0,0 +1,97 @@
+use strnom::{alt, expr_parser, parse_with_skip, regex, string, Assoc, Cursor, ParseError, Parser};
+
+thread_local! {
+    static EXPR: Box<dyn Parser<String>> = Box::new(expr());
+}
+
+fn nested_expr(cursor: &mut Cursor, required: bool) -> Result<String, Option<ParseError>> {
+    EXPR.with(|parser| parser.parse(cursor, required))
+}
+
+/// Parse an expression, showing its structure with parentheses.
+fn expr() -> impl Parser<String> {
+    let var = regex("variable", "[a-z]+")
+        .unwrap()
+        .substr(|s| s.to_owned())
+        .token();
+    let parens = string("(")
+        .token()
+        .cut((nested_expr, string(")").token()))
+        .map(|(_, (expr, _))| expr);
+    let atom = alt("atom", (var, parens));
+    let binop = |op: &'static str| string(op).token().constant(op);
+    expr_parser("expression", atom)
+        .infix(1, Assoc::Left, binop("+"), binary)
+        .infix(1, Assoc::Left, binop("-"), binary)
+        .infix(2, Assoc::Left, binop("*"), binary)
+        .infix(4, Assoc::Right, binop("^"), binary)
+        .prefix(3, binop("-"), |op, x| format!("({op}{x})"))
+        .postfix(5, binop("!"), |x, op| format!("({x}{op})"))
+        .postfix(
+            5,
+            string("[").token().cut((nested_expr, string("]").token())),
+            |x, (_, (index, _))| format!("{x}[{index}]"),
+        )
+}
+
+fn binary(x: String, op: &str, y: String) -> String {
+    format!("({x} {op} {y})")
+}
+
+fn parse_expr(source: &str) -> Result<String, ParseError> {
+    let whitespace = regex("whitespace", "[ \t\n]+").unwrap();
+    parse_with_skip("<test>", source, whitespace, nested_expr)
+}
+
+#[test]
+fn test_expr_parser() {
+    let ok = |source: &str, expected: &str| assert_eq!(parse_expr(source), Ok(expected.to_owned()));
+    ok("a", "a");
+    ok("a + b", "(a + b)");
+    ok("a - b + c - d", "(((a - b) + c) - d)");
+    ok("a + b * c", "(a + (b * c))");
+    ok("a * b + c", "((a * b) + c)");
+    ok("a ^ b ^ c", "(a ^ (b ^ c))");
+    ok("a * b ^ c * d", "((a * (b ^ c)) * d)");
+    ok("(a + b) * c", "((a + b) * c)");
+    ok("--a", "(-(-a))");
+    ok("-a * b", "((-a) * b)");
+    ok("-a ^ b", "(-(a ^ b))");
+    ok("a * -b", "(a * (-b))");
+    ok("a!", "(a!)");
+    ok("-a!!", "(-((a!)!))");
+    ok("a ^ b!", "(a ^ (b!))");
+    ok("a[b + c]!", "(a[(b + c)]!)");
+    ok("a - -b", "(a - (-b))");
+}
+
+#[test]
+fn test_expr_parser_errors() {
+    colored::control::set_override(false);
+
+    let message = |source: &str| {
+        let err = parse_expr(source).unwrap_err().to_string();
+        err.lines().next().unwrap().to_owned()
+    };
+    assert_eq!(
+        message(""),
+        "parse error: expected one of: `-`, variable, `(`"
+    );
+    assert_eq!(message("a +"), "parse error: expected expression after `+`");
+    assert_eq!(
+        message("a * -"),
+        "parse error: expected expression after `-`"
+    );
+    assert_eq!(
+        message("(a + )"),
+        "parse error: expected expression after `+`"
+    );
+    assert_eq!(
+        message("(a + b"),
+        "parse error: expected one of: `!`, `[`, `+`, `-`, `*`, `^`, `)`"
+    );
+    assert_eq!(
+        message("a b"),
+        "parse error: expected one of: `!`, `[`, `+`, `-`, `*`, `^`, end of file"
+    );
+}
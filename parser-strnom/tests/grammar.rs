@@ -0,0 +1,110 @@
+use strnom::{alt, expr_parser, regex, string, Assoc, Cursor, Grammar, ParseError, Parser};
+
+fn value(cursor: &mut Cursor, required: bool) -> Result<(), Option<ParseError>> {
+    value_parser().parse(cursor, required)
+}
+
+/// JSON values, ignoring whitespace.
+fn value_parser() -> impl Parser<()> {
+    let number = regex("number", "[0-9]+").unwrap();
+    let string_lit = regex("string", r#""[^"]*""#).unwrap();
+    let keyword = alt("keyword", (string("true"), string("false"), string("null")));
+    let array = string("[")
+        .cut((value.rule("value").many_sep0(string(",")), string("]")))
+        .constant(());
+    let member = regex("string", r#""[^"]*""#)
+        .unwrap()
+        .cut((string(":"), value.rule("value")))
+        .rule("member");
+    let object = (string("{"), member.many_sep0(string(",")), string("}")).constant(());
+    alt("value", (number, string_lit, keyword, array, object))
+}
+
+#[test]
+fn test_grammar_ebnf() {
+    assert_eq!(
+        value_parser().rule("value").grammar().to_ebnf(),
+        "\
+value = ? number ? | ? string ? | keyword | \"[\" , [ value , { \",\" , value } ] , \"]\" | \"{\" , [ member , { \",\" , member } ] , \"}\" ;
+keyword = \"true\" | \"false\" | \"null\" ;
+member = ? string ? , \":\" , value ;
+"
+    );
+}
+
+#[test]
+fn test_grammar_combinators() {
+    let a = || string("a");
+    let b = || regex("b", "b").unwrap();
+
+    // Unnamed grammars are called "start"; transparent combinators don't show up.
+    let parser = (a().opt(), b().many1(), a().map(|_| 1).many0());
+    assert_eq!(
+        parser.grammar().to_ebnf(),
+        "start = [ \"a\" ] , ? b ? , { ? b ? } , { \"a\" } ;\n"
+    );
+
+    let elem = a();
+    let parser = elem.refn().many_sep1(b()).fold(string("'"), |x, _| x);
+    assert_eq!(
+        parser.grammar().to_ebnf(),
+        "start = \"a\" , { ? b ? , \"a\" } , { \"'\" } ;\n"
+    );
+
+    // Nested choices need parentheses.
+    let parser = (alt("", (a(), b())), alt("", ((a(), b()).constant(()), a())));
+    assert_eq!(
+        parser.grammar().to_ebnf(),
+        "start = ( \"a\" | ? b ? ) , ( \"a\" , ? b ? | \"a\" ) ;\n"
+    );
+
+    // Different rules with the same name get numbered.
+    let parser = (
+        alt("x y", (a(), b())),
+        alt("x y", (b(), a())),
+        alt("x y", (a(), b())),
+    );
+    assert_eq!(
+        parser.grammar().to_ebnf(),
+        "\
+start = x_y , x_y_2 , x_y ;
+x_y = \"a\" | ? b ? ;
+x_y_2 = ? b ? | \"a\" ;
+"
+    );
+
+    let parser = a().cut(|_: &mut Cursor, _| Ok(()));
+    assert_eq!(
+        parser.grammar(),
+        Grammar::Seq(vec![Grammar::Literal("a".to_owned()), Grammar::Opaque])
+    );
+}
+
+#[test]
+fn test_grammar_expr() {
+    let var = regex("variable", "[a-z]+").unwrap();
+    let expr = expr_parser("expression", var)
+        .infix(1, Assoc::Left, string("+"), |_, _, _| ())
+        .infix(2, Assoc::Left, string("*"), |_, _, _| ())
+        .prefix(3, string("-"), |_, _| ())
+        .postfix(4, string("!"), |_, _| ());
+    assert_eq!(
+        expr.grammar().to_ebnf(),
+        "expression = { \"-\" } , ? variable ? , { \"!\" } , { ( \"+\" | \"*\" ) , { \"-\" } , ? variable ? , { \"!\" } } ;\n"
+    );
+}
+
+#[test]
+fn test_grammar_svg() {
+    let svg = value_parser().rule("value").grammar().to_svg();
+    assert!(svg.starts_with("<svg xmlns=\"http://www.w3.org/2000/svg\""));
+    assert!(svg.ends_with("</svg>\n"));
+    for rule in ["value", "keyword", "member"] {
+        assert!(svg.contains(&format!(">{}</text>", rule)));
+    }
+    assert!(svg.contains(r#"<text class="literal" x="#));
+    assert!(svg.contains(r#"<text class="label" x="#));
+
+    let svg = string("<&>").grammar().to_svg();
+    assert!(svg.contains(">&lt;&amp;&gt;</text>"));
+}
@@ -0,0 +1,77 @@
+use std::cell::Cell;
+use strnom::{alt, parse, regex, string, Cursor, ParseError, Parser};
+
+fn number() -> impl Parser<i64> {
+    regex("number", "[0-9]+")
+        .unwrap()
+        .substr(|s| s.parse().unwrap())
+}
+
+thread_local! {
+    static SUB: Box<dyn Parser<i64>> = Box::new(
+        alt(
+            "expression",
+            ((sub, string("-"), number()).map(|(x, _, y)| x - y), number()),
+        )
+        .memo(),
+    );
+    static CALLS: Cell<usize> = const { Cell::new(0) };
+    static NESTED: Box<dyn Parser<usize>> = Box::new(
+        (|cursor: &mut Cursor, required: bool| {
+            CALLS.with(|calls| calls.set(calls.get() + 1));
+            alt(
+                "nested",
+                (
+                    (string("a"), nested, string("b")).map(|(_, n, _)| n + 1),
+                    (string("a"), nested, string("c")).map(|(_, n, _)| n + 1),
+                    string("d").constant(0),
+                ),
+            )
+            .parse(cursor, required)
+        })
+        .memo(),
+    );
+}
+
+/// `sub = sub "-" number | number`, which is left recursive.
+fn sub(cursor: &mut Cursor, required: bool) -> Result<i64, Option<ParseError>> {
+    SUB.with(|parser| parser.parse(cursor, required))
+}
+
+/// `nested = "a" nested "b" | "a" nested "c" | "d"`, which takes exponential time to parse
+/// without memoization.
+fn nested(cursor: &mut Cursor, required: bool) -> Result<usize, Option<ParseError>> {
+    NESTED.with(|parser| parser.parse(cursor, required))
+}
+
+#[test]
+fn test_left_recursion() {
+    assert_eq!(parse("<test>", "7", sub), Ok(7));
+    assert_eq!(parse("<test>", "10-3", sub), Ok(7));
+    assert_eq!(parse("<test>", "10-3-2", sub), Ok(5));
+    assert_eq!(parse("<test>", "1-1-1-1-1-1-1-1-1-1", sub), Ok(-8));
+    assert!(parse("<test>", "10-3-", sub).is_err());
+    assert!(parse("<test>", "-3", sub).is_err());
+}
+
+#[test]
+fn test_memo_calls() {
+    let source = "a".repeat(20) + "d" + &"c".repeat(20);
+    CALLS.with(|calls| calls.set(0));
+    assert_eq!(parse("<test>", &source, nested), Ok(20));
+    // Once per position
+    assert_eq!(CALLS.with(|calls| calls.get()), 21);
+}
+
+#[test]
+fn test_memo_empty() {
+    let ident = regex("identifier", "[a-z]*")
+        .unwrap()
+        .substr(|s| s.to_owned());
+    let ident = ident.memo();
+    let twice = (ident.refn(), string(";"), ident.refn()).map(|(x, _, y)| (x, y));
+    assert_eq!(
+        parse("<test>", ";b", twice),
+        Ok(("".to_owned(), "b".to_owned()))
+    );
+}
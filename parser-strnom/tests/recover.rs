@@ -0,0 +1,80 @@
+use strnom::{parse_all_errors_with_skip, parse_with_skip, regex, string, ParseError, Parser};
+
+/// `let NAME = NUMBER;` statements, recovering from errors in each statement and from missing
+/// semicolons.
+fn program() -> impl Parser<Vec<Option<String>>> {
+    let ident = regex("identifier", "[a-z]+")
+        .unwrap()
+        .substr(|s| s.to_owned())
+        .token();
+    let number = regex("number", "[0-9]+").unwrap().token();
+    let stmt = string("let")
+        .token()
+        .cut((ident, string("=").token(), number))
+        .map(|(_, (name, _, _))| name);
+    stmt.recover_until(string(";").token())
+        .cut(string(";").token().recover_with(()))
+        .map(|(name, _)| name)
+        .many0()
+}
+
+fn parse_program(source: &str) -> (Option<Vec<Option<String>>>, Vec<ParseError>) {
+    let whitespace = regex("whitespace", "[ \t\n]+").unwrap();
+    parse_all_errors_with_skip("<test>", source, whitespace, program())
+}
+
+fn names(names: &[Option<&str>]) -> Option<Vec<Option<String>>> {
+    Some(names.iter().map(|name| name.map(str::to_owned)).collect())
+}
+
+fn error_summary(errors: &[ParseError]) -> Vec<(u32, Vec<String>)> {
+    errors
+        .iter()
+        .map(|err| (err.start().col, err.expected().to_vec()))
+        .collect()
+}
+
+#[test]
+fn test_recovery() {
+    let (result, errors) = parse_program("let a = 1; let b = 2;");
+    assert_eq!(result, names(&[Some("a"), Some("b")]));
+    assert!(errors.is_empty());
+
+    // Bad statement
+    let (result, errors) = parse_program("let a = ; let b = 2;");
+    assert_eq!(result, names(&[None, Some("b")]));
+    assert_eq!(error_summary(&errors), [(8, vec!["number".to_owned()])]);
+
+    // Missing semicolon
+    let (result, errors) = parse_program("let a = 1 let b = 2;");
+    assert_eq!(result, names(&[Some("a"), Some("b")]));
+    assert_eq!(error_summary(&errors), [(10, vec!["`;`".to_owned()])]);
+
+    // Garbage, and several errors
+    let (result, errors) = parse_program("let a = 1; @@@; let = 2; let c = 3");
+    assert_eq!(result, names(&[Some("a"), None, None, Some("c")]));
+    assert_eq!(
+        error_summary(&errors),
+        [
+            (11, vec!["`let`".to_owned()]),
+            (20, vec!["identifier".to_owned()]),
+            (34, vec!["`;`".to_owned()]),
+        ]
+    );
+
+    // Garbage at the end, with no semicolon to sync to
+    let (result, errors) = parse_program("let a = 1; @@@");
+    assert_eq!(result, names(&[Some("a"), None]));
+    assert_eq!(
+        error_summary(&errors),
+        [(11, vec!["`let`".to_owned()]), (14, vec!["`;`".to_owned()])]
+    );
+}
+
+#[test]
+fn test_recovery_first_error() {
+    // `parse` stops at the first error, even if the parser recovered from it.
+    let whitespace = regex("whitespace", "[ \t\n]+").unwrap();
+    let err = parse_with_skip("<test>", "let a = ; let = 2;", whitespace, program());
+    assert_eq!(err.unwrap_err().expected(), ["number"]);
+}
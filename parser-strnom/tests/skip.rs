@@ -0,0 +1,69 @@
+use strnom::{alt, parse, parse_with_skip, regex, string, Parser};
+
+#[test]
+fn test_skip() {
+    let whitespace = regex("whitespace", "[ \t\n]+").unwrap();
+    let line_comment = regex("comment", "//[^\n]*").unwrap();
+    let block_comment = string("/*").cut(regex("end of comment", r"(?s).*?\*/").unwrap());
+    let skip = alt(
+        "whitespace",
+        (whitespace, line_comment, block_comment.constant(())),
+    );
+
+    let ident = regex("identifier", "[a-z]+")
+        .unwrap()
+        .substr(|s| s.to_owned());
+    let list = string("(")
+        .token()
+        .cut((
+            ident.token().many_sep0(string(",").token()),
+            string(")").token(),
+        ))
+        .map(|(_, (idents, _))| idents);
+    let list = list.refn();
+
+    assert_eq!(
+        parse_with_skip("<test>", " ( a,b , /* c, */ d // e\n ) // end", skip, list),
+        Ok(vec!["a".to_owned(), "b".to_owned(), "d".to_owned()])
+    );
+    assert_eq!(
+        parse("<test>", "(a,b)", list),
+        Ok(vec!["a".to_owned(), "b".to_owned()])
+    );
+    assert!(parse("<test>", "(a, b)", list).is_err());
+}
+
+#[test]
+fn test_skip_errors() {
+    let skip = string("/*").cut(regex("end of comment", r"(?s).*?\*/").unwrap());
+    let skip = skip.constant(());
+    let parser = string("x").token().many0();
+
+    assert_eq!(
+        parse_with_skip("<test>", "x/* a */x/**/", skip.refn(), parser.refn()),
+        Ok(vec![(), ()])
+    );
+    let err = parse_with_skip("<test>", "x/* a", skip.refn(), parser.refn()).unwrap_err();
+    assert!(err.to_string().contains("expected end of comment"));
+}
+
+#[test]
+fn test_skip_span() {
+    let skip = regex("whitespace", "[ \t\n]+").unwrap().constant(());
+    let x = string("x").token();
+    let x = x.refn();
+    let parser = (x, string("y").token().opt(), x.many0())
+        .substr(|s| s.to_owned())
+        .token()
+        .many0();
+
+    // The spans stop at the last token matched, not at the whitespace after it.
+    assert_eq!(
+        parse_with_skip("<test>", " x y  x x ", skip.refn(), parser.refn()),
+        Ok(vec!["x y  x x".to_owned()])
+    );
+    assert_eq!(
+        parse_with_skip("<test>", "x  ", skip.refn(), parser.refn()),
+        Ok(vec!["x".to_owned()])
+    );
+}
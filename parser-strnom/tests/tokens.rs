@@ -0,0 +1,79 @@
+use lexer::{Lexeme, LexerBuilder, Token};
+use strnom::tokens::{text, token};
+use strnom::{expr_parser, parse, Assoc, ParseError, Parser, Tokens};
+
+struct Tokenizer {
+    lexer: lexer::Lexer,
+    num: Token,
+    var: Token,
+}
+
+fn tokenizer() -> Tokenizer {
+    let mut builder = LexerBuilder::new("[ \t\n]+").unwrap();
+    builder.skip("#[^\n]*").unwrap();
+    let num = builder.regex("[0-9]+").unwrap();
+    let var = builder.regex("[a-z]+").unwrap();
+    for op in ["+", "-", "*", "(", ")"] {
+        builder.string(op).unwrap();
+    }
+    Tokenizer {
+        lexer: builder.finish().unwrap(),
+        num,
+        var,
+    }
+}
+
+/// Arithmetic over tokens, written as a string with the variables in brackets.
+fn expr(tokenizer: &Tokenizer) -> impl Parser<String, Tokens> {
+    let show = |lexemes: &[Lexeme]| lexemes[0].lexeme.to_owned();
+    let num = token("number", tokenizer.num).substr(show);
+    let var =
+        token("variable", tokenizer.var).substr(move |lexemes| format!("[{}]", show(lexemes)));
+    let binary = |x, _, y| format!("({x} {y})");
+    expr_parser("expression", strnom::alt("atom", (num, var)))
+        .infix(1, Assoc::Left, text("+"), binary)
+        .infix(1, Assoc::Left, text("-"), binary)
+        .infix(2, Assoc::Left, text("*"), binary)
+        .prefix(3, text("-"), |_, x| format!("-{x}"))
+}
+
+fn parse_expr(source: &str) -> Result<String, ParseError> {
+    let tokenizer = tokenizer();
+    let lexemes = tokenizer.lexer.lex(source).collect::<Vec<_>>();
+    parse("<test>", &lexemes, expr(&tokenizer))
+}
+
+#[test]
+fn test_tokens() {
+    assert_eq!(parse_expr("1"), Ok("1".to_owned()));
+    assert_eq!(parse_expr("x + 2 * -y"), Ok("([x] (2 -[y]))".to_owned()));
+    assert_eq!(parse_expr("a-b-c"), Ok("(([a] [b]) [c])".to_owned()));
+
+    let expected = |source: &str| parse_expr(source).unwrap_err().expected().to_vec();
+    assert_eq!(expected(""), ["`-`", "number", "variable"]);
+    assert_eq!(expected("1 2"), ["`+`", "`-`", "`*`", "end of file"]);
+    assert_eq!(expected("1 ) 2"), ["`+`", "`-`", "`*`", "end of file"]);
+}
+
+#[test]
+fn test_token_error_positions() {
+    colored::control::set_override(false);
+
+    // The lexer skipped the comment, so the line is rebuilt from the lexemes without it.
+    let err = parse_expr("1 +\nx  +  @ # comment").unwrap_err();
+    assert_eq!((err.start().line, err.start().col), (1, 6));
+    assert_eq!(
+        err.to_string(),
+        "parse error: expected expression after `+`
+ --> <test>:2:7
+  |
+2 |x  +  @
+  |      ^
+  |      expected expression after `+`
+  |"
+    );
+
+    // At the end of the input, errors point just past the last lexeme.
+    let err = parse_expr("1 +\n2 *").unwrap_err();
+    assert_eq!((err.start().line, err.start().col), (1, 3));
+}
@@ -1,17 +1,29 @@
 use std::fmt;
+use std::rc::Rc;
 
 pub type Register = u32;
 
-#[derive(Debug)]
+#[derive(Debug, Clone, PartialEq)]
 pub enum Value {
     Reg(u32),
     Int(i32),
+    Float(f64),
+    /// Heap allocated, and shared between copies.
+    String(Rc<str>),
 }
 
-#[derive(Debug)]
+#[derive(Debug, Clone)]
 pub enum Instr {
     Push(Value),
     Add,
+    AddFloat,
+    Eq,
+    /// Concatenate two strings.
+    Concat,
+    /// The length of a string, in chars.
+    Len,
+    /// The char at an index of a string, as a string.
+    Index,
     GetReg,
     SetReg,
 }
@@ -26,6 +38,8 @@ impl fmt::Display for Value {
         match self {
             Reg(i) => write!(f, "${}", i),
             Int(i) => write!(f, "{}", i),
+            Float(x) => write!(f, "{:?}", x),
+            String(s) => write!(f, "{:?}", s),
         }
     }
 }
@@ -37,6 +51,11 @@ impl fmt::Display for Instr {
         match self {
             Push(v) => write!(f, "{}", v),
             Add => write!(f, "+"),
+            AddFloat => write!(f, "+."),
+            Eq => write!(f, "=="),
+            Concat => write!(f, "++"),
+            Len => write!(f, "len"),
+            Index => write!(f, "index"),
             GetReg => write!(f, "get"),
             SetReg => write!(f, "set"),
         }
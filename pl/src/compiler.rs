@@ -1,10 +1,38 @@
-use super::bytecode::Register;
-use super::expr::{Compiled, Type};
-use super::source::Src;
-use std::collections::HashMap;
+use super::bytecode::{Code, Instr, Register};
+use super::expr::{Compiled, Expr, Type};
+use super::interface::{Interface, MethodImpl, MethodSig, Param};
+use super::source::{Src, Srcloc};
+use std::collections::{BTreeMap, HashMap};
+use std::fmt;
 
 #[derive(Debug)]
-pub struct TypeError;
+pub struct TypeError {
+    pub line: usize,
+    pub column: usize,
+    pub message: String,
+}
+
+impl TypeError {
+    pub fn new(loc: Srcloc, message: String) -> TypeError {
+        TypeError {
+            line: loc.line,
+            column: loc.column,
+            message,
+        }
+    }
+}
+
+impl fmt::Display for TypeError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(
+            f,
+            "type error at {}:{}: {}",
+            self.line + 1,
+            self.column + 1,
+            self.message
+        )
+    }
+}
 
 pub type Sort = String;
 pub type Construct = String;
@@ -14,12 +42,92 @@ pub type Fragment =
 
 pub struct Registry {
     fragments: HashMap<(Sort, Construct), Fragment>,
+    interfaces: BTreeMap<String, Interface>,
+    impls: HashMap<(String, Type), HashMap<String, MethodImpl>>,
 }
 
 impl Registry {
     pub fn new() -> Registry {
         Registry {
             fragments: HashMap::new(),
+            interfaces: BTreeMap::new(),
+            impls: HashMap::new(),
+        }
+    }
+
+    /// Declare an interface. Every method must mention `Self` in its parameters, since that's
+    /// what method calls are resolved by.
+    pub fn add_interface(&mut self, name: &str, methods: Vec<MethodSig>) {
+        assert!(
+            !self.interfaces.contains_key(name),
+            "interface {} declared twice",
+            name
+        );
+        for sig in &methods {
+            assert!(
+                sig.params.contains(&Param::SelfType),
+                "method {}.{} has no Self parameter",
+                name,
+                sig.name
+            );
+        }
+        let interface = Interface {
+            name: name.to_owned(),
+            methods,
+        };
+        self.interfaces.insert(name.to_owned(), interface);
+    }
+
+    /// Implement a declared interface for `typ`, giving instructions for each of its methods.
+    pub fn add_impl(&mut self, interface: &str, typ: Type, methods: Vec<(&str, Vec<Instr>)>) {
+        let decl = match self.interfaces.get(interface) {
+            Some(decl) => decl,
+            None => panic!("impl of undeclared interface {}", interface),
+        };
+        let mut method_impls = HashMap::new();
+        for (name, instrs) in methods {
+            assert!(
+                decl.method(name).is_some(),
+                "impl {} for {}: {} is not a method of the interface",
+                interface,
+                typ,
+                name
+            );
+            method_impls.insert(name.to_owned(), MethodImpl { instrs });
+        }
+        for sig in &decl.methods {
+            assert!(
+                method_impls.contains_key(&sig.name),
+                "impl {} for {}: missing method {}",
+                interface,
+                typ,
+                sig.name
+            );
+        }
+        let prev = self.impls.insert((interface.to_owned(), typ), method_impls);
+        assert!(
+            prev.is_none(),
+            "interface {} implemented twice for {}",
+            interface,
+            typ
+        );
+    }
+
+    fn get_impl(&self, interface: &str, typ: Type, method: &str) -> Option<&MethodImpl> {
+        self.impls
+            .get(&(interface.to_owned(), typ))
+            .and_then(|methods| methods.get(method))
+    }
+
+    /// Whether `name` should be compiled as a method call: either it's qualified, like
+    /// `Interface.method`, or some declared interface has a method by that name.
+    fn is_method(&self, name: &str) -> bool {
+        match split_method_name(name) {
+            (Some(_), _) => true,
+            (None, method) => self
+                .interfaces
+                .values()
+                .any(|decl| decl.method(method).is_some()),
         }
     }
 
@@ -28,7 +136,7 @@ impl Registry {
             .insert((sort.to_owned(), con.to_owned()), fragment);
     }
 
-    fn get_fragment<'s>(&self, sort: &str, con: &str) -> Option<Fragment> {
+    fn get_fragment(&self, sort: &str, con: &str) -> Option<Fragment> {
         self.fragments
             .get(&(sort.to_owned(), con.to_owned()))
             .copied()
@@ -64,6 +172,20 @@ impl<'s, 'r> Compiler<'s, 'r> {
         self.register -= 1;
     }
 
+    /// The number of variables currently in scope.
+    pub fn num_vars(&self) -> usize {
+        self.environment.len()
+    }
+
+    /// Pop variables until only `num_vars` remain. Used to recover from errors that happen
+    /// between a `push_var` and its `pop_var`.
+    pub fn truncate_vars(&mut self, num_vars: usize) {
+        while self.environment.len() > num_vars {
+            self.environment.pop();
+            self.register -= 1;
+        }
+    }
+
     pub fn lookup_var(&self, name: &'s str) -> Option<(Type, Register)> {
         for (var_name, var_typ, var_reg) in self.environment.iter().rev() {
             if *var_name == name {
@@ -76,8 +198,210 @@ impl<'s, 'r> Compiler<'s, 'r> {
     pub fn compile(&mut self, sort: &str, src: Src<'s>) -> Result<Compiled<'s>, TypeError> {
         let fragment = match self.registry.get_fragment(sort, src.construct()) {
             Some(fragment) => fragment,
-            None => panic!("missing compiler fragment for {}", src.construct()),
+            None if sort == "Expr" && self.registry.is_method(src.construct()) => {
+                return self.compile_method_call(src);
+            }
+            None => {
+                return Err(TypeError::new(
+                    src.loc(),
+                    format!("unknown {} construct `{}`", sort, src.construct()),
+                ))
+            }
         };
         fragment(self, src)
     }
+
+    /// `($m:method $x:Expr ...)` or `($I:Interface.$m:method $x:Expr ...)`
+    fn compile_method_call(&mut self, src: Src<'s>) -> Result<Compiled<'s>, TypeError> {
+        let (interface, method) = split_method_name(src.construct());
+        let mut args = vec![];
+        for arg in src.args() {
+            args.push(self.compile("Expr", *arg)?.into_expr());
+        }
+        let expr = self.call_method(src.loc(), interface, method, args)?;
+        Ok(Compiled::Expr(expr))
+    }
+
+    /// Compile a call to an interface method, resolving its implementation from the type of the
+    /// `Self` argument. If `interface` is `None`, every interface with a method of that name is
+    /// considered, and it is an error for more than one of them to be implemented.
+    pub fn call_method(
+        &self,
+        loc: Srcloc<'s>,
+        interface: Option<&str>,
+        method: &str,
+        args: Vec<Expr<'s>>,
+    ) -> Result<Expr<'s>, TypeError> {
+        let registry = self.registry;
+        let arg_types = args.iter().map(|arg| arg.typ).collect::<Vec<_>>();
+
+        let candidates = registry
+            .interfaces
+            .values()
+            .filter(|decl| interface.is_none_or(|iface| decl.name == iface))
+            .filter_map(|decl| decl.method(method).map(|sig| (decl, sig)))
+            .collect::<Vec<_>>();
+        if candidates.is_empty() {
+            return Err(match interface {
+                Some(iface) if !registry.interfaces.contains_key(iface) => {
+                    TypeError::new(loc, format!("unknown interface `{}`", iface))
+                }
+                Some(iface) => TypeError::new(
+                    loc,
+                    format!("interface `{}` has no method `{}`", iface, method),
+                ),
+                None => TypeError::new(loc, format!("unknown method `{}`", method)),
+            });
+        }
+
+        let mut found = vec![];
+        for (decl, sig) in &candidates {
+            if let Some(self_type) = sig.self_type(&arg_types) {
+                if let Some(imp) = registry.get_impl(&decl.name, self_type, method) {
+                    found.push((*decl, *sig, self_type, imp));
+                }
+            }
+        }
+        let (decl, sig, self_type, imp) = match found.len() {
+            1 => found.pop().unwrap(),
+            0 => {
+                let (decl, sig) = candidates[0];
+                return match sig.self_type(&arg_types) {
+                    None => Err(arity_error(loc, method, sig, args.len())),
+                    Some(self_type) if candidates.len() == 1 => Err(TypeError::new(
+                        loc,
+                        format!(
+                            "`{}` does not implement `{}`, which is needed for `{}`",
+                            self_type, decl.name, method
+                        ),
+                    )),
+                    Some(self_type) => Err(TypeError::new(
+                        loc,
+                        format!(
+                            "no implementation of method `{}` for `{}`",
+                            method, self_type
+                        ),
+                    )),
+                };
+            }
+            _ => {
+                let names = found
+                    .iter()
+                    .map(|(decl, _, _, _)| format!("`{}`", decl.name))
+                    .collect::<Vec<_>>();
+                return Err(TypeError::new(
+                    loc,
+                    format!(
+                        "ambiguous method `{}`: it is implemented by {}; write e.g. `{}.{}` to pick one",
+                        method,
+                        names.join(" and "),
+                        found[0].0.name,
+                        method
+                    ),
+                ));
+            }
+        };
+
+        if sig.params.len() != args.len() {
+            return Err(arity_error(loc, method, sig, args.len()));
+        }
+        let mut code = Code(vec![]);
+        for (param, mut arg) in sig.params.iter().zip(args) {
+            let expected = param_type(*param, self_type);
+            if arg.typ != expected {
+                return Err(TypeError::new(
+                    arg.loc,
+                    format!(
+                        "argument to `{}.{}` should be `{}`, but is `{}`",
+                        decl.name, method, expected, arg.typ
+                    ),
+                ));
+            }
+            code.0.append(&mut arg.code.0);
+        }
+        code.0.extend(imp.instrs.iter().cloned());
+
+        Ok(Expr {
+            loc,
+            typ: param_type(sig.ret, self_type),
+            code,
+        })
+    }
+}
+
+fn split_method_name(name: &str) -> (Option<&str>, &str) {
+    match name.split_once('.') {
+        Some((interface, method)) => (Some(interface), method),
+        None => (None, name),
+    }
+}
+
+fn param_type(param: Param, self_type: Type) -> Type {
+    match param {
+        Param::SelfType => self_type,
+        Param::Type(typ) => typ,
+    }
+}
+
+fn arity_error(loc: Srcloc, method: &str, sig: &MethodSig, num_args: usize) -> TypeError {
+    TypeError::new(
+        loc,
+        format!(
+            "`{}` expects {} arguments, but was given {}",
+            method,
+            sig.params.len(),
+            num_args
+        ),
+    )
+}
+
+#[test]
+fn test_interface_dispatch() {
+    use super::sexpr::parse_sexpr;
+    use typed_arena::Arena;
+
+    let mut registry = super::std_registry();
+    registry.add_interface(
+        "Plus",
+        vec![MethodSig::new(
+            "add",
+            &[Param::SelfType, Param::SelfType],
+            Param::SelfType,
+        )],
+    );
+    registry.add_interface(
+        "Neg",
+        vec![MethodSig::new("neg", &[Param::SelfType], Param::SelfType)],
+    );
+    registry.add_impl("Plus", Type::Int, vec![("add", vec![Instr::Add])]);
+
+    let compile = |source: &str| {
+        let arena = Arena::new();
+        let src = parse_sexpr(&arena, source).unwrap();
+        let mut compiler = Compiler::new(&registry);
+        compiler
+            .compile("Expr", src)
+            .map(|compiled| compiled.into_expr().code.to_string())
+            .map_err(|err| err.message)
+    };
+
+    assert_eq!(compile("(+ 1 2)").unwrap(), "[1 2 +]");
+    assert_eq!(compile("(Add.add 1 2)").unwrap(), "[1 2 +]");
+    assert_eq!(compile("(eq (+ 1 2) 3)").unwrap(), "[1 2 + 3 ==]");
+    assert_eq!(
+        compile("(add 1 2)").unwrap_err(),
+        "ambiguous method `add`: it is implemented by `Add` and `Plus`; write e.g. `Add.add` to pick one"
+    );
+    assert_eq!(
+        compile("(neg 1)").unwrap_err(),
+        "`Int` does not implement `Neg`, which is needed for `neg`"
+    );
+    assert_eq!(
+        compile("(Add.add 1)").unwrap_err(),
+        "`add` expects 2 arguments, but was given 1"
+    );
+    assert_eq!(
+        compile("(Add.sub 1 2)").unwrap_err(),
+        "interface `Add` has no method `sub`"
+    );
 }
@@ -0,0 +1,147 @@
+use super::bytecode::{Code, Instr, Value};
+use super::compiler::{Compiler, Registry, TypeError};
+use super::sexpr::{parse_sexpr, ParseError};
+use super::source::Src;
+use super::vm::{RuntimeError, Vm};
+use std::fmt;
+use typed_arena::Arena;
+
+#[derive(Debug)]
+pub enum Error {
+    Parse(ParseError),
+    Type(TypeError),
+    Runtime(RuntimeError),
+}
+
+impl From<ParseError> for Error {
+    fn from(err: ParseError) -> Error {
+        Error::Parse(err)
+    }
+}
+
+impl From<TypeError> for Error {
+    fn from(err: TypeError) -> Error {
+        Error::Type(err)
+    }
+}
+
+impl From<RuntimeError> for Error {
+    fn from(err: RuntimeError) -> Error {
+        Error::Runtime(err)
+    }
+}
+
+impl fmt::Display for Error {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            Error::Parse(err) => write!(f, "{}", err),
+            Error::Type(err) => write!(f, "{}", err),
+            Error::Runtime(err) => write!(f, "{}", err),
+        }
+    }
+}
+
+/// Parse and compile a single s-expression.
+pub fn compile_source(registry: &Registry, source: &str) -> Result<Code, Error> {
+    let arena = Arena::new();
+    let src = parse_sexpr(&arena, source)?;
+    let mut compiler = Compiler::new(registry);
+    Ok(compiler.compile("Expr", src)?.into_expr().code)
+}
+
+/// A read-eval-print loop. Each line is an expression, or a top-level `(let $v:id $x:Expr)`
+/// whose binding stays in scope for all following lines.
+pub struct Repl<'s, 'r> {
+    lines: &'s Arena<String>,
+    srcs: &'s Arena<Vec<Src<'s>>>,
+    compiler: Compiler<'s, 'r>,
+    vm: Vm,
+}
+
+impl<'s, 'r> Repl<'s, 'r> {
+    pub fn new(
+        registry: &'r Registry,
+        lines: &'s Arena<String>,
+        srcs: &'s Arena<Vec<Src<'s>>>,
+    ) -> Repl<'s, 'r> {
+        Repl {
+            lines,
+            srcs,
+            compiler: Compiler::new(registry),
+            vm: Vm::new(),
+        }
+    }
+
+    /// Evaluate one line, returning the value it produced. Blank lines and top-level `let`s
+    /// produce no value.
+    pub fn eval(&mut self, line: String) -> Result<Option<Value>, Error> {
+        let line = self.lines.alloc(line);
+        if line.trim().is_empty() {
+            return Ok(None);
+        }
+        let src = parse_sexpr(self.srcs, line)?;
+
+        let num_vars = self.compiler.num_vars();
+        let code = match self.compile_line(src) {
+            Ok(code) => code,
+            Err(err) => {
+                self.compiler.truncate_vars(num_vars);
+                return Err(err.into());
+            }
+        };
+        let value = match self.vm.run(&code) {
+            Ok(value) => value,
+            Err(err) => {
+                // Don't leave behind a variable whose register was never set.
+                self.compiler.truncate_vars(num_vars);
+                return Err(err.into());
+            }
+        };
+        if src.construct() == "let" && src.args().len() == 2 {
+            Ok(None)
+        } else {
+            Ok(value)
+        }
+    }
+
+    fn compile_line(&mut self, src: Src<'s>) -> Result<Code, TypeError> {
+        if src.construct() == "let" && src.args().len() == 2 {
+            let expr = self.compiler.compile("Expr", src.args()[1])?.into_expr();
+            let name = self.compiler.compile("Pattern", src.args()[0])?.into_id();
+            // Deliberately never popped: the variable lives for the rest of the session.
+            let reg = self.compiler.push_var(name, expr.typ);
+            let mut code = expr.code;
+            code.0.push(Instr::Push(Value::Reg(reg)));
+            code.0.push(Instr::SetReg);
+            Ok(code)
+        } else {
+            Ok(self.compiler.compile("Expr", src)?.into_expr().code)
+        }
+    }
+}
+
+#[test]
+fn test_repl() {
+    let registry = super::std_registry();
+    let lines = Arena::new();
+    let srcs = Arena::new();
+    let mut repl = Repl::new(&registry, &lines, &srcs);
+
+    let mut eval = |line: &str| repl.eval(line.to_owned());
+    assert_eq!(eval("(let x 1)").unwrap(), None);
+    assert_eq!(eval("(let y (+ x 2))").unwrap(), None);
+    assert_eq!(eval("(+ x y)").unwrap(), Some(Value::Int(4)));
+    assert!(eval("(let z (+ x w))").is_err());
+    assert!(eval("(let x").is_err());
+    assert_eq!(eval("(let x 10 (+ x y))").unwrap(), Some(Value::Int(13)));
+    assert_eq!(eval("x").unwrap(), Some(Value::Int(1)));
+    assert_eq!(eval("  ").unwrap(), None);
+    assert!(eval("(let s (index \"ab\" 2))").is_err());
+    assert!(eval("s").is_err());
+    assert_eq!(eval("(let s \"ab\")").unwrap(), None);
+    assert_eq!(
+        eval("(+ s (index s 0))").unwrap(),
+        Some(Value::String("aba".into()))
+    );
+    assert_eq!(eval(r#"(len "a\\")"#).unwrap(), Some(Value::Int(2)));
+}
@@ -1,5 +1,6 @@
 use super::bytecode::Code;
 use super::source::Srcloc;
+use std::fmt;
 
 #[derive(Debug)]
 pub enum Compiled<'s> {
@@ -23,9 +24,21 @@ impl<'s> Compiled<'s> {
     }
 }
 
-#[derive(Debug, Clone, Copy)]
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
 pub enum Type {
     Int,
+    Float,
+    String,
+}
+
+impl fmt::Display for Type {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            Type::Int => write!(f, "Int"),
+            Type::Float => write!(f, "Float"),
+            Type::String => write!(f, "String"),
+        }
+    }
 }
 
 #[derive(Debug)]
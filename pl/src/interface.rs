@@ -0,0 +1,59 @@
+use super::bytecode::Instr;
+use super::expr::Type;
+
+/// A parameter or return type in a method signature.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Param {
+    /// The implementing type.
+    SelfType,
+    Type(Type),
+}
+
+/// The signature of one method of an interface, e.g. `add: Self, Self -> Self`.
+#[derive(Debug, Clone)]
+pub struct MethodSig {
+    pub name: String,
+    pub params: Vec<Param>,
+    pub ret: Param,
+}
+
+impl MethodSig {
+    pub fn new(name: &str, params: &[Param], ret: Param) -> MethodSig {
+        MethodSig {
+            name: name.to_owned(),
+            params: params.to_vec(),
+            ret,
+        }
+    }
+
+    /// The type that `Self` must be, judging by the arguments. `None` if no parameter mentions
+    /// `Self` (or there are too few arguments to tell).
+    pub fn self_type(&self, arg_types: &[Type]) -> Option<Type> {
+        self.params
+            .iter()
+            .zip(arg_types)
+            .find(|(param, _)| **param == Param::SelfType)
+            .map(|(_, typ)| *typ)
+    }
+}
+
+/// A set of methods that a `Type` can implement. Method calls are resolved at compile time, by
+/// looking up the implementation for the type of the `Self` argument.
+#[derive(Debug)]
+pub struct Interface {
+    pub name: String,
+    pub methods: Vec<MethodSig>,
+}
+
+impl Interface {
+    pub fn method(&self, name: &str) -> Option<&MethodSig> {
+        self.methods.iter().find(|sig| sig.name == name)
+    }
+}
+
+/// One method's implementation: the instructions to run after all of its arguments have been
+/// pushed onto the stack.
+#[derive(Debug)]
+pub struct MethodImpl {
+    pub instrs: Vec<Instr>,
+}
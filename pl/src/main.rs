@@ -1,24 +1,45 @@
 mod bytecode;
 mod compiler;
+mod driver;
 mod expr;
+mod interface;
 mod sexpr;
 mod source;
+mod vm;
 
 use bytecode::{Code, Instr, Value};
 use compiler::{Compiler, Registry, TypeError};
+use driver::{compile_source, Repl};
 use expr::{Compiled, Expr, Type};
+use interface::{MethodSig, Param};
 use source::Src;
+use std::io::{self, BufRead, IsTerminal, Read, Write};
+use std::{env, fs, process};
+use vm::Vm;
 
 macro_rules! type_err {
     ($loc:expr, $fmt_str:expr $( , $args:expr )*) => {
-        return Err(TypeError)
+        return Err(TypeError::new($loc, format!($fmt_str $( , $args )*)))
     };
 }
 
+fn expect_args<'s>(src: Src<'s>, arity: usize) -> Result<&'s [Src<'s>], TypeError> {
+    if src.args().len() != arity {
+        type_err!(
+            src.loc(),
+            "`{}` expects {} arguments, but was given {}",
+            src.construct(),
+            arity,
+            src.args().len()
+        );
+    }
+    Ok(src.args())
+}
+
 fn compile_int<'s>(_comp: &mut Compiler, src: Src<'s>) -> Result<Compiled<'s>, TypeError> {
     let n: i32 = match src.as_str().parse::<i32>() {
         Ok(n) => n,
-        Err(_) => type_err!(loc, "bad int"),
+        Err(_) => type_err!(src.loc(), "bad int {}", src.as_str()),
     };
     Ok(Compiled::Expr(Expr {
         loc: src.loc(),
@@ -27,31 +48,107 @@ fn compile_int<'s>(_comp: &mut Compiler, src: Src<'s>) -> Result<Compiled<'s>, T
     }))
 }
 
-/// $x:Expr + $y:Expr
-fn compile_add<'s>(comp: &mut Compiler<'s, '_>, src: Src<'s>) -> Result<Compiled<'s>, TypeError> {
-    let loc = src.loc();
-    let x = comp.compile("Expr", src.args()[0])?.into_expr();
-    let mut y = comp.compile("Expr", src.args()[1])?.into_expr();
-
-    let typ = match (x.typ, y.typ) {
-        (Type::Int, Type::Int) => Type::Int,
-        // (_, _) => type_err!(loc, "Adding non ints {} {}", x.typ, y.typ),
+fn compile_float<'s>(_comp: &mut Compiler, src: Src<'s>) -> Result<Compiled<'s>, TypeError> {
+    let x: f64 = match src.as_str().parse::<f64>() {
+        Ok(x) => x,
+        Err(_) => type_err!(src.loc(), "bad float {}", src.as_str()),
     };
+    Ok(Compiled::Expr(Expr {
+        loc: src.loc(),
+        typ: Type::Float,
+        code: Code(vec![Instr::Push(Value::Float(x))]),
+    }))
+}
+
+fn compile_string<'s>(_comp: &mut Compiler, src: Src<'s>) -> Result<Compiled<'s>, TypeError> {
+    let literal = src.as_str();
+    let mut string = String::new();
+    let mut chars = literal[1..literal.len() - 1].chars();
+    while let Some(ch) = chars.next() {
+        if ch != '\\' {
+            string.push(ch);
+            continue;
+        }
+        match chars.next() {
+            Some('n') => string.push('\n'),
+            Some('t') => string.push('\t'),
+            Some('r') => string.push('\r'),
+            Some('0') => string.push('\0'),
+            Some(ch @ ('\\' | '"')) => string.push(ch),
+            Some(ch) => type_err!(src.loc(), "unknown escape sequence \\{}", ch),
+            None => type_err!(src.loc(), "unterminated escape sequence"),
+        }
+    }
+    Ok(Compiled::Expr(Expr {
+        loc: src.loc(),
+        typ: Type::String,
+        code: Code(vec![Instr::Push(Value::String(string.into()))]),
+    }))
+}
 
-    let mut code = x.code;
-    code.0.append(&mut y.code.0);
-    code.0.push(Instr::Add);
+/// (len $s:Expr)
+fn compile_len<'s>(comp: &mut Compiler<'s, '_>, src: Src<'s>) -> Result<Compiled<'s>, TypeError> {
+    let args = expect_args(src, 1)?;
+    let s = comp.compile("Expr", args[0])?.into_expr();
+    if s.typ != Type::String {
+        type_err!(s.loc, "`len` expects a String, but was given `{}`", s.typ);
+    }
 
-    Ok(Compiled::Expr(Expr { loc, typ, code }))
+    let mut code = s.code;
+    code.0.push(Instr::Len);
+
+    Ok(Compiled::Expr(Expr {
+        loc: src.loc(),
+        typ: Type::Int,
+        code,
+    }))
+}
+
+/// (index $s:Expr $i:Expr)
+fn compile_index<'s>(comp: &mut Compiler<'s, '_>, src: Src<'s>) -> Result<Compiled<'s>, TypeError> {
+    let args = expect_args(src, 2)?;
+    let s = comp.compile("Expr", args[0])?.into_expr();
+    let mut i = comp.compile("Expr", args[1])?.into_expr();
+    if s.typ != Type::String {
+        type_err!(s.loc, "`index` expects a String, but was given `{}`", s.typ);
+    }
+    if i.typ != Type::Int {
+        type_err!(
+            i.loc,
+            "`index` expects an Int index, but was given `{}`",
+            i.typ
+        );
+    }
+
+    let mut code = s.code;
+    code.0.append(&mut i.code.0);
+    code.0.push(Instr::Index);
+
+    Ok(Compiled::Expr(Expr {
+        loc: src.loc(),
+        typ: Type::String,
+        code,
+    }))
+}
+
+/// $x:Expr + $y:Expr
+fn compile_add<'s>(comp: &mut Compiler<'s, '_>, src: Src<'s>) -> Result<Compiled<'s>, TypeError> {
+    let loc = src.loc();
+    let args = expect_args(src, 2)?;
+    let x = comp.compile("Expr", args[0])?.into_expr();
+    let y = comp.compile("Expr", args[1])?.into_expr();
+    let expr = comp.call_method(loc, Some("Add"), "add", vec![x, y])?;
+    Ok(Compiled::Expr(expr))
 }
 
 /// let $v:id = $x:Expr in $b:Expr
 fn compile_let<'s>(comp: &mut Compiler<'s, '_>, src: Src<'s>) -> Result<Compiled<'s>, TypeError> {
     let loc = src.loc();
-    let expr = comp.compile("Expr", src.args()[1])?.into_expr();
-    let name = comp.compile("Pattern", src.args()[0])?.into_id();
+    let args = expect_args(src, 3)?;
+    let expr = comp.compile("Expr", args[1])?.into_expr();
+    let name = comp.compile("Pattern", args[0])?.into_id();
     let reg = comp.push_var(name, expr.typ);
-    let mut body = comp.compile("Expr", src.args()[2])?.into_expr();
+    let mut body = comp.compile("Expr", args[2])?.into_expr();
     comp.pop_var(name, reg);
 
     let mut code = expr.code;
@@ -87,10 +184,36 @@ fn compile_pattern_id<'s>(_comp: &mut Compiler, src: Src<'s>) -> Result<Compiled
 }
 
 fn std_registry() -> Registry {
+    use Param::SelfType;
+
     let mut registry = Registry::new();
 
+    // Interfaces
+    registry.add_interface(
+        "Add",
+        vec![MethodSig::new("add", &[SelfType, SelfType], SelfType)],
+    );
+    registry.add_interface(
+        "Eq",
+        vec![MethodSig::new(
+            "eq",
+            &[SelfType, SelfType],
+            Param::Type(Type::Int),
+        )],
+    );
+    registry.add_impl("Add", Type::Int, vec![("add", vec![Instr::Add])]);
+    registry.add_impl("Add", Type::Float, vec![("add", vec![Instr::AddFloat])]);
+    registry.add_impl("Add", Type::String, vec![("add", vec![Instr::Concat])]);
+    for typ in [Type::Int, Type::Float, Type::String] {
+        registry.add_impl("Eq", typ, vec![("eq", vec![Instr::Eq])]);
+    }
+
     // Exprs
     registry.add_fragment("Expr", "int", compile_int);
+    registry.add_fragment("Expr", "float", compile_float);
+    registry.add_fragment("Expr", "string", compile_string);
+    registry.add_fragment("Expr", "len", compile_len);
+    registry.add_fragment("Expr", "index", compile_index);
     registry.add_fragment("Expr", "+", compile_add);
     registry.add_fragment("Expr", "id", compile_id);
     registry.add_fragment("Expr", "let", compile_let);
@@ -101,24 +224,130 @@ fn std_registry() -> Registry {
     registry
 }
 
+const USAGE: &str = "\
+usage: pl [--emit | --run | --repl] [FILE...]
+
+Compile each FILE, an s-expression, and either print its bytecode (--emit) or
+execute it and print the result (--run, the default). A FILE of `-`, or no FILE
+at all, reads from stdin. With --repl, or with no FILE when stdin is a
+terminal, start an interactive session in which `(let x EXPR)` keeps `x` bound
+for later lines.";
+
+enum Mode {
+    Emit,
+    Run,
+    Repl,
+}
+
 fn main() {
-    use sexpr::parse_sexpr;
-    use typed_arena::Arena;
+    let mut mode = None;
+    let mut files = vec![];
+    for arg in env::args().skip(1) {
+        match arg.as_str() {
+            "--emit" => mode = Some(Mode::Emit),
+            "--run" => mode = Some(Mode::Run),
+            "--repl" => mode = Some(Mode::Repl),
+            "-h" | "--help" => {
+                println!("{}", USAGE);
+                return;
+            }
+            _ if arg.starts_with("--") => {
+                eprintln!("pl: unknown option {}\n\n{}", arg, USAGE);
+                process::exit(2);
+            }
+            _ => files.push(arg),
+        }
+    }
+    let mode = match mode {
+        Some(mode) => mode,
+        None if files.is_empty() && io::stdin().is_terminal() => Mode::Repl,
+        None => Mode::Run,
+    };
 
     let registry = std_registry();
-    let mut compiler = Compiler::new(&registry);
+    if let Mode::Repl = mode {
+        if !files.is_empty() {
+            eprintln!("pl: --repl does not take files\n\n{}", USAGE);
+            process::exit(2);
+        }
+        run_repl(&registry);
+        return;
+    }
 
-    let arena = Arena::new();
-    let source = "(+ (+ 1 2) 3)";
-    let expr = parse_sexpr(&arena, source).unwrap();
-    let expr = compiler.compile("Expr", expr).unwrap().into_expr();
-    println!("{}", expr.code);
+    if files.is_empty() {
+        files.push("-".to_owned());
+    }
+    let mut failed = false;
+    for file in &files {
+        let source = match read_file(file) {
+            Ok(source) => source,
+            Err(err) => {
+                eprintln!("pl: {}: {}", file, err);
+                failed = true;
+                continue;
+            }
+        };
+        let code = match compile_source(&registry, &source) {
+            Ok(code) => code,
+            Err(err) => {
+                eprintln!("pl: {}: {}", file, err);
+                failed = true;
+                continue;
+            }
+        };
+        match mode {
+            Mode::Emit => println!("{}", code),
+            Mode::Run => match Vm::new().run(&code) {
+                Ok(Some(value)) => println!("{}", value),
+                Ok(None) => (),
+                Err(err) => {
+                    eprintln!("pl: {}: {}", file, err);
+                    failed = true;
+                }
+            },
+            Mode::Repl => unreachable!(),
+        }
+    }
+    if failed {
+        process::exit(1);
+    }
+}
 
-    let arena = Arena::new();
-    let source = "(let x 1 (+ x 2))";
-    let expr = parse_sexpr(&arena, source).unwrap();
-    let expr = compiler.compile("Expr", expr).unwrap().into_expr();
-    println!("{}", expr.code);
+fn read_file(file: &str) -> io::Result<String> {
+    if file == "-" {
+        let mut source = String::new();
+        io::stdin().read_to_string(&mut source)?;
+        Ok(source)
+    } else {
+        fs::read_to_string(file)
+    }
+}
+
+fn run_repl(registry: &Registry) {
+    use typed_arena::Arena;
 
-    println!("ok");
+    let lines = Arena::new();
+    let srcs = Arena::new();
+    let mut repl = Repl::new(registry, &lines, &srcs);
+    let stdin = io::stdin();
+    let mut input = stdin.lock();
+    loop {
+        print!("> ");
+        io::stdout().flush().unwrap();
+        let mut line = String::new();
+        match input.read_line(&mut line) {
+            Ok(0) => break,
+            Ok(_) => (),
+            Err(err) => {
+                eprintln!("pl: {}", err);
+                break;
+            }
+        }
+        match repl.eval(line) {
+            Ok(Some(value)) => println!("{}", value),
+            Ok(None) => (),
+            Err(err) => eprintln!("{}", err),
+        }
+    }
+    println!();
 }
@@ -1,4 +1,5 @@
 use super::source::{Src, Srcloc};
+use std::fmt;
 use std::iter::Peekable;
 use std::str;
 use typed_arena::Arena;
@@ -10,6 +11,22 @@ pub enum ParseError {
     UnclosedParen,
     UnclosedString,
     MissingConstruct,
+    TrailingInput,
+}
+
+impl fmt::Display for ParseError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        use ParseError::*;
+
+        match self {
+            EmptyFile => write!(f, "parse error: empty input"),
+            UnopenedParen => write!(f, "parse error: unopened paren"),
+            UnclosedParen => write!(f, "parse error: unclosed paren"),
+            UnclosedString => write!(f, "parse error: unclosed string"),
+            MissingConstruct => write!(f, "parse error: missing construct name after paren"),
+            TrailingInput => write!(f, "parse error: unexpected input after expression"),
+        }
+    }
 }
 
 pub fn parse_sexpr<'s>(
@@ -17,7 +34,12 @@ pub fn parse_sexpr<'s>(
     source: &'s str,
 ) -> Result<Src<'s>, ParseError> {
     let mut parser = SExprParser::new(arena, source);
-    parser.parse()
+    let expr = parser.parse()?;
+    parser.consume_whitespace();
+    if parser.peek_char().is_some() {
+        return Err(ParseError::TrailingInput);
+    }
+    Ok(expr)
 }
 
 struct SExprParser<'s> {
@@ -98,9 +120,7 @@ impl<'s> SExprParser<'s> {
                     });
                 }
                 '\\' => {
-                    if !is_escaped {
-                        is_escaped = true;
-                    }
+                    is_escaped = !is_escaped;
                 }
                 _ => {
                     is_escaped = false;
@@ -137,7 +157,7 @@ impl<'s> SExprParser<'s> {
         let start_loc = self.srcloc_start();
         while let Some(ch) = self.peek_char() {
             match ch {
-                ' ' | '\t' | '\r' | '\n' => break,
+                ' ' | '\t' | '\r' | '\n' | '(' | ')' => break,
                 _ => self.consume_char(),
             }
         }
@@ -227,4 +247,10 @@ fn test_sexpr_parser() {
     assert_eq!(expr.loc.line, 0);
     assert_eq!(expr.loc.column, 1);
     assert_eq!(expr.construct, "string");
+
+    let arena = Arena::new();
+    let source = r#"(len "a\\")"#;
+    let expr = parse_sexpr(&arena, source).unwrap();
+    assert_eq!(expr.args[0].loc.source, r#""a\\""#);
+    assert_eq!(expr.args[0].construct, "string");
 }
@@ -0,0 +1,181 @@
+use super::bytecode::{Code, Instr, Value};
+use std::fmt;
+use std::rc::Rc;
+
+/// A stack machine that executes compiled `Code`.
+///
+/// Registers outlive a single call to `run`, so that a REPL can keep variables alive between
+/// lines.
+pub struct Vm {
+    stack: Vec<Value>,
+    registers: Vec<Option<Value>>,
+}
+
+/// An error caused by the program being run, like indexing past the end of a string.
+#[derive(Debug)]
+pub struct RuntimeError {
+    pub message: String,
+}
+
+impl fmt::Display for RuntimeError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "runtime error: {}", self.message)
+    }
+}
+
+impl Vm {
+    pub fn new() -> Vm {
+        Vm {
+            stack: vec![],
+            registers: vec![],
+        }
+    }
+
+    /// Execute `code`, returning the value left on top of the stack (if any).
+    ///
+    /// Panics if the code is malformed (which the compiler should never produce).
+    pub fn run(&mut self, code: &Code) -> Result<Option<Value>, RuntimeError> {
+        use Instr::*;
+
+        self.stack.clear();
+        for instr in &code.0 {
+            match instr {
+                Push(value) => self.stack.push(value.clone()),
+                Add => {
+                    let y = self.pop_int();
+                    let x = self.pop_int();
+                    self.stack.push(Value::Int(x.wrapping_add(y)));
+                }
+                AddFloat => {
+                    let y = self.pop_float();
+                    let x = self.pop_float();
+                    self.stack.push(Value::Float(x + y));
+                }
+                Eq => {
+                    let y = self.pop();
+                    let x = self.pop();
+                    self.stack.push(Value::Int((x == y) as i32));
+                }
+                Concat => {
+                    let y = self.pop_string();
+                    let x = self.pop_string();
+                    let mut string = String::with_capacity(x.len() + y.len());
+                    string.push_str(&x);
+                    string.push_str(&y);
+                    self.stack.push(Value::String(Rc::from(string)));
+                }
+                Len => {
+                    let string = self.pop_string();
+                    let len = string.chars().count();
+                    self.stack.push(Value::Int(len as i32));
+                }
+                Index => {
+                    let index = self.pop_int();
+                    let string = self.pop_string();
+                    let ch = usize::try_from(index)
+                        .ok()
+                        .and_then(|i| string.chars().nth(i));
+                    let ch = match ch {
+                        Some(ch) => ch,
+                        None => {
+                            return Err(RuntimeError {
+                                message: format!(
+                                    "index {} out of bounds for string of length {}",
+                                    index,
+                                    string.chars().count()
+                                ),
+                            })
+                        }
+                    };
+                    self.stack.push(Value::String(Rc::from(ch.to_string())));
+                }
+                GetReg => {
+                    let reg = self.pop_reg();
+                    let value = match self.registers.get(reg as usize) {
+                        Some(Some(value)) => value.clone(),
+                        _ => panic!("vm: read from uninitialized register ${}", reg),
+                    };
+                    self.stack.push(value);
+                }
+                SetReg => {
+                    let reg = self.pop_reg() as usize;
+                    let value = self.pop();
+                    if reg >= self.registers.len() {
+                        self.registers.resize(reg + 1, None);
+                    }
+                    self.registers[reg] = Some(value);
+                }
+            }
+        }
+        Ok(self.stack.pop())
+    }
+
+    fn pop(&mut self) -> Value {
+        match self.stack.pop() {
+            Some(value) => value,
+            None => panic!("vm: stack underflow"),
+        }
+    }
+
+    fn pop_int(&mut self) -> i32 {
+        match self.pop() {
+            Value::Int(n) => n,
+            value => panic!("vm: expected int, found {}", value),
+        }
+    }
+
+    fn pop_float(&mut self) -> f64 {
+        match self.pop() {
+            Value::Float(x) => x,
+            value => panic!("vm: expected float, found {}", value),
+        }
+    }
+
+    fn pop_string(&mut self) -> Rc<str> {
+        match self.pop() {
+            Value::String(s) => s,
+            value => panic!("vm: expected string, found {}", value),
+        }
+    }
+
+    fn pop_reg(&mut self) -> u32 {
+        match self.pop() {
+            Value::Reg(reg) => reg,
+            value => panic!("vm: expected register, found {}", value),
+        }
+    }
+}
+
+#[test]
+fn test_vm() {
+    let mut vm = Vm::new();
+    let code = Code(vec![
+        Instr::Push(Value::Int(1)),
+        Instr::Push(Value::Reg(0)),
+        Instr::SetReg,
+        Instr::Push(Value::Reg(0)),
+        Instr::GetReg,
+        Instr::Push(Value::Int(2)),
+        Instr::Add,
+    ]);
+    assert_eq!(vm.run(&code).unwrap(), Some(Value::Int(3)));
+
+    let code = Code(vec![Instr::Push(Value::Reg(0)), Instr::GetReg]);
+    assert_eq!(vm.run(&code).unwrap(), Some(Value::Int(1)));
+
+    let code = Code(vec![
+        Instr::Push(Value::String(Rc::from("héllo"))),
+        Instr::Push(Value::String(Rc::from("!"))),
+        Instr::Concat,
+        Instr::Push(Value::Int(1)),
+        Instr::Index,
+    ]);
+    assert_eq!(vm.run(&code).unwrap(), Some(Value::String(Rc::from("é"))));
+
+    let code = Code(vec![
+        Instr::Push(Value::String(Rc::from("ab"))),
+        Instr::Push(Value::Int(2)),
+        Instr::Index,
+    ]);
+    assert!(vm.run(&code).is_err());
+}